@@ -0,0 +1,7 @@
+-- Anonymous sessions can't satisfy the original NOT NULL user_id
+DELETE FROM user_sessions WHERE user_id IS NULL;
+
+DROP INDEX IF EXISTS idx_user_sessions_expires_at;
+
+ALTER TABLE user_sessions DROP COLUMN data;
+ALTER TABLE user_sessions ALTER COLUMN user_id SET NOT NULL;
@@ -0,0 +1,7 @@
+-- Back tower-sessions with the user_sessions table.
+-- Sessions exist before login (e.g. during the OAuth round trip), so user_id is optional
+-- and filled in from the session data once the user is authenticated.
+ALTER TABLE user_sessions ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE user_sessions ADD COLUMN data JSONB NOT NULL DEFAULT '{}';
+
+CREATE INDEX idx_user_sessions_expires_at ON user_sessions(expires_at);
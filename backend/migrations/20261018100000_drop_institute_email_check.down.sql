@@ -0,0 +1,2 @@
+-- Fails if users outside the original domains have signed in since
+ALTER TABLE users ADD CONSTRAINT check_institute_email CHECK (email ~* '@(students\.)?iitmandi\.ac\.in$');
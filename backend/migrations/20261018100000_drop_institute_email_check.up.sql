@@ -0,0 +1,2 @@
+-- Allowed sign-in domains come from configuration (ALLOWED_EMAIL_DOMAINS) and are checked at login
+ALTER TABLE users DROP CONSTRAINT IF EXISTS check_institute_email;
@@ -0,0 +1,2 @@
+-- Bumped on every request that carries the session, for the active sessions list
+ALTER TABLE user_sessions ADD COLUMN last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS role_permissions;
+DROP TYPE IF EXISTS permission_scope;
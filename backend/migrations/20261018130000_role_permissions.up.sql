@@ -0,0 +1,72 @@
+-- Role-to-permission grants, editable by admins at runtime.
+-- The scope limits a grant to records related to the user:
+--   any        - every record
+--   own        - records the user submitted / posted / is the subject of
+--   assigned   - grievances assigned to the user
+--   unassigned - grievances not yet assigned to anyone
+CREATE TYPE permission_scope AS ENUM ('any', 'own', 'assigned', 'unassigned');
+
+CREATE TABLE role_permissions (
+    role user_role NOT NULL,
+    permission VARCHAR(64) NOT NULL,
+    scope permission_scope NOT NULL DEFAULT 'any',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (role, permission, scope)
+);
+
+-- Defaults reproduce the rules previously hardcoded in the handlers
+INSERT INTO role_permissions (role, permission, scope) VALUES
+    ('student', 'grievance.create', 'any'),
+    ('faculty', 'grievance.create', 'any'),
+
+    ('student', 'grievance.view', 'any'),
+    ('faculty', 'grievance.view', 'assigned'),
+    ('authority', 'grievance.view', 'any'),
+    ('admin', 'grievance.view', 'any'),
+
+    ('student', 'grievance.edit', 'own'),
+    ('authority', 'grievance.edit', 'assigned'),
+    ('authority', 'grievance.edit', 'unassigned'),
+    ('admin', 'grievance.edit', 'any'),
+
+    ('student', 'grievance.delete', 'own'),
+    ('admin', 'grievance.delete', 'any'),
+
+    ('authority', 'grievance.update_status', 'any'),
+    ('admin', 'grievance.update_status', 'any'),
+    ('authority', 'grievance.assign', 'any'),
+    ('admin', 'grievance.assign', 'any'),
+    ('authority', 'grievance.resolve', 'any'),
+    ('admin', 'grievance.resolve', 'any'),
+    ('authority', 'grievance.internal_comments', 'any'),
+    ('admin', 'grievance.internal_comments', 'any'),
+
+    ('faculty', 'grievance.handle', 'any'),
+    ('authority', 'grievance.handle', 'any'),
+    ('admin', 'grievance.handle', 'any'),
+
+    ('faculty', 'course.create', 'own'),
+    ('admin', 'course.create', 'any'),
+    ('student', 'course.enroll', 'any'),
+
+    ('student', 'attendance.mark', 'own'),
+    ('faculty', 'attendance.mark', 'any'),
+    ('authority', 'attendance.mark', 'any'),
+    ('admin', 'attendance.mark', 'any'),
+
+    ('faculty', 'resource.verify', 'any'),
+    ('admin', 'resource.verify', 'any'),
+
+    ('faculty', 'event.create', 'any'),
+    ('admin', 'event.create', 'any'),
+
+    ('faculty', 'opportunity.create', 'any'),
+    ('admin', 'opportunity.create', 'any'),
+    ('faculty', 'opportunity.manage_applications', 'own'),
+    ('admin', 'opportunity.manage_applications', 'any'),
+
+    ('admin', 'user.view', 'any'),
+    ('admin', 'user.manage', 'any'),
+    ('admin', 'audit.view', 'any'),
+    ('admin', 'stats.view', 'any'),
+    ('admin', 'role.manage', 'any');
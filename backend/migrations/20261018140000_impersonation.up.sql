@@ -0,0 +1,7 @@
+-- Set on entries written while an admin was viewing the app as `user_id`
+ALTER TABLE audit_logs ADD COLUMN impersonator_id UUID REFERENCES users(id) ON DELETE SET NULL;
+CREATE INDEX idx_audit_logs_impersonator_id ON audit_logs(impersonator_id) WHERE impersonator_id IS NOT NULL;
+
+-- Read-only impersonation for admins. `user.impersonate_write` (not granted by
+-- default) additionally allows write requests while impersonating.
+INSERT INTO role_permissions (role, permission, scope) VALUES ('admin', 'user.impersonate', 'any');
@@ -0,0 +1,20 @@
+DROP TRIGGER IF EXISTS audit_logs_no_truncate ON audit_logs;
+DROP TRIGGER IF EXISTS audit_logs_append_only ON audit_logs;
+DROP TRIGGER IF EXISTS audit_logs_chain ON audit_logs;
+DROP FUNCTION IF EXISTS audit_logs_append_only();
+DROP FUNCTION IF EXISTS audit_logs_chain();
+DROP FUNCTION IF EXISTS audit_row_hash(audit_logs);
+DROP FUNCTION IF EXISTS audit_field(TEXT);
+
+DROP INDEX IF EXISTS idx_audit_logs_target;
+ALTER TABLE audit_logs
+    DROP COLUMN IF EXISTS hash,
+    DROP COLUMN IF EXISTS prev_hash,
+    DROP COLUMN IF EXISTS target_id,
+    DROP COLUMN IF EXISTS target_type,
+    DROP COLUMN IF EXISTS seq;
+
+-- NOT VALID: entries may refer to users deleted while the chain was in place
+ALTER TABLE audit_logs
+    ADD CONSTRAINT audit_logs_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL NOT VALID,
+    ADD CONSTRAINT audit_logs_impersonator_id_fkey FOREIGN KEY (impersonator_id) REFERENCES users(id) ON DELETE SET NULL NOT VALID;
@@ -0,0 +1,91 @@
+-- Audit entries form a hash chain: each row stores the hash of its own content
+-- and of the row before it, so editing, deleting or reordering rows is detectable
+-- with `backend verify-audit`.
+
+-- The chain must not change when a user is deleted, so ids are kept as plain values
+ALTER TABLE audit_logs DROP CONSTRAINT IF EXISTS audit_logs_user_id_fkey;
+ALTER TABLE audit_logs DROP CONSTRAINT IF EXISTS audit_logs_impersonator_id_fkey;
+
+ALTER TABLE audit_logs
+    ADD COLUMN seq BIGINT,
+    ADD COLUMN target_type VARCHAR(50),
+    ADD COLUMN target_id TEXT,
+    ADD COLUMN prev_hash TEXT,
+    ADD COLUMN hash TEXT;
+
+-- Length-prefixed so no two different rows produce the same input.
+-- Mirrored by `audit::row_hash`; change both together.
+CREATE FUNCTION audit_field(value TEXT) RETURNS TEXT
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT CASE WHEN value IS NULL THEN '-;' ELSE octet_length(value) || ':' || value || ';' END
+$$;
+
+CREATE FUNCTION audit_row_hash(entry audit_logs) RETURNS TEXT
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT encode(sha256(convert_to(
+        audit_field(entry.seq::text)
+        || audit_field(entry.prev_hash)
+        || audit_field((extract(epoch FROM entry.created_at) * 1000000)::bigint::text)
+        || audit_field(entry.user_id::text)
+        || audit_field(entry.impersonator_id::text)
+        || audit_field(entry.action)
+        || audit_field(entry.target_type)
+        || audit_field(entry.target_id)
+        || audit_field(entry.metadata::text),
+        'UTF8'
+    )), 'hex')
+$$;
+
+-- Chain the existing rows in the order they were written
+DO $$
+DECLARE
+    entry audit_logs;
+    prev TEXT := NULL;
+    n BIGINT := 0;
+BEGIN
+    FOR entry IN SELECT * FROM audit_logs ORDER BY created_at, id LOOP
+        n := n + 1;
+        entry.seq := n;
+        entry.prev_hash := prev;
+        prev := audit_row_hash(entry);
+        UPDATE audit_logs SET seq = n, prev_hash = entry.prev_hash, hash = prev WHERE id = entry.id;
+    END LOOP;
+END $$;
+
+ALTER TABLE audit_logs
+    ALTER COLUMN seq SET NOT NULL,
+    ALTER COLUMN hash SET NOT NULL,
+    ADD CONSTRAINT audit_logs_seq_key UNIQUE (seq);
+CREATE INDEX idx_audit_logs_target ON audit_logs(target_type, target_id) WHERE target_id IS NOT NULL;
+
+-- Every insert is linked to the current head. The advisory lock (held until the
+-- inserting transaction ends) keeps concurrent writers from forking the chain.
+-- The price is that audit writes are serialized: a transaction that has written an
+-- entry makes every other writer wait until it commits or rolls back. Transactions
+-- that write audit rows have to stay short, with no network calls after the insert.
+CREATE FUNCTION audit_logs_chain() RETURNS trigger
+LANGUAGE plpgsql AS $$
+DECLARE
+    head audit_logs;
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext('audit_logs_chain'));
+    SELECT * INTO head FROM audit_logs ORDER BY seq DESC LIMIT 1;
+    NEW.seq := COALESCE(head.seq, 0) + 1;
+    NEW.prev_hash := head.hash;
+    NEW.hash := audit_row_hash(NEW);
+    RETURN NEW;
+END $$;
+
+CREATE TRIGGER audit_logs_chain BEFORE INSERT ON audit_logs
+    FOR EACH ROW EXECUTE FUNCTION audit_logs_chain();
+
+CREATE FUNCTION audit_logs_append_only() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_logs is append-only';
+END $$;
+
+CREATE TRIGGER audit_logs_append_only BEFORE UPDATE OR DELETE ON audit_logs
+    FOR EACH ROW EXECUTE FUNCTION audit_logs_append_only();
+CREATE TRIGGER audit_logs_no_truncate BEFORE TRUNCATE ON audit_logs
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_logs_append_only();
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_audit_logs_action;
+DROP INDEX IF EXISTS idx_audit_logs_created_at;
+DELETE FROM role_permissions WHERE permission = 'audit.export';
@@ -0,0 +1,6 @@
+-- Bulk download of the audit log, separate from browsing it
+INSERT INTO role_permissions (role, permission, scope) VALUES ('admin', 'audit.export', 'any');
+
+-- Search by time range and by action
+CREATE INDEX idx_audit_logs_created_at ON audit_logs(created_at);
+CREATE INDEX idx_audit_logs_action ON audit_logs(action);
@@ -0,0 +1,21 @@
+CREATE INDEX IF NOT EXISTS idx_grievance_comments_grievance ON grievance_comments(grievance_id);
+DROP INDEX IF EXISTS idx_grievance_comments_grievance_created_at_id;
+
+CREATE INDEX IF NOT EXISTS idx_tasks_user ON personal_tasks(user_id);
+DROP INDEX IF EXISTS idx_tasks_user_due_date_id;
+
+DROP INDEX IF EXISTS idx_opportunities_active_created_at_id;
+
+CREATE INDEX IF NOT EXISTS idx_events_start_time ON academic_events(start_time);
+DROP INDEX IF EXISTS idx_events_start_time_id;
+
+CREATE INDEX IF NOT EXISTS idx_resources_course ON academic_resources(course_id);
+DROP INDEX IF EXISTS idx_resources_course_created_at_id;
+
+CREATE INDEX IF NOT EXISTS idx_courses_code ON courses(code);
+DROP INDEX IF EXISTS idx_courses_code_id;
+
+DROP INDEX IF EXISTS idx_users_created_at_id;
+
+CREATE INDEX IF NOT EXISTS idx_grievances_created_at ON grievances(created_at DESC);
+DROP INDEX IF EXISTS idx_grievances_created_at_id;
@@ -0,0 +1,23 @@
+-- Composite indexes matching the default (sort column, id) order of each
+-- paginated list, so a page after a cursor is an index range scan
+CREATE INDEX idx_grievances_created_at_id ON grievances(created_at, id);
+DROP INDEX IF EXISTS idx_grievances_created_at;
+
+CREATE INDEX idx_users_created_at_id ON users(created_at, id);
+
+CREATE INDEX idx_courses_code_id ON courses(code, id);
+DROP INDEX IF EXISTS idx_courses_code;
+
+CREATE INDEX idx_resources_course_created_at_id ON academic_resources(course_id, created_at, id);
+DROP INDEX IF EXISTS idx_resources_course;
+
+CREATE INDEX idx_events_start_time_id ON academic_events(start_time, id);
+DROP INDEX IF EXISTS idx_events_start_time;
+
+CREATE INDEX idx_opportunities_active_created_at_id ON opportunities(created_at, id) WHERE is_active;
+
+CREATE INDEX idx_tasks_user_due_date_id ON personal_tasks(user_id, due_date, id);
+DROP INDEX IF EXISTS idx_tasks_user;
+
+CREATE INDEX idx_grievance_comments_grievance_created_at_id ON grievance_comments(grievance_id, created_at, id);
+DROP INDEX IF EXISTS idx_grievance_comments_grievance;
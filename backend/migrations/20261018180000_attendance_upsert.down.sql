@@ -0,0 +1,2 @@
+CREATE INDEX idx_attendance_enrollment ON attendance_logs(enrollment_id);
+DROP INDEX IF EXISTS idx_attendance_enrollment_date;
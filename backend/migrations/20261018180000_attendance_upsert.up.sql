@@ -0,0 +1,10 @@
+-- One attendance entry per enrollment and day, so marking attendance can be
+-- an upsert. Earlier duplicates keep only their most recent entry.
+DELETE FROM attendance_logs a
+USING attendance_logs b
+WHERE a.enrollment_id = b.enrollment_id
+  AND a.date = b.date
+  AND (COALESCE(a.created_at, '-infinity'), a.id) < (COALESCE(b.created_at, '-infinity'), b.id);
+
+CREATE UNIQUE INDEX idx_attendance_enrollment_date ON attendance_logs(enrollment_id, date);
+DROP INDEX IF EXISTS idx_attendance_enrollment;
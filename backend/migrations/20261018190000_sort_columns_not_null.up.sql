@@ -0,0 +1,66 @@
+-- Columns that list endpoints sort and paginate on, and that the API decodes as
+-- non-null, were declared without NOT NULL. A NULL there drops out of keyset pages
+-- (`(NULL, id) > cursor` is never true), so fill in the gaps and forbid new ones.
+-- The updated_at triggers are paused so the backfill doesn't count as an edit.
+
+ALTER TABLE users DISABLE TRIGGER users_updated_at;
+UPDATE users
+SET created_at = COALESCE(created_at, updated_at, 'epoch'),
+    updated_at = COALESCE(updated_at, created_at, 'epoch')
+WHERE created_at IS NULL OR updated_at IS NULL;
+ALTER TABLE users ENABLE TRIGGER users_updated_at;
+ALTER TABLE users
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN updated_at SET NOT NULL;
+
+ALTER TABLE grievances DISABLE TRIGGER grievances_updated_at;
+UPDATE grievances
+SET created_at = COALESCE(created_at, updated_at, 'epoch'),
+    updated_at = COALESCE(updated_at, created_at, 'epoch'),
+    upvote_count = COALESCE(upvote_count, 0)
+WHERE created_at IS NULL OR updated_at IS NULL OR upvote_count IS NULL;
+ALTER TABLE grievances ENABLE TRIGGER grievances_updated_at;
+ALTER TABLE grievances
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN updated_at SET NOT NULL,
+    ALTER COLUMN upvote_count SET NOT NULL;
+
+ALTER TABLE grievance_comments DISABLE TRIGGER grievance_comments_updated_at;
+UPDATE grievance_comments
+SET created_at = COALESCE(created_at, updated_at, 'epoch'),
+    updated_at = COALESCE(updated_at, created_at, 'epoch')
+WHERE created_at IS NULL OR updated_at IS NULL;
+ALTER TABLE grievance_comments ENABLE TRIGGER grievance_comments_updated_at;
+ALTER TABLE grievance_comments
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN updated_at SET NOT NULL;
+
+ALTER TABLE courses DISABLE TRIGGER courses_updated_at;
+UPDATE courses
+SET created_at = COALESCE(created_at, updated_at, 'epoch'),
+    updated_at = COALESCE(updated_at, created_at, 'epoch')
+WHERE created_at IS NULL OR updated_at IS NULL;
+ALTER TABLE courses ENABLE TRIGGER courses_updated_at;
+ALTER TABLE courses
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN updated_at SET NOT NULL;
+
+ALTER TABLE academic_resources DISABLE TRIGGER resources_updated_at;
+UPDATE academic_resources
+SET created_at = COALESCE(created_at, updated_at, 'epoch'),
+    updated_at = COALESCE(updated_at, created_at, 'epoch')
+WHERE created_at IS NULL OR updated_at IS NULL;
+ALTER TABLE academic_resources ENABLE TRIGGER resources_updated_at;
+ALTER TABLE academic_resources
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN updated_at SET NOT NULL;
+
+ALTER TABLE opportunities DISABLE TRIGGER opportunities_updated_at;
+UPDATE opportunities
+SET created_at = COALESCE(created_at, updated_at, 'epoch'),
+    updated_at = COALESCE(updated_at, created_at, 'epoch')
+WHERE created_at IS NULL OR updated_at IS NULL;
+ALTER TABLE opportunities ENABLE TRIGGER opportunities_updated_at;
+ALTER TABLE opportunities
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN updated_at SET NOT NULL;
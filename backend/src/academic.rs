@@ -1,71 +1,33 @@
 #![allow(dead_code)]
 
 use crate::error::AppError;
+use crate::extractors::{AuthUser, Json, Path, Query};
+use crate::listing::PageParams;
+use crate::loaders::{self, UserMap};
+use crate::permissions::{Permission, Resource, allowed, authorize};
+use crate::repos::Repos;
 use crate::structs::*;
-use axum::{
-    extract::{Path, Query, State},
-    response::Json,
-};
-use chrono::{DateTime, Utc};
-use sqlx::PgPool;
-use tower_sessions::Session;
+use axum::extract::State;
 use uuid::Uuid;
 
 // ============================================================================
-// HELPERS
-// ============================================================================
-
-// Helper: Get current user from session
-async fn get_session_user(session: &Session, pool: &PgPool) -> Result<User, AppError> {
-    let user_id: Uuid = session
-        .get("user_id")
-        .await?
-        .ok_or(AppError::Unauthorized)?;
-
-    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-        .bind(user_id)
-        .fetch_one(pool)
-        .await?;
-
-    Ok(user)
-}
-
-// Helper: Ensure user is Faculty or Admin
-fn require_faculty_or_admin(user: &User) -> Result<(), AppError> {
-    if matches!(user.role, UserRole::Faculty | UserRole::Admin) {
-        Ok(())
-    } else {
-        Err(AppError::Forbidden)
-    }
-}
-
-// Helper: Ensure user is Student
-fn require_student(user: &User) -> Result<(), AppError> {
-    if user.role == UserRole::Student {
-        Ok(())
-    } else {
-        Err(AppError::Forbidden)
-    }
-}
-
-// ============================================================================
-// CREATE COURSE (Faculty & Admin only)
+// CREATE COURSE (course.create)
 // ============================================================================
 pub async fn create_course(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Json(payload): Json<CreateCourseRequest>,
 ) -> Result<Json<ApiResponse<CourseResponse>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-    require_faculty_or_admin(&user)?;
-
     // Resolve instructor_id:
-    // If Admin provides email, use it. If Faculty, default to themselves.
-    let instructor_id = if user.role == UserRole::Admin {
-        if let Some(email) = payload.instructor_email {
-            let instructor = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
-                .bind(email)
-                .fetch_optional(&pool)
+    // With an `any` grant the instructor can be picked by email (or left empty),
+    // with `own` the creator is the instructor.
+    let can_pick_instructor =
+        allowed(&repos, &user, Permission::CourseCreate, Resource::Global).await?;
+    let instructor_id = if can_pick_instructor {
+        if let Some(email) = &payload.instructor_email {
+            let instructor = repos
+                .users
+                .find_by_email(email)
                 .await?
                 .ok_or_else(|| AppError::BadRequest("Instructor email not found".to_string()))?;
 
@@ -74,57 +36,20 @@ pub async fn create_course(
             None
         }
     } else {
-        // If Faculty is creating, they are the instructor
+        authorize(&repos, &user, Permission::CourseCreate, Resource::OwnedBy(user.id)).await?;
         Some(user.id)
     };
 
-    let course = sqlx::query_as::<_, Course>(
-        r#"
-        INSERT INTO courses (
-            code, title, description, credits, department, course_type, semester, instructor_id
-        )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-        RETURNING *
-        "#,
-    )
-    .bind(&payload.code)
-    .bind(&payload.title)
-    .bind(&payload.description)
-    .bind(payload.credits)
-    .bind(&payload.department)
-    .bind(&payload.course_type)
-    .bind(&payload.semester)
-    .bind(instructor_id)
-    .fetch_one(&pool)
-    .await?;
+    let course = repos.courses.create(&user, payload, instructor_id).await?;
 
     // Fetch instructor details for the response
-    let instructor_user = if let Some(iid) = course.instructor_id {
-        sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-            .bind(iid)
-            .fetch_optional(&pool)
-            .await?
-    } else {
-        None
-    };
-
-    // Construct Response
-    let response = CourseResponse {
-        id: course.id,
-        code: course.code,
-        title: course.title,
-        description: course.description,
-        credits: course.credits,
-        department: course.department,
-        course_type: course.course_type,
-        instructor: instructor_user.map(UserResponse::from),
-        semester: course.semester,
-    };
+    let response = loaders::course_responses(&repos, vec![course]).await?.remove(0);
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some(response),
         message: Some("Course created successfully".to_string()),
+        error: None,
     }))
 }
 
@@ -132,71 +57,28 @@ pub async fn create_course(
 // GET ALL COURSES (Public/Authenticated)
 // ============================================================================
 pub async fn get_courses(
-    State(pool): State<PgPool>,
-    _session: Session, // Kept to ensure user is logged in if middleware requires it
+    State(repos): State<Repos>,
     Query(filters): Query<CourseFilter>,
-) -> Result<Json<ApiResponse<Vec<CourseResponse>>>, AppError> {
+) -> Result<Json<ApiResponse<Paginated<CourseResponse>>>, AppError> {
     // Note: We aren't strictly enforcing auth here to allow browsing,
-    // but if you want to lock it down, uncomment the next line:
-    // let _user = get_session_user(&_session, &pool).await?;
-
-    let mut query = String::from("SELECT * FROM courses WHERE 1=1");
+    // but if you want to lock it down, add an `AuthUser` extractor.
 
-    // Dynamic Filtering
-    if let Some(ref semester) = filters.semester {
-        query.push_str(&format!(" AND semester = '{}'", semester)); // Warning: Use bind for safety in production, strict eq here
-    }
-    if let Some(ref department) = filters.department {
-        query.push_str(&format!(" AND department = '{}'", department));
-    }
-    if let Some(ref search) = filters.search {
-        query.push_str(&format!(
-            " AND (title ILIKE '%{}%' OR code ILIKE '%{}%')",
-            search, search
-        ));
-    }
-
-    query.push_str(" ORDER BY code ASC");
-
-    // Execute Query
-    let courses = sqlx::query_as::<_, Course>(&query).fetch_all(&pool).await?;
-
-    // Enrich courses with Instructor details efficiently
-    // (In a real high-scale app, we'd use a JOIN in the SQL, but this loop is fine for now)
-    let mut response_list = Vec::new();
-
-    for course in courses {
-        let instructor_user = if let Some(iid) = course.instructor_id {
-            sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-                .bind(iid)
-                .fetch_optional(&pool)
-                .await?
-        } else {
-            None
-        };
+    let mut page = repos.courses.list(filters).await?;
 
-        response_list.push(CourseResponse {
-            id: course.id,
-            code: course.code,
-            title: course.title,
-            description: course.description,
-            credits: course.credits,
-            department: course.department,
-            course_type: course.course_type,
-            instructor: instructor_user.map(UserResponse::from),
-            semester: course.semester,
-        });
-    }
+    // Enrich courses with Instructor details, one query for the whole page
+    let rows = std::mem::take(&mut page.items);
+    let response_list = loaders::course_responses(&repos, rows).await?;
 
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(response_list),
+        data: Some(page.with_items(response_list)),
         message: None,
+        error: None,
     }))
 }
 
 // ============================================================================
-// ENROLL IN COURSE (Student only)
+// ENROLL IN COURSE (course.enroll)
 // ============================================================================
 #[derive(serde::Deserialize)]
 pub struct EnrollRequest {
@@ -204,106 +86,51 @@ pub struct EnrollRequest {
 }
 
 pub async fn enroll_course(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Json(payload): Json<EnrollRequest>,
 ) -> Result<Json<ApiResponse<String>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-    require_student(&user)?;
+    authorize(&repos, &user, Permission::CourseEnroll, Resource::Global).await?;
 
     // Check if course exists
-    let course_exists =
-        sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM courses WHERE id = $1)")
-            .bind(payload.course_id)
-            .fetch_one(&pool)
-            .await?;
-
-    if !course_exists {
+    if repos.courses.find(payload.course_id).await?.is_none() {
         return Err(AppError::NotFound); // Or custom "Course not found"
     }
 
-    // Check if already enrolled
-    let already_enrolled = sqlx::query_scalar::<_, bool>(
-        "SELECT EXISTS(SELECT 1 FROM course_enrollments WHERE student_id = $1 AND course_id = $2)",
-    )
-    .bind(user.id)
-    .bind(payload.course_id)
-    .fetch_one(&pool)
-    .await?;
-
-    if already_enrolled {
-        return Err(AppError::BadRequest(
-            "You are already enrolled in this course".to_string(),
-        ));
-    }
-
-    // Perform Enrollment
-    sqlx::query("INSERT INTO course_enrollments (student_id, course_id) VALUES ($1, $2)")
-        .bind(user.id)
-        .bind(payload.course_id)
-        .execute(&pool)
-        .await?;
+    // Perform Enrollment; nothing comes back when already enrolled
+    repos
+        .courses
+        .enroll(&user, payload.course_id)
+        .await?
+        .ok_or_else(|| AppError::Conflict("You are already enrolled in this course".to_string()))?;
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some("Enrolled successfully".to_string()),
         message: None,
+        error: None,
     }))
 }
 
 // ============================================================================
-// GET MY ENROLLMENTS (Student only)
+// GET MY ENROLLMENTS (course.enroll)
 // ============================================================================
 pub async fn get_my_enrollments(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
 ) -> Result<Json<ApiResponse<Vec<CourseResponse>>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-    require_student(&user)?;
-
-    // Join query to get Course details for enrollments
-    let courses = sqlx::query_as::<_, Course>(
-        r#"
-        SELECT c.* FROM courses c
-        INNER JOIN course_enrollments ce ON c.id = ce.course_id
-        WHERE ce.student_id = $1
-        ORDER BY c.semester DESC, c.code ASC
-        "#,
-    )
-    .bind(user.id)
-    .fetch_all(&pool)
-    .await?;
+    authorize(&repos, &user, Permission::CourseEnroll, Resource::Global).await?;
 
-    // Map to Response (reusing the logic to fetch instructors)
-    let mut response_list = Vec::new();
-
-    for course in courses {
-        let instructor_user = if let Some(iid) = course.instructor_id {
-            sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-                .bind(iid)
-                .fetch_optional(&pool)
-                .await?
-        } else {
-            None
-        };
+    let courses = repos.courses.enrolled_courses(user.id).await?;
 
-        response_list.push(CourseResponse {
-            id: course.id,
-            code: course.code,
-            title: course.title,
-            description: course.description,
-            credits: course.credits,
-            department: course.department,
-            course_type: course.course_type,
-            instructor: instructor_user.map(UserResponse::from),
-            semester: course.semester,
-        });
-    }
+    // Map to Response (reusing the logic to fetch instructors)
+    let response_list = loaders::course_responses(&repos, courses).await?;
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some(response_list),
         message: None,
+        error: None,
     }))
 }
 
@@ -317,28 +144,18 @@ pub struct CourseDetailStats {
 }
 
 pub async fn get_course_details(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    _: AuthUser,
     Path(course_id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<CourseDetailStats>>, AppError> {
-    let _user = get_session_user(&session, &pool).await?;
-
     // Optional: Restrict to only the instructor or admin?
     // For now, let's allow faculty to check their course stats.
 
     // Check if course exists
-    let _course = sqlx::query_as::<_, Course>("SELECT * FROM courses WHERE id = $1")
-        .bind(course_id)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    repos.courses.find(course_id).await?.ok_or(AppError::NotFound)?;
 
     // Get enrollment count
-    let count: i64 =
-        sqlx::query_scalar("SELECT COUNT(*) FROM course_enrollments WHERE course_id = $1")
-            .bind(course_id)
-            .fetch_one(&pool)
-            .await?;
+    let count = repos.courses.enrollment_count(course_id).await?;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -346,6 +163,7 @@ pub async fn get_course_details(
             enrolled_count: count,
         }),
         message: None,
+        error: None,
     }))
 }
 
@@ -364,69 +182,37 @@ pub struct MarkAttendanceRequest {
 
 // UPDATED: Allows Students to mark their own attendance
 pub async fn mark_attendance(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Json(payload): Json<MarkAttendanceRequest>,
 ) -> Result<Json<ApiResponse<String>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-
-    // Logic:
-    // If Student -> Can only mark for themselves (student_id in payload is ignored or must match user.id)
-    // If Faculty -> Can mark for anyone
-
-    let target_student_id = if user.role == UserRole::Student {
-        user.id
-    } else {
-        payload.student_id // Faculty marking for someone else
-    };
-
-    // 1. Verify Enrollment
-    let enrollment_id: Option<Uuid> = sqlx::query_scalar(
-        "SELECT id FROM course_enrollments WHERE student_id = $1 AND course_id = $2",
+    // Students (`own` grant) can only mark themselves, faculty (`any`) can mark anyone
+    authorize(
+        &repos,
+        &user,
+        Permission::AttendanceMark,
+        Resource::OwnedBy(payload.student_id),
     )
-    .bind(target_student_id)
-    .bind(payload.course_id)
-    .fetch_optional(&pool)
     .await?;
 
-    let enrollment_id = enrollment_id
+    // 1. Verify Enrollment
+    let enrollment = repos
+        .courses
+        .find_enrollment(payload.student_id, payload.course_id)
+        .await?
         .ok_or_else(|| AppError::BadRequest("Not enrolled in this course".to_string()))?;
 
-    // 2. Insert/Update Log (Same as before)
-    let exists = sqlx::query_scalar::<_, bool>(
-        "SELECT EXISTS(SELECT 1 FROM attendance_logs WHERE enrollment_id = $1 AND date = $2)",
-    )
-    .bind(enrollment_id)
-    .bind(payload.date)
-    .fetch_one(&pool)
-    .await?;
-
-    if exists {
-        sqlx::query(
-            "UPDATE attendance_logs SET status = $1, remarks = $2 WHERE enrollment_id = $3 AND date = $4"
-        )
-        .bind(&payload.status)
-        .bind(&payload.remarks)
-        .bind(enrollment_id)
-        .bind(payload.date)
-        .execute(&pool)
+    // 2. Insert, or update the day's existing log
+    repos
+        .courses
+        .mark_attendance(&user, &enrollment, payload.date, payload.status, payload.remarks)
         .await?;
-    } else {
-        sqlx::query(
-            "INSERT INTO attendance_logs (enrollment_id, date, status, remarks) VALUES ($1, $2, $3, $4)"
-        )
-        .bind(enrollment_id)
-        .bind(payload.date)
-        .bind(&payload.status)
-        .bind(&payload.remarks)
-        .execute(&pool)
-        .await?;
-    }
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some("Attendance logged".to_string()),
         message: None,
+        error: None,
     }))
 }
 
@@ -441,32 +227,21 @@ pub struct AttendanceSummary {
 }
 
 pub async fn get_my_attendance(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Path(course_id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<AttendanceSummary>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
     // Students can see their own, Faculty can see (logic omitted for brevity, adding check for student)
 
-    // 1. Get Enrollment ID
-    let enrollment_id: Option<Uuid> = sqlx::query_scalar(
-        "SELECT id FROM course_enrollments WHERE student_id = $1 AND course_id = $2",
-    )
-    .bind(user.id)
-    .bind(course_id)
-    .fetch_optional(&pool)
-    .await?;
-
-    let enrollment_id = enrollment_id
+    // 1. Get Enrollment
+    let enrollment = repos
+        .courses
+        .find_enrollment(user.id, course_id)
+        .await?
         .ok_or_else(|| AppError::BadRequest("You are not enrolled in this course".to_string()))?;
 
     // 2. Fetch Logs
-    let logs = sqlx::query_as::<_, AttendanceLog>(
-        "SELECT * FROM attendance_logs WHERE enrollment_id = $1 ORDER BY date DESC",
-    )
-    .bind(enrollment_id)
-    .fetch_all(&pool)
-    .await?;
+    let logs = repos.courses.attendance(enrollment.id).await?;
 
     // 3. Calculate Stats
     let total_classes = logs.len() as i64;
@@ -491,6 +266,7 @@ pub async fn get_my_attendance(
             logs,
         }),
         message: None,
+        error: None,
     }))
 }
 
@@ -500,38 +276,18 @@ pub async fn get_my_attendance(
 
 // UPDATED: Allows Students to upload (sets verified=false)
 pub async fn create_resource(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Path(course_id): Path<Uuid>,
     Json(payload): Json<CreateResourceRequest>,
 ) -> Result<Json<ApiResponse<AcademicResource>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-
-    // Logic:
-    // Admin/Faculty -> is_verified = true
-    // Student -> is_verified = false (needs approval)
-    let is_verified = matches!(user.role, UserRole::Admin | UserRole::Faculty);
+    // Uploads from users who can't verify resources wait for approval
+    let is_verified = allowed(&repos, &user, Permission::ResourceVerify, Resource::Global).await?;
 
-    let resource = sqlx::query_as::<_, AcademicResource>(
-        r#"
-        INSERT INTO academic_resources (
-            course_id, uploaded_by, title, description, resource_type, file_url, year, tags, is_verified
-        )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-        RETURNING *
-        "#
-    )
-    .bind(course_id)
-    .bind(user.id)
-    .bind(&payload.title)
-    .bind(&payload.description)
-    .bind(&payload.resource_type)
-    .bind(&payload.file_url)
-    .bind(payload.year)
-    .bind(&payload.tags)
-    .bind(is_verified)
-    .fetch_one(&pool)
-    .await?;
+    let resource = repos
+        .courses
+        .create_resource(&user, course_id, payload, is_verified)
+        .await?;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -541,73 +297,47 @@ pub async fn create_resource(
         } else {
             "Resource uploaded and pending verification".to_string()
         }),
+        error: None,
     }))
 }
 
 // 2. Get Course Resources (Public/Student)
 pub async fn get_course_resources(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    _: AuthUser,
     Path(course_id): Path<Uuid>,
-    Query(filters): Query<std::collections::HashMap<String, String>>, // Simple filters
-) -> Result<Json<ApiResponse<Vec<AcademicResourceResponse>>>, AppError> {
-    let _user = get_session_user(&session, &pool).await?; // Ensure logged in
-
+    Query(filters): Query<ResourceFilter>,
+) -> Result<Json<ApiResponse<Paginated<AcademicResourceResponse>>>, AppError> {
     // Check course exists first
-    let exists =
-        sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM courses WHERE id = $1)")
-            .bind(course_id)
-            .fetch_one(&pool)
-            .await?;
-
-    if !exists {
+    if repos.courses.find(course_id).await?.is_none() {
         return Err(AppError::NotFound);
     }
 
-    let mut query = String::from("SELECT * FROM academic_resources WHERE course_id = $1");
-
-    // Simple filter for type (e.g. ?type=pyq)
-    if let Some(res_type) = filters.get("type") {
-        query.push_str(&format!(" AND resource_type = '{}'", res_type)); // Warning: Bind safer, but strict enum check needed
-    }
-
-    query.push_str(" ORDER BY created_at DESC");
-
-    let resources = sqlx::query_as::<_, AcademicResource>(&query)
-        .bind(course_id)
-        .fetch_all(&pool)
-        .await?;
+    let page = repos.courses.resources(course_id, filters).await?;
 
     // Transform to Response DTO
-    let mut responses = Vec::new();
-    for res in resources {
-        // Fetch uploader info
-        let uploader = if let Some(uid) = res.uploaded_by {
-            sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-                .bind(uid)
-                .fetch_optional(&pool)
-                .await?
-        } else {
-            None
-        };
-
-        responses.push(AcademicResourceResponse {
-            id: res.id,
-            title: res.title,
-            description: res.description,
-            resource_type: res.resource_type,
-            file_url: res.file_url,
-            uploaded_by: uploader.map(UserResponse::from),
-            year: res.year,
-            tags: res.tags.unwrap_or_default(),
-            created_at: res.created_at,
-        });
-    }
+    let uploaders = UserMap::load(
+        repos.users.as_ref(),
+        page.items.iter().filter_map(|r| r.uploaded_by),
+    )
+    .await?;
+    let page = page.map(|res| AcademicResourceResponse {
+        id: res.id,
+        title: res.title,
+        description: res.description,
+        resource_type: res.resource_type,
+        file_url: res.file_url,
+        uploaded_by: uploaders.response(res.uploaded_by),
+        year: res.year,
+        tags: res.tags.unwrap_or_default(),
+        created_at: res.created_at,
+    });
 
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(responses),
+        data: Some(page),
         message: None,
+        error: None,
     }))
 }
 
@@ -618,115 +348,224 @@ pub async fn get_course_resources(
 // 1. Create Event (Faculty/Admin for Global/Course events)
 // Students could theoretically create personal events if you want,
 // but let's stick to official ones for now.
-#[derive(serde::Deserialize)]
-pub struct CreateEventRequest {
-    pub title: String,
-    pub description: Option<String>,
-    pub event_type: EventType,
-    pub start_time: DateTime<Utc>,
-    pub end_time: Option<DateTime<Utc>>,
-    pub course_id: Option<Uuid>, // Optional: If provided, it's specific to a course
-}
 
 pub async fn create_event(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Json(payload): Json<CreateEventRequest>,
 ) -> Result<Json<ApiResponse<AcademicEvent>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-    require_faculty_or_admin(&user)?; // Only staff creates official deadlines/exams
+    authorize(&repos, &user, Permission::EventCreate, Resource::Global).await?;
 
-    let event = sqlx::query_as::<_, AcademicEvent>(
-        r#"
-        INSERT INTO academic_events (
-            title, description, event_type, start_time, end_time, course_id, created_by
-        )
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
-        RETURNING *
-        "#,
-    )
-    .bind(&payload.title)
-    .bind(&payload.description)
-    .bind(&payload.event_type)
-    .bind(payload.start_time)
-    .bind(payload.end_time)
-    .bind(payload.course_id)
-    .bind(user.id)
-    .fetch_one(&pool)
-    .await?;
+    let event = repos.courses.create_event(&user, payload).await?;
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some(event),
         message: Some("Event created successfully".to_string()),
+        error: None,
     }))
 }
 
 // 2. Get My Calendar (Personalized View)
 // Returns: Global Events + Events for courses I am enrolled in
 pub async fn get_my_calendar(
-    State(pool): State<PgPool>,
-    session: Session,
-    Query(_params): Query<std::collections::HashMap<String, String>>, // ?month=10&year=2025
-) -> Result<Json<ApiResponse<Vec<AcademicEventResponse>>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-
-    // We fetch:
-    // 1. Events where course_id IS NULL (Global events like "Diwali")
-    // 2. Events where course_id matches one of the student's enrollments
-
-    let query = r#"
-        SELECT e.*, c.code as course_code, c.title as course_title 
-        FROM academic_events e
-        LEFT JOIN courses c ON e.course_id = c.id
-        LEFT JOIN course_enrollments ce ON c.id = ce.course_id AND ce.student_id = $1
-        WHERE 
-            (e.course_id IS NULL) -- Global Event
-            OR 
-            (ce.student_id IS NOT NULL) -- User is enrolled in this course
-        ORDER BY e.start_time ASC
-    "#;
-
-    // Note: To map this to AcademicEventResponse, we need a custom struct or manual mapping
-    // because standard SQLx structs don't usually include joined fields (course_code).
-    // Let's do a manual map for flexibility.
-
-    // We need a temporary struct to hold the JOIN result
-    #[derive(sqlx::FromRow)]
-    struct EventRow {
-        id: Uuid,
-        title: String,
-        description: Option<String>,
-        event_type: EventType,
-        start_time: DateTime<Utc>,
-        end_time: Option<DateTime<Utc>>,
-        course_id: Option<Uuid>,
-        course_code: Option<String>,
-        course_title: Option<String>,
-    }
-
-    let rows = sqlx::query_as::<_, EventRow>(query)
-        .bind(user.id)
-        .fetch_all(&pool)
-        .await?;
-
-    let responses: Vec<AcademicEventResponse> = rows
-        .into_iter()
-        .map(|row| AcademicEventResponse {
-            id: row.id,
-            title: row.title,
-            description: row.description,
-            event_type: row.event_type,
-            start_time: row.start_time,
-            end_time: row.end_time,
-            course_code: row.course_code,
-            course_title: row.course_title,
-        })
-        .collect();
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
+    Query(params): Query<PageParams>,
+) -> Result<Json<ApiResponse<Paginated<AcademicEventResponse>>>, AppError> {
+    // Global events plus those of my courses, with the course's code and title
+    let responses = repos.courses.calendar(user.id, params).await?;
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some(responses),
         message: None,
+        error: None,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permissions::Permission;
+    use crate::repos::memory::{Memory, body};
+    use serde_json::json;
+
+    fn course_request(instructor_email: Option<&str>) -> Json<CreateCourseRequest> {
+        body(json!({
+            "code": "CS101",
+            "title": "Introduction to Programming",
+            "description": null,
+            "credits": 4,
+            "department": "CSE",
+            "course_type": "core",
+            "semester": "2026-odd",
+            "instructor_email": instructor_email,
+        }))
+    }
+
+    // Students enroll and mark their own attendance, faculty mark anyone's
+    const GRANTS: &[(UserRole, Permission, PermissionScope)] = &[
+        (UserRole::Student, Permission::CourseEnroll, PermissionScope::Any),
+        (UserRole::Student, Permission::AttendanceMark, PermissionScope::Own),
+        (UserRole::Faculty, Permission::AttendanceMark, PermissionScope::Any),
+    ];
+
+    async fn enroll(repos: &Repos, student: &User, course_id: Uuid) -> Result<(), AppError> {
+        let payload = body(json!({ "course_id": course_id }));
+        enroll_course(State(repos.clone()), AuthUser(student.clone()), payload).await.map(drop)
+    }
+
+    fn attendance_request(
+        student: &User,
+        course: &Course,
+        status: &str,
+    ) -> Json<MarkAttendanceRequest> {
+        body(json!({
+            "student_id": student.id,
+            "course_id": course.id,
+            "date": "2026-10-12",
+            "status": status,
+            "remarks": null,
+        }))
+    }
+
+    #[tokio::test]
+    async fn own_course_grant_makes_the_creator_instructor() {
+        let (memory, repos) = Memory::with_grants(GRANTS);
+        memory.grant(UserRole::Faculty, Permission::CourseCreate, PermissionScope::Own);
+        let faculty = memory.add_user(UserRole::Faculty);
+        let other = memory.add_user(UserRole::Faculty);
+
+        let created = create_course(
+            State(repos),
+            AuthUser(faculty.clone()),
+            course_request(Some(&other.email)),
+        )
+        .await
+        .unwrap();
+        let instructor = created.0.data.unwrap().instructor.unwrap();
+        assert_eq!(instructor.id, faculty.id);
+    }
+
+    #[tokio::test]
+    async fn any_course_grant_picks_the_instructor_by_email() {
+        let (memory, repos) = Memory::with_grants(GRANTS);
+        memory.grant(UserRole::Admin, Permission::CourseCreate, PermissionScope::Any);
+        let admin = memory.add_user(UserRole::Admin);
+        let faculty = memory.add_user(UserRole::Faculty);
+
+        let created = create_course(
+            State(repos.clone()),
+            AuthUser(admin.clone()),
+            course_request(Some(&faculty.email)),
+        )
+        .await
+        .unwrap();
+        let instructor = created.0.data.unwrap().instructor.unwrap();
+        assert_eq!(instructor.id, faculty.id);
+
+        let result = create_course(
+            State(repos),
+            AuthUser(admin),
+            course_request(Some("nobody@iitmandi.ac.in")),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn students_cannot_create_courses() {
+        let (memory, repos) = Memory::with_grants(GRANTS);
+        let student = memory.add_user(UserRole::Student);
+
+        let result = create_course(State(repos), AuthUser(student), course_request(None)).await;
+        assert!(matches!(result, Err(AppError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn enrolling_twice_conflicts() {
+        let (memory, repos) = Memory::with_grants(GRANTS);
+        let student = memory.add_user(UserRole::Student);
+        let course = memory.add_course("CS101", None);
+
+        enroll(&repos, &student, course.id).await.unwrap();
+        let again = enroll(&repos, &student, course.id).await;
+        assert!(matches!(again, Err(AppError::Conflict(_))));
+
+        let missing = enroll(&repos, &student, Uuid::new_v4()).await;
+        assert!(matches!(missing, Err(AppError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn students_mark_only_their_own_attendance() {
+        let (memory, repos) = Memory::with_grants(GRANTS);
+        let student = memory.add_user(UserRole::Student);
+        let classmate = memory.add_user(UserRole::Student);
+        let course = memory.add_course("CS101", None);
+        enroll(&repos, &student, course.id).await.unwrap();
+        enroll(&repos, &classmate, course.id).await.unwrap();
+
+        let mark = |user: &User, request| {
+            mark_attendance(State(repos.clone()), AuthUser(user.clone()), request)
+        };
+        let marked = mark(&student, attendance_request(&student, &course, "present")).await;
+        assert_eq!(marked.unwrap().0.data.as_deref(), Some("Attendance logged"));
+        let result = mark(&student, attendance_request(&classmate, &course, "present")).await;
+        assert!(matches!(result, Err(AppError::Forbidden)));
+
+        // Faculty can mark anyone; a second mark for the same day replaces the first
+        let faculty = memory.add_user(UserRole::Faculty);
+        let marked = mark(&faculty, attendance_request(&student, &course, "absent")).await;
+        assert!(marked.is_ok());
+
+        let summary = get_my_attendance(State(repos.clone()), AuthUser(student), Path(course.id))
+            .await
+            .unwrap()
+            .0
+            .data
+            .unwrap();
+        assert_eq!(summary.total_classes, 1);
+        assert_eq!(summary.present_count, 0);
+    }
+
+    #[tokio::test]
+    async fn attendance_needs_an_enrollment() {
+        let (memory, repos) = Memory::with_grants(GRANTS);
+        let student = memory.add_user(UserRole::Student);
+        let course = memory.add_course("CS101", None);
+
+        let result = mark_attendance(
+            State(repos),
+            AuthUser(student.clone()),
+            attendance_request(&student, &course, "present"),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn unverified_uploads_wait_for_review() {
+        let (memory, repos) = Memory::with_grants(GRANTS);
+        memory.grant(UserRole::Faculty, Permission::ResourceVerify, PermissionScope::Any);
+        let student = memory.add_user(UserRole::Student);
+        let faculty = memory.add_user(UserRole::Faculty);
+        let course = memory.add_course("CS101", Some(faculty.id));
+
+        let upload = |user: &User| {
+            let payload = body(json!({
+                "title": "2025 endsem",
+                "description": null,
+                "resource_type": "pyq",
+                "file_url": "https://example.com/endsem.pdf",
+                "year": 2025,
+                "tags": null,
+            }));
+            create_resource(State(repos.clone()), AuthUser(user.clone()), Path(course.id), payload)
+        };
+        let from_student = upload(&student).await.unwrap().0.data.unwrap();
+        assert!(!from_student.is_verified);
+        let from_faculty = upload(&faculty).await.unwrap().0.data.unwrap();
+        assert!(from_faculty.is_verified);
+    }
+}
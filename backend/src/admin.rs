@@ -1,96 +1,56 @@
 #![allow(dead_code)]
 
+use crate::audit::{self, Target};
 use crate::error::AppError;
+use crate::extractors::{AuthUser, Json, Path, Query, Tx};
+use crate::listing::{ListQuery, PageParams, Sort};
+use crate::permissions::{Permission, Resource, authorize};
+use crate::session_store::delete_user_sessions;
 use crate::structs::*;
-use axum::{
-    extract::{Path, Query, State},
-    response::Json,
-};
+use axum::extract::State;
 use serde::Serialize;
 use sqlx::PgPool;
-use tower_sessions::Session;
 use uuid::Uuid;
 
-// Helper function to get current user from session
-async fn get_session_user(session: &Session, pool: &PgPool) -> Result<User, AppError> {
-    let user_id: Uuid = session
-        .get("user_id")
-        .await?
-        .ok_or(AppError::Unauthorized)?;
-
-    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-        .bind(user_id)
-        .fetch_one(pool)
-        .await?;
-
-    Ok(user)
-}
-
-// Helper function to check if user is admin
-fn require_admin(user: &User) -> Result<(), AppError> {
-    if user.role != UserRole::Admin {
-        return Err(AppError::Forbidden);
-    }
-    Ok(())
-}
-
 // ============================================================================
-// GET ALL USERS (Admin only)
+// GET ALL USERS (user.view)
 // ============================================================================
 pub async fn get_all_users(
     State(pool): State<PgPool>,
-    session: Session,
+    AuthUser(user): AuthUser,
     Query(filters): Query<UserFilters>,
-) -> Result<Json<ApiResponse<Vec<UserListResponse>>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-    require_admin(&user)?;
+) -> Result<Json<ApiResponse<Paginated<UserListResponse>>>, AppError> {
+    authorize(&pool, &user, Permission::UserView, Resource::Global).await?;
 
-    let page = filters.page.unwrap_or(1).max(1);
-    let limit = filters.limit.unwrap_or(50).clamp(1, 100);
-    let offset = (page - 1) * limit;
+    let sort = filters.sort.unwrap_or(Sort::desc(UserSort::CreatedAt));
+    let mut query = ListQuery::new("*", "users", "id", sort);
+    query
+        .eq("role", filters.role)
+        .eq("status", filters.status)
+        .eq("department", filters.department)
+        .search(&["first_name", "last_name", "email"], filters.search.as_deref());
 
-    let mut query = String::from("SELECT * FROM users WHERE 1=1");
-
-    // Apply filters
-    if let Some(role) = &filters.role {
-        query.push_str(&format!(" AND role = '{:?}'", role).to_lowercase());
-    }
-    if let Some(status) = &filters.status {
-        query.push_str(&format!(" AND status = '{:?}'", status).to_lowercase());
-    }
-    if let Some(ref search) = filters.search {
-        query.push_str(&format!(
-            " AND (first_name ILIKE '%{}%' OR last_name ILIKE '%{}%' OR email ILIKE '%{}%')",
-            search, search, search
-        ));
-    }
-
-    query.push_str(" ORDER BY created_at DESC");
-    query.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
-
-    let users = sqlx::query_as::<_, User>(&query)
-        .fetch_all(&pool)
-        .await?;
-
-    let responses: Vec<UserListResponse> = users.into_iter().map(UserListResponse::from).collect();
+    let params = PageParams { cursor: filters.cursor, limit: filters.limit, count: filters.count };
+    let users = query.fetch::<User>(&pool, params).await?;
+    let responses = users.map(UserListResponse::from);
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some(responses),
         message: None,
+        error: None,
     }))
 }
 
 // ============================================================================
-// GET USER BY ID (Admin only)
+// GET USER BY ID (user.view)
 // ============================================================================
 pub async fn get_user_by_id(
     State(pool): State<PgPool>,
-    session: Session,
+    AuthUser(user): AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<UserListResponse>>, AppError> {
-    let current_user = get_session_user(&session, &pool).await?;
-    require_admin(&current_user)?;
+    authorize(&pool, &user, Permission::UserView, Resource::Global).await?;
 
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
         .bind(id)
@@ -102,20 +62,21 @@ pub async fn get_user_by_id(
         success: true,
         data: Some(UserListResponse::from(user)),
         message: None,
+        error: None,
     }))
 }
 
 // ============================================================================
-// UPDATE USER ROLE (Admin only)
+// UPDATE USER ROLE (user.manage)
 // ============================================================================
 pub async fn update_user_role(
     State(pool): State<PgPool>,
-    session: Session,
+    AuthUser(admin_user): AuthUser,
     Path(id): Path<Uuid>,
+    mut tx: Tx,
     Json(payload): Json<UpdateUserRoleRequest>,
 ) -> Result<Json<ApiResponse<UserListResponse>>, AppError> {
-    let admin_user = get_session_user(&session, &pool).await?;
-    require_admin(&admin_user)?;
+    authorize(&pool, &admin_user, Permission::UserManage, Resource::Global).await?;
 
     // Prevent admin from changing their own role (safety measure)
     if admin_user.id == id {
@@ -124,96 +85,61 @@ pub async fn update_user_role(
         ));
     }
 
-    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+    // Locked so concurrent changes to this user are applied one at a time
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1 FOR UPDATE")
         .bind(id)
-        .fetch_optional(&pool)
+        .fetch_optional(&mut *tx)
         .await?
         .ok_or(AppError::NotFound)?;
 
-    sqlx::query("UPDATE users SET role = $1 WHERE id = $2")
-        .bind(&payload.role)
-        .bind(id)
-        .execute(&pool)
-        .await?;
+    let updated_user =
+        sqlx::query_as::<_, User>("UPDATE users SET role = $1 WHERE id = $2 RETURNING *")
+            .bind(&payload.role)
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+    // Make the user sign in again under the new role
+    let revoked_sessions = if user.role != payload.role {
+        delete_user_sessions(&mut *tx, id).await?
+    } else {
+        0
+    };
 
     // Log the action
-    sqlx::query(
-        "INSERT INTO audit_logs (user_id, action, metadata) VALUES ($1, $2, $3)",
+    audit::record(
+        &mut *tx,
+        &admin_user,
+        "UPDATE_USER_ROLE",
+        Some(Target::User(id)),
+        serde_json::json!({
+            "old_role": user.role,
+            "new_role": payload.role,
+            "revoked_sessions": revoked_sessions,
+        }),
     )
-    .bind(admin_user.id)
-    .bind("UPDATE_USER_ROLE")
-    .bind(serde_json::json!({
-        "target_user_id": id,
-        "old_role": user.role,
-        "new_role": payload.role,
-    }))
-    .execute(&pool)
     .await?;
-
-    let updated_user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-        .bind(id)
-        .fetch_one(&pool)
-        .await?;
+    tx.commit().await?;
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some(UserListResponse::from(updated_user)),
         message: Some("User role updated successfully".to_string()),
+        error: None,
     }))
 }
 
 // ============================================================================
-// UPDATE CURRENT USER ROLE (For dev/testing - allows self role change)
-// ============================================================================
-pub async fn update_own_role(
-    State(pool): State<PgPool>,
-    session: Session,
-    Json(payload): Json<UpdateUserRoleRequest>,
-) -> Result<Json<ApiResponse<UserResponse>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-
-    sqlx::query("UPDATE users SET role = $1 WHERE id = $2")
-        .bind(&payload.role)
-        .bind(user.id)
-        .execute(&pool)
-        .await?;
-
-    // Log the action
-    sqlx::query(
-        "INSERT INTO audit_logs (user_id, action, metadata) VALUES ($1, $2, $3)",
-    )
-    .bind(user.id)
-    .bind("UPDATE_OWN_ROLE")
-    .bind(serde_json::json!({
-        "old_role": user.role,
-        "new_role": payload.role,
-    }))
-    .execute(&pool)
-    .await?;
-
-    let updated_user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-        .bind(user.id)
-        .fetch_one(&pool)
-        .await?;
-
-    Ok(Json(ApiResponse {
-        success: true,
-        data: Some(UserResponse::from(updated_user)),
-        message: Some("Your role has been updated".to_string()),
-    }))
-}
-
-// ============================================================================
-// UPDATE USER STATUS (Admin only)
+// UPDATE USER STATUS (user.manage)
 // ============================================================================
 pub async fn update_user_status(
     State(pool): State<PgPool>,
-    session: Session,
+    AuthUser(admin_user): AuthUser,
     Path(id): Path<Uuid>,
+    mut tx: Tx,
     Json(payload): Json<UpdateUserStatusRequest>,
 ) -> Result<Json<ApiResponse<UserListResponse>>, AppError> {
-    let admin_user = get_session_user(&session, &pool).await?;
-    require_admin(&admin_user)?;
+    authorize(&pool, &admin_user, Permission::UserManage, Resource::Global).await?;
 
     // Prevent admin from changing their own status
     if admin_user.id == id {
@@ -222,104 +148,89 @@ pub async fn update_user_status(
         ));
     }
 
-    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+    // Locked so concurrent changes to this user are applied one at a time
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1 FOR UPDATE")
         .bind(id)
-        .fetch_optional(&pool)
+        .fetch_optional(&mut *tx)
         .await?
         .ok_or(AppError::NotFound)?;
 
-    sqlx::query("UPDATE users SET status = $1 WHERE id = $2")
-        .bind(&payload.status)
-        .bind(id)
-        .execute(&pool)
-        .await?;
+    let updated_user =
+        sqlx::query_as::<_, User>("UPDATE users SET status = $1 WHERE id = $2 RETURNING *")
+            .bind(&payload.status)
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+    // Suspended or deactivated users are logged out everywhere
+    let revoked_sessions = if matches!(payload.status, UserStatus::Active) {
+        0
+    } else {
+        delete_user_sessions(&mut *tx, id).await?
+    };
 
     // Log the action
-    sqlx::query(
-        "INSERT INTO audit_logs (user_id, action, metadata) VALUES ($1, $2, $3)",
+    audit::record(
+        &mut *tx,
+        &admin_user,
+        "UPDATE_USER_STATUS",
+        Some(Target::User(id)),
+        serde_json::json!({
+            "old_status": user.status,
+            "new_status": payload.status,
+            "revoked_sessions": revoked_sessions,
+        }),
     )
-    .bind(admin_user.id)
-    .bind("UPDATE_USER_STATUS")
-    .bind(serde_json::json!({
-        "target_user_id": id,
-        "old_status": user.status,
-        "new_status": payload.status,
-    }))
-    .execute(&pool)
     .await?;
-
-    let updated_user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-        .bind(id)
-        .fetch_one(&pool)
-        .await?;
+    tx.commit().await?;
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some(UserListResponse::from(updated_user)),
         message: Some("User status updated successfully".to_string()),
+        error: None,
     }))
 }
 
 // ============================================================================
-// GET AUDIT LOGS (Admin only)
+// REVOKE ALL SESSIONS FOR A USER (user.manage)
 // ============================================================================
-pub async fn get_audit_logs(
+pub async fn revoke_user_sessions(
     State(pool): State<PgPool>,
-    session: Session,
-    Query(params): Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<ApiResponse<Vec<AuditLogResponse>>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-    require_admin(&user)?;
-
-    let limit: i64 = params
-        .get("limit")
-        .and_then(|l| l.parse().ok())
-        .unwrap_or(100)
-        .clamp(1, 500);
-
-    let offset: i64 = params
-        .get("offset")
-        .and_then(|o| o.parse().ok())
-        .unwrap_or(0);
+    AuthUser(admin_user): AuthUser,
+    Path(id): Path<Uuid>,
+    mut tx: Tx,
+) -> Result<Json<ApiResponse<serde_json::Value>>, AppError> {
+    authorize(&pool, &admin_user, Permission::UserManage, Resource::Global).await?;
+
+    sqlx::query_scalar::<_, Uuid>("SELECT id FROM users WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::NotFound)?;
 
-    let logs = sqlx::query_as::<_, AuditLog>(
-        "SELECT * FROM audit_logs ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+    let revoked_sessions = delete_user_sessions(&mut *tx, id).await?;
+
+    audit::record(
+        &mut *tx,
+        &admin_user,
+        "REVOKE_USER_SESSIONS",
+        Some(Target::User(id)),
+        serde_json::json!({ "revoked_sessions": revoked_sessions }),
     )
-    .bind(limit)
-    .bind(offset)
-    .fetch_all(&pool)
     .await?;
-
-    let mut responses = Vec::new();
-    for log in logs {
-        let log_user = if let Some(user_id) = log.user_id {
-            sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-                .bind(user_id)
-                .fetch_optional(&pool)
-                .await?
-                .map(UserResponse::from)
-        } else {
-            None
-        };
-
-        responses.push(AuditLogResponse {
-            id: log.id,
-            user: log_user,
-            action: log.action,
-            metadata: log.metadata,
-            created_at: log.created_at,
-        });
-    }
+    tx.commit().await?;
 
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(responses),
-        message: None,
+        data: Some(serde_json::json!({ "revoked_sessions": revoked_sessions })),
+        message: Some("User sessions revoked".to_string()),
+        error: None,
     }))
 }
 
 // ============================================================================
-// GET SYSTEM STATS (Admin only)
+// GET SYSTEM STATS (stats.view)
 // ============================================================================
 #[derive(Debug, Serialize)]
 pub struct SystemStats {
@@ -333,10 +244,9 @@ pub struct SystemStats {
 
 pub async fn get_system_stats(
     State(pool): State<PgPool>,
-    session: Session,
+    AuthUser(user): AuthUser,
 ) -> Result<Json<ApiResponse<SystemStats>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-    require_admin(&user)?;
+    authorize(&pool, &user, Permission::StatsView, Resource::Global).await?;
 
     let total_users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
         .fetch_one(&pool)
@@ -391,52 +301,6 @@ pub async fn get_system_stats(
             users_by_role,
         }),
         message: None,
+        error: None,
     }))
 }
-
-// ============================================================================
-// SEED DUMMY USERS (Dev only)
-// ============================================================================
-pub async fn seed_dummy_users(
-    State(pool): State<PgPool>,
-) -> Result<Json<ApiResponse<String>>, AppError> {
-    let dummy_users = vec![
-        ("Dr. Strange", "strange@iitmandi.ac.in", UserRole::Faculty),
-        ("Tony Stark", "stark@iitmandi.ac.in", UserRole::Authority),
-        ("Steve Rogers", "rogers@students.iitmandi.ac.in", UserRole::Student),
-    ];
-
-    for (name, email, role) in dummy_users {
-        // Check if exists
-        let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)")
-            .bind(email)
-            .fetch_one(&pool)
-            .await?;
-
-        if !exists {
-            let parts: Vec<&str> = name.split_whitespace().collect();
-            let first_name = parts[0];
-            let last_name = parts.get(1).unwrap_or(&"");
-
-            sqlx::query(
-                r#"
-                INSERT INTO users (email, google_id, role, status, first_name, last_name, profile_picture)
-                VALUES ($1, $2, $3, 'active', $4, $5, '')
-                "#
-            )
-            .bind(email)
-            .bind(Uuid::new_v4().to_string()) // Random dummy google_id
-            .bind(role)
-            .bind(first_name)
-            .bind(last_name)
-            .execute(&pool)
-            .await?;
-        }
-    }
-
-    Ok(Json(ApiResponse {
-        success: true,
-        data: Some("Dummy users created. You can now assign them tasks.".to_string()),
-        message: None,
-    }))
-}
\ No newline at end of file
@@ -0,0 +1,257 @@
+// Every route with its middleware and state. `main.rs` serves it and starts the
+// background jobs; tests build the same router against their own database.
+
+use crate::academic;
+use crate::admin::{
+    get_all_users, get_system_stats, get_user_by_id, revoke_user_sessions, update_user_role,
+    update_user_status,
+};
+use crate::audit;
+use crate::auth::{get_current_user, login_callback, login_initiate, logout};
+use crate::config::AppConfig;
+#[cfg(feature = "dev-routes")]
+use crate::dev;
+use crate::grievances::{
+    add_comment, assign_grievance, create_grievance, delete_grievance, get_comments,
+    get_departments, get_grievance_by_id, get_grievance_history, get_grievances, resolve_grievance,
+    toggle_upvote, update_grievance_status, upload_grievance_photos,
+};
+use crate::health;
+use crate::identity;
+use crate::impersonation;
+use crate::monitoring;
+use crate::opportunity;
+use crate::permissions;
+use crate::rate_limit::{self, RateLimiters, RouteGroup};
+use crate::repos::Repos;
+use crate::session_store::PgSessionStore;
+use crate::sessions;
+use crate::state::AppState;
+use crate::telemetry;
+use crate::tokens;
+use axum::{
+    Json, Router,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post, put},
+};
+use http::{HeaderName, Method};
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tower_http::cors::CorsLayer;
+use tower_sessions::{Expiry, SessionManagerLayer, cookie::time::Duration};
+
+// Sessions expire after a week without activity
+const SESSION_INACTIVITY_DAYS: i64 = 7;
+// Headroom for multipart boundaries and headers on top of the photo size limit
+const MULTIPART_OVERHEAD_BYTES: usize = 64 * 1024;
+
+pub struct App {
+    pub router: Router,
+    // Cancelled when the server starts shutting down; `/readyz` reports 503 from then on
+    pub shutdown: CancellationToken,
+}
+
+// Builds the router as served by `main.rs`. Doesn't start any background jobs.
+pub fn build_app(config: Arc<AppConfig>, pool: PgPool) -> anyhow::Result<App> {
+    let cors = CorsLayer::new()
+        .allow_origin(config.cors_origins.clone())
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([
+            HeaderName::from_static("content-type"),
+            HeaderName::from_static("authorization"),
+            HeaderName::from_static("accept"),
+        ])
+        .expose_headers([telemetry::REQUEST_ID_HEADER])
+        .allow_credentials(true);
+
+    let metrics = monitoring::install()?;
+    let shutdown = CancellationToken::new();
+    let session_store = PgSessionStore::new(pool.clone());
+    let repos = Repos::postgres(pool.clone());
+
+    let mut session_layer = SessionManagerLayer::new(session_store)
+        .with_name(config.cookie.name.clone())
+        .with_expiry(Expiry::OnInactivity(Duration::days(SESSION_INACTIVITY_DAYS)))
+        .with_secure(config.cookie.secure)
+        .with_http_only(true)
+        .with_same_site(config.cookie.same_site)
+        .with_path("/");
+    if let Some(domain) = &config.cookie.domain {
+        session_layer = session_layer.with_domain(domain.clone());
+    }
+
+    let photo_body_limit =
+        config.uploads.max_photos * config.uploads.max_photo_bytes + MULTIPART_OVERHEAD_BYTES;
+
+    let identity = identity::from_config(&config.identity_provider, pool.clone());
+    let limiters = RateLimiters::new(&config.rate_limits, pool.clone());
+
+    let app = Router::new()
+        .route(
+            "/",
+            get(|| async { Json(json!({"status": "ok", "message": "Backend is running"})) }),
+        )
+        // Auth routes
+        .route("/auth/login", get(login_initiate))
+        .route("/auth/callback", get(login_callback))
+        // Original Google paths, still used by the frontend and registered redirect URLs
+        .route("/auth/google", get(login_initiate))
+        .route("/auth/google/callback", get(login_callback))
+        .route("/auth/logout", get(logout))
+        .route("/auth/me", get(get_current_user))
+        .route("/api/me/permissions", get(permissions::get_my_permissions))
+        // Active sessions
+        .route("/api/me/sessions", get(sessions::get_my_sessions))
+        .route("/api/me/sessions/{id}", delete(sessions::revoke_my_session))
+        // Personal access tokens
+        .route("/api/tokens", post(tokens::create_token).get(tokens::get_my_tokens))
+        .route("/api/tokens/scopes", get(tokens::get_scopes))
+        .route("/api/tokens/{id}", delete(tokens::revoke_token))
+        // Grievance routes
+        .route(
+            "/api/grievances",
+            post(create_grievance).layer(middleware::from_fn_with_state(
+                limiters.group(RouteGroup::GrievanceCreate),
+                rate_limit::enforce,
+            )),
+        )
+        .route("/api/grievances", get(get_grievances))
+        .route("/api/grievances/{id}", get(get_grievance_by_id))
+        .route("/api/grievances/{id}", delete(delete_grievance))
+        .route("/api/grievances/{id}/status", put(update_grievance_status))
+        .route("/api/grievances/{id}/assign", put(assign_grievance))
+        .route("/api/grievances/{id}/resolve", put(resolve_grievance))
+        .route(
+            "/api/grievances/{id}/upvote",
+            post(toggle_upvote).layer(middleware::from_fn_with_state(
+                limiters.group(RouteGroup::Upvotes),
+                rate_limit::enforce,
+            )),
+        )
+        .route(
+            "/api/grievances/{id}/photos",
+            post(upload_grievance_photos)
+                .layer(DefaultBodyLimit::max(photo_body_limit))
+                .layer(middleware::from_fn_with_state(
+                    limiters.group(RouteGroup::Uploads),
+                    rate_limit::enforce,
+                )),
+        )
+        .route("/api/grievances/{id}/history", get(get_grievance_history))
+        .route("/api/grievances/{id}/comments", post(add_comment))
+        .route("/api/grievances/{id}/comments", get(get_comments))
+        .route("/api/departments", get(get_departments))
+        // Admin routes
+        .route("/api/admin/users", get(get_all_users))
+        .route("/api/admin/users/{id}", get(get_user_by_id))
+        .route("/api/admin/users/{id}/role", put(update_user_role))
+        .route("/api/admin/users/{id}/status", put(update_user_status))
+        .route("/api/admin/users/{id}/sessions", delete(revoke_user_sessions))
+        .route(
+            "/api/admin/users/{id}/impersonate",
+            post(impersonation::start_impersonation),
+        )
+        .route(
+            "/api/admin/impersonation",
+            delete(impersonation::stop_impersonation),
+        )
+        .route("/api/admin/permissions", get(permissions::get_role_permissions))
+        .route("/api/admin/permissions/{role}", put(permissions::update_role_permissions))
+        .route("/api/admin/audit-logs", get(audit::get_audit_logs))
+        .route("/api/admin/audit-logs/export/{format}", get(audit::export_audit_logs))
+        .route("/api/admin/audit-logs/verify", get(audit::verify_audit_logs))
+        .route("/api/admin/stats", get(get_system_stats))
+        // --- ACADEMIC ROUTES ---
+        // Course Management
+        .route(
+            "/api/courses",
+            post(academic::create_course).get(academic::get_courses),
+        )
+        .route("/api/courses/enroll", post(academic::enroll_course))
+        .route(
+            "/api/courses/my-enrollments",
+            get(academic::get_my_enrollments),
+        )
+        .route("/api/courses/{id}", get(academic::get_course_details))
+        // Attendance
+        .route("/api/attendance/mark", post(academic::mark_attendance))
+        .route("/api/attendance/{id}", get(academic::get_my_attendance))
+        // Resources (Vault)
+        .route(
+            "/api/courses/{id}/resources",
+            post(academic::create_resource).get(academic::get_course_resources),
+        )
+        // Calendar
+        .route(
+            "/api/events",
+            post(academic::create_event).get(academic::get_my_calendar),
+        )
+        // --- OPPORTUNITY ROUTES (PILLAR 4) ---
+        // 1. Opportunities (Faculty post, Students browse)
+        .route(
+            "/api/opportunities",
+            post(opportunity::create_opportunity).get(opportunity::get_opportunities),
+        )
+        // 2. Applications (Apply & View My History)
+        .route(
+            "/api/opportunities/{id}/apply",
+            post(opportunity::apply_opportunity),
+        )
+        .route(
+            "/api/applications/my-applications",
+            get(opportunity::get_my_applications),
+        )
+        // 3. Faculty Management (View Applicants & Update Status)
+        .route(
+            "/api/opportunities/{id}/applications",
+            get(opportunity::get_opportunity_applications),
+        )
+        .route(
+            "/api/applications/{id}/status",
+            put(opportunity::update_application_status),
+        )
+        // 4. The Scholar's Ledger (Personal Tasks)
+        .route(
+            "/api/tasks",
+            post(opportunity::create_task).get(opportunity::get_tasks),
+        )
+        .route(
+            "/api/tasks/{id}",
+            put(opportunity::update_task).delete(opportunity::delete_task),
+        );
+
+    // Self role change and seeding, see dev.rs
+    #[cfg(feature = "dev-routes")]
+    let app = app.merge(dev::routes(&config));
+
+    let router = app
+        .route_layer(middleware::from_fn_with_state(
+            limiters.group(RouteGroup::Default),
+            rate_limit::enforce,
+        ))
+        .route_layer(middleware::from_fn(sessions::touch))
+        // Health probes and scraping are added after these so they are never throttled
+        // and never write the session
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .route("/metrics", get(monitoring::metrics_handler))
+        .route_layer(middleware::from_fn(monitoring::track_http))
+        .with_state(AppState {
+            pool,
+            repos,
+            config: config.clone(),
+            shutdown: shutdown.clone(),
+            metrics,
+            identity,
+        })
+        .layer(session_layer)
+        .layer(cors);
+
+    Ok(App {
+        router: telemetry::with_request_ids(router),
+        shutdown,
+    })
+}
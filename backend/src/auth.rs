@@ -1,166 +1,188 @@
+use crate::audit::{self, Actor, Target};
+use crate::config::AppConfig;
 use crate::error::AppError;
-use crate::structs::{ApiResponse, GoogleUserInfo, User, UserResponse, UserRole, UserStatus};
+use crate::extractors::{AuthUser, ClientIp, Json, Query};
+use crate::identity::{AuthorizationRequest, ExternalIdentity, IdentityProvider};
+use crate::impersonation;
+use crate::monitoring;
+use crate::structs::{ApiResponse, CurrentUserResponse, SessionDevice, User, UserResponse, UserStatus};
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
+    extract::State,
+    http::{HeaderMap, header},
     response::{IntoResponse, Redirect},
-    Json,
 };
-use chrono::Utc;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlx::PgPool;
-use std::env;
+use std::sync::Arc;
 use tower_sessions::Session as TowerSession;
 use uuid::Uuid;
 
-const SESSION_USER_ID_KEY: &str = "user_id";
+pub const SESSION_USER_ID_KEY: &str = "user_id";
+pub const SESSION_DEVICE_KEY: &str = "device";
+const SESSION_OAUTH_KEY: &str = "oauth_pending";
+// How long the user has to finish signing in at the identity provider
+const OAUTH_STATE_TTL_SECS: i64 = 10 * 60;
+
+// Login in progress, bound to the browser's session between initiate and callback
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingLogin {
+    state: String,
+    code_verifier: String,
+    redirect_origin: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginQuery {
+    origin: Option<String>,
+    login_hint: Option<String>,
+}
 
 #[derive(Debug, Deserialize)]
-pub struct GoogleCallbackQuery {
+pub struct CallbackQuery {
     code: String,
     state: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
-struct GoogleTokenRequest {
-    code: String,
-    client_id: String,
-    client_secret: String,
-    redirect_uri: String,
-    grant_type: String,
+// 256 bits from the OS RNG, URL-safe so it can go straight into a query string
+pub fn random_token() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
 }
 
-#[derive(Debug, Deserialize)]
-struct GoogleTokenResponse {
-    access_token: String,
+// PKCE S256: the challenge sent up front is the hash of the verifier sent with the code
+fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
 }
 
-pub async fn google_login_initiate(
-    Query(params): Query<std::collections::HashMap<String, String>>,
-) -> impl IntoResponse {
-    let client_id = env::var("GOOGLE_CLIENT_ID").expect("GOOGLE_CLIENT_ID must be set");
-    let redirect_uri = env::var("GOOGLE_REDIRECT_URL").expect("GOOGLE_REDIRECT_URL must be set");
-    
-    // Get the frontend origin from query parameter or default to FRONTEND_URL
-    let frontend_origin = params.get("origin").cloned()
-        .unwrap_or_else(|| env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:4173".to_string()));
-    
-    let auth_url = format!(
-        "https://accounts.google.com/o/oauth2/v2/auth?client_id={}&redirect_uri={}&response_type=code&scope=openid%20email%20profile&state={}",
-        client_id, redirect_uri, urlencoding::encode(&frontend_origin)
-    );
-    
-    Redirect::to(&auth_url)
+async fn record_rejection(pool: &PgPool, action: &str, metadata: serde_json::Value) {
+    if let Err(e) = audit::record(pool, Actor::System, action, None, metadata).await {
+        tracing::error!("AUDIT_LOG: Failed to record {}: {:?}", action, e);
+    }
 }
 
-pub async fn google_callback(
-    Query(query): Query<GoogleCallbackQuery>,
+pub async fn login_initiate(
+    State(config): State<Arc<AppConfig>>,
     State(pool): State<PgPool>,
+    State(provider): State<Arc<dyn IdentityProvider>>,
     session: TowerSession,
-) -> Result<impl IntoResponse, AppError> {
-    tracing::info!("CALLBACK_START: Received callback from Google");
-    tracing::info!("CALLBACK_STATE: state={:?}", query.state);
-    
-    let client_id = env::var("GOOGLE_CLIENT_ID").expect("GOOGLE_CLIENT_ID must be set");
-    let client_secret = env::var("GOOGLE_CLIENT_SECRET").expect("GOOGLE_CLIENT_SECRET must be set");
-    let redirect_uri = env::var("GOOGLE_REDIRECT_URL").expect("GOOGLE_REDIRECT_URL must be set");
+    Query(params): Query<LoginQuery>,
+) -> Result<Redirect, AppError> {
+    // The frontend passes its own origin; only configured origins may receive the login
+    let redirect_origin = match &params.origin {
+        Some(origin) => {
+            let origin = origin.trim_end_matches('/');
+            if !config.redirect_origins.iter().any(|allowed| allowed == origin) {
+                tracing::warn!("OAUTH_INITIATE: Rejected redirect origin {}", origin);
+                record_rejection(
+                    &pool,
+                    "oauth_redirect_rejected",
+                    serde_json::json!({ "origin": origin }),
+                )
+                .await;
+                return Err(AppError::BadRequest("Login origin is not allowed".to_string()));
+            }
+            origin.to_string()
+        }
+        None => config.frontend_url.clone(),
+    };
 
-    tracing::info!("TOKEN_EXCHANGE: Starting token exchange with Google");
-    let client = reqwest::Client::new();
-    
-    let token_response = client
-        .post("https://oauth2.googleapis.com/token")
-        .json(&GoogleTokenRequest {
-            code: query.code.clone(),
-            client_id,
-            client_secret,
-            redirect_uri,
-            grant_type: "authorization_code".to_string(),
+    let pending = PendingLogin {
+        state: random_token(),
+        code_verifier: random_token(),
+        redirect_origin,
+        created_at: Utc::now(),
+    };
+
+    let auth_url = provider
+        .authorization_url(AuthorizationRequest {
+            state: &pending.state,
+            code_challenge: &pkce_challenge(&pending.code_verifier),
+            login_hint: params.login_hint.as_deref(),
         })
-        .send()
-        .await
-        .map_err(|e| {
-            tracing::error!("TOKEN_EXCHANGE_ERROR: Failed to send token request: {}", e);
-            AppError::HttpError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
-        })?
-        .json::<GoogleTokenResponse>()
+        .await?;
+
+    session
+        .insert(SESSION_OAUTH_KEY, &pending)
         .await
-        .map_err(|e| {
-            tracing::error!("TOKEN_PARSE_ERROR: Failed to parse token response: {}", e);
-            AppError::HttpError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
-        })?;
+        .map_err(|e| AppError::Internal(e.into()))?;
 
-    tracing::info!("TOKEN_EXCHANGE: Success");
-    tracing::info!("USER_INFO: Fetching user info from Google");
-    
-    let user_info_response = client
-        .get("https://www.googleapis.com/oauth2/v3/userinfo")
-        .bearer_auth(&token_response.access_token)
-        .send()
+    Ok(Redirect::to(&auth_url))
+}
+
+pub async fn login_callback(
+    Query(query): Query<CallbackQuery>,
+    State(pool): State<PgPool>,
+    State(config): State<Arc<AppConfig>>,
+    State(provider): State<Arc<dyn IdentityProvider>>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    session: TowerSession,
+) -> Result<impl IntoResponse, AppError> {
+    tracing::info!("CALLBACK_START: Received callback from {}", provider.name());
+
+    // Taking the pending login out of the session makes each state single-use
+    let pending = session
+        .remove::<PendingLogin>(SESSION_OAUTH_KEY)
         .await
-        .map_err(|e| {
-            tracing::error!("USER_INFO_ERROR: Failed to fetch user info: {}", e);
-            AppError::HttpError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
-        })?;
-    
-    let status = user_info_response.status();
-    tracing::info!("USER_INFO: Response status: {}", status);
-    
-    let response_text = user_info_response.text().await
-        .map_err(|e| {
-            tracing::error!("USER_INFO_TEXT_ERROR: Failed to read response text: {}", e);
-            AppError::HttpError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
-        })?;
-    
-    tracing::info!("USER_INFO: Raw response body: {}", response_text);
-    
-    let user_info: GoogleUserInfo = serde_json::from_str(&response_text)
-        .map_err(|e| {
-            tracing::error!("USER_INFO_PARSE_ERROR: Failed to parse user info JSON: {}", e);
-            tracing::error!("USER_INFO_PARSE_ERROR: Response was: {}", response_text);
-            AppError::HttpError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
-        })?;
+        .map_err(|e| AppError::Internal(e.into()))?;
+
+    let pending = match pending {
+        None => Err("missing"),
+        Some(p) if query.state.as_deref() != Some(p.state.as_str()) => Err("mismatch"),
+        Some(p) if (Utc::now() - p.created_at).num_seconds() > OAUTH_STATE_TTL_SECS => Err("expired"),
+        Some(p) => Ok(p),
+    };
+    let pending = match pending {
+        Ok(pending) => pending,
+        Err(reason) => {
+            tracing::warn!("CALLBACK_REJECTED: OAuth state {}", reason);
+            record_rejection(
+                &pool,
+                "oauth_state_rejected",
+                serde_json::json!({ "reason": reason }),
+            )
+            .await;
+            let error_msg = urlencoding::encode("Login session expired or invalid");
+            let error_details = urlencoding::encode("Please start the sign-in again");
+            return Ok(Redirect::to(&format!(
+                "{}/auth-error?error={}&details={}",
+                config.frontend_url, error_msg, error_details
+            )));
+        }
+    };
+    tracing::info!("CALLBACK_STATE: state verified");
+
+    let user_info = provider
+        .exchange_code(&query.code, &pending.code_verifier)
+        .await?;
 
     tracing::info!("USER_INFO: Received email={}", user_info.email);
     tracing::info!("EMAIL_CHECK: Checking if email domain is allowed");
     
-    let ends_with_iitmandi = user_info.email.ends_with("@iitmandi.ac.in");
-    let ends_with_students = user_info.email.ends_with("@students.iitmandi.ac.in");
-    tracing::info!("EMAIL_CHECK: ends_with @iitmandi.ac.in = {}", ends_with_iitmandi);
-    tracing::info!("EMAIL_CHECK: ends_with @students.iitmandi.ac.in = {}", ends_with_students);
+    let email_allowed = config.is_allowed_email(&user_info.email);
+    tracing::info!("EMAIL_CHECK: allowed = {} (domains: {:?})", email_allowed, config.allowed_email_domains);
 
-    if !ends_with_iitmandi && !ends_with_students {
+    if !email_allowed {
         tracing::warn!("========================================");
         tracing::warn!("EMAIL_REJECTED: Non-institute email detected");
         tracing::warn!("EMAIL_REJECTED: Email = {}", user_info.email);
         tracing::warn!("EMAIL_REJECTED: Starting redirect to auth-error page");
         tracing::warn!("========================================");
         
-        let state_value = query.state.as_ref();
-        tracing::info!("STATE_DECODE: state parameter = {:?}", state_value);
-        
-        let frontend_url = state_value
-            .and_then(|s| {
-                tracing::info!("STATE_DECODE: Attempting to decode state: {}", s);
-                let decoded = urlencoding::decode(s);
-                tracing::info!("STATE_DECODE: Decode result = {:?}", decoded);
-                decoded.ok()
-            })
-            .map(|s| {
-                let url = s.to_string();
-                tracing::info!("STATE_DECODE: Decoded frontend URL = {}", url);
-                url
-            })
-            .unwrap_or_else(|| {
-                let fallback = env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:4173".to_string());
-                tracing::info!("STATE_DECODE: No state found, using fallback = {}", fallback);
-                fallback
-            });
-        
+        let frontend_url = &pending.redirect_origin;
         tracing::info!("REDIRECT_BUILD: Frontend URL = {}", frontend_url);
         
-        let error_msg = urlencoding::encode("Only IIT Mandi email addresses are allowed");
-        let error_details = urlencoding::encode("Please use your @iitmandi.ac.in or @students.iitmandi.ac.in email address");
+        let error_msg = urlencoding::encode("Only institute email addresses are allowed");
+        let domains = config
+            .allowed_email_domains
+            .iter()
+            .map(|d| format!("@{}", d))
+            .collect::<Vec<_>>()
+            .join(" or ");
+        let error_details = urlencoding::encode(&format!("Please use your {} email address", domains)).into_owned();
         
         tracing::info!("REDIRECT_BUILD: error_msg (encoded) = {}", error_msg);
         tracing::info!("REDIRECT_BUILD: error_details (encoded) = {}", error_details);
@@ -176,52 +198,11 @@ pub async fn google_callback(
     }
     
     tracing::info!("EMAIL_CHECK: Email domain approved");
-    tracing::info!("DB_QUERY: Looking up user by google_id");
+    let user = find_or_create_user(&pool, &config, provider.name(), &user_info).await?;
 
-    let user = match sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE google_id = $1"
-    )
-    .bind(&user_info.sub)
-    .fetch_optional(&pool)
-    .await?
-    {
-        Some(mut existing_user) => {
-            tracing::info!("DB_QUERY: Found existing user id={}", existing_user.id);
-            sqlx::query("UPDATE users SET last_login_at = NOW() WHERE id = $1")
-                .bind(existing_user.id)
-                .execute(&pool)
-                .await?;
-            existing_user.last_login_at = Some(Utc::now());
-            tracing::info!("DB_UPDATE: Updated last_login_at for user id={}", existing_user.id);
-            existing_user
-        }
-        None => {
-            tracing::info!("DB_QUERY: No existing user found, creating new user");
-            let default_role = if user_info.email.ends_with("@students.iitmandi.ac.in") {
-                UserRole::Student
-            } else {
-                UserRole::Faculty
-            };
-            tracing::info!("USER_CREATE: Role assigned={:?}", default_role);
-
-            sqlx::query_as::<_, User>(
-                r#"
-                INSERT INTO users (email, google_id, role, status, first_name, last_name, profile_picture, last_login_at)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
-                RETURNING *
-                "#
-            )
-            .bind(&user_info.email)
-            .bind(&user_info.sub)
-            .bind(default_role)
-            .bind(UserStatus::Active)
-            .bind(&user_info.given_name)
-            .bind(&user_info.family_name.as_deref().unwrap_or(""))
-            .bind(&user_info.picture)
-            .fetch_one(&pool)
-            .await?
-        }
-    };
+    // New session id on login so a pre-login id can't be fixed onto the victim
+    session.cycle_id().await
+        .map_err(|e| AppError::Internal(e.into()))?;
 
     tracing::info!("SESSION: Inserting user_id={} into session", user.id);
     session.insert(SESSION_USER_ID_KEY, user.id).await
@@ -231,37 +212,109 @@ pub async fn google_callback(
         })?;
     tracing::info!("SESSION: Successfully stored user_id in session");
 
+    // Shown in the user's active sessions list
+    let device = SessionDevice {
+        user_agent: headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string()),
+        ip: ip.to_string(),
+    };
+    session.insert(SESSION_DEVICE_KEY, device).await
+        .map_err(|e| AppError::Internal(e.into()))?;
+
     tracing::info!("AUDIT_LOG: Recording login event");
-    sqlx::query(
-        "INSERT INTO audit_logs (user_id, action, metadata) VALUES ($1, $2, $3)"
-    )
-    .bind(user.id)
-    .bind("login")
-    .bind(serde_json::json!({"method": "google"}))
-    .execute(&pool)
-    .await?;
+    audit::record(&pool, &user, "login", None, serde_json::json!({"method": provider.name()})).await?;
     tracing::info!("AUDIT_LOG: Login event recorded");
+    metrics::counter!(monitoring::LOGINS).increment(1);
 
     tracing::info!("REDIRECT: Preparing redirect to frontend");
-    // Use state parameter to redirect to the correct frontend origin
-    let frontend_url = query.state.as_ref()
-        .and_then(|s| {
-            tracing::info!("REDIRECT: Decoding state for redirect: {}", s);
-            urlencoding::decode(s).ok()
-        })
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| {
-            let fallback = env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:4173".to_string());
-            tracing::info!("REDIRECT: Using fallback URL: {}", fallback);
-            fallback
-        });
-    
+    // Origin was checked against the allowlist when the login started
+    let frontend_url = &pending.redirect_origin;
     let redirect_url = format!("{}/dashboard", frontend_url);
     tracing::info!("REDIRECT: Redirecting to {}", redirect_url);
     tracing::info!("CALLBACK_END: Success");
     Ok(Redirect::to(&redirect_url))
 }
 
+// The account for a signed-in identity, matched by the provider's subject. An identity
+// with a new subject (e.g. after switching from Google to another OIDC provider) is
+// matched to the existing account by email, but only when the provider has verified
+// that email; the account then takes the new subject. Otherwise a new user is created.
+async fn find_or_create_user(
+    pool: &PgPool,
+    config: &AppConfig,
+    provider: &str,
+    user_info: &ExternalIdentity,
+) -> Result<User, AppError> {
+    tracing::info!("DB_QUERY: Looking up user by google_id");
+    let by_subject = sqlx::query_as::<_, User>(
+        "UPDATE users SET last_login_at = NOW() WHERE google_id = $1 RETURNING *"
+    )
+    .bind(&user_info.sub)
+    .fetch_optional(pool)
+    .await?;
+    if let Some(user) = by_subject {
+        tracing::info!("DB_QUERY: Found existing user id={}", user.id);
+        return Ok(user);
+    }
+
+    let by_email = sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = LOWER($1)")
+        .bind(&user_info.email)
+        .fetch_optional(pool)
+        .await?;
+    if let Some(existing_user) = by_email {
+        if user_info.email_verified != Some(true) {
+            tracing::warn!("LOGIN_REJECTED: Unverified email matches user id={}", existing_user.id);
+            return Err(AppError::Conflict(
+                "An account with this email exists, but the provider has not verified the email".to_string(),
+            ));
+        }
+
+        tracing::info!("DB_UPDATE: Linking new {} subject to user id={}", provider, existing_user.id);
+        let mut tx = pool.begin().await?;
+        let user = sqlx::query_as::<_, User>(
+            "UPDATE users SET google_id = $1, last_login_at = NOW() WHERE id = $2 RETURNING *"
+        )
+        .bind(&user_info.sub)
+        .bind(existing_user.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        audit::record(
+            &mut *tx,
+            &user,
+            "identity_relinked",
+            Some(Target::User(user.id)),
+            serde_json::json!({ "provider": provider }),
+        )
+        .await?;
+        tx.commit().await?;
+        return Ok(user);
+    }
+
+    tracing::info!("DB_QUERY: No existing user found, creating new user");
+    let default_role = config.role_for_email(&user_info.email);
+    tracing::info!("USER_CREATE: Role assigned={:?}", default_role);
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (email, google_id, role, status, first_name, last_name, profile_picture, last_login_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
+        RETURNING *
+        "#
+    )
+    .bind(&user_info.email)
+    .bind(&user_info.sub)
+    .bind(default_role)
+    .bind(UserStatus::Active)
+    .bind(&user_info.given_name)
+    .bind(user_info.family_name.as_deref().unwrap_or(""))
+    .bind(&user_info.picture)
+    .fetch_one(pool)
+    .await?;
+    Ok(user)
+}
+
 pub async fn logout(
     session: TowerSession,
     State(pool): State<PgPool>,
@@ -269,13 +322,7 @@ pub async fn logout(
     if let Some(user_id) = session.get::<Uuid>(SESSION_USER_ID_KEY).await
         .map_err(|e| AppError::Internal(e.into()))? 
     {
-        sqlx::query(
-            "INSERT INTO audit_logs (user_id, action) VALUES ($1, $2)"
-        )
-        .bind(user_id)
-        .bind("logout")
-        .execute(&pool)
-        .await?;
+        audit::record(&pool, Actor::User(user_id), "logout", None, serde_json::Value::Null).await?;
     }
 
     session.delete().await
@@ -285,49 +332,93 @@ pub async fn logout(
         success: true,
         data: None,
         message: Some("Logged out successfully".to_string()),
+        error: None,
     }))
 }
 
 pub async fn get_current_user(
-    session: TowerSession,
     State(pool): State<PgPool>,
-) -> Result<Json<ApiResponse<UserResponse>>, AppError> {
-    tracing::info!("GET_CURRENT_USER: Request received");
-    tracing::info!("GET_CURRENT_USER: Attempting to retrieve user_id from session");
-    
-    let user_id_result = session.get::<Uuid>(SESSION_USER_ID_KEY).await;
-    tracing::info!("GET_CURRENT_USER: Session get result: {:?}", user_id_result);
-    
-    let user_id = user_id_result
-        .map_err(|e| {
-            tracing::error!("GET_CURRENT_USER: Session error: {}", e);
-            AppError::Internal(e.into())
-        })?
-        .ok_or_else(|| {
-            tracing::warn!("GET_CURRENT_USER: No user_id in session, user not authenticated");
-            AppError::HttpError(StatusCode::UNAUTHORIZED, anyhow::anyhow!("Not authenticated"))
-        })?;
-
-    tracing::info!("GET_CURRENT_USER: Found user_id in session: {}", user_id);
-    tracing::info!("GET_CURRENT_USER: Fetching user from database");
-    
-    let user = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE id = $1"
-    )
-    .bind(user_id)
-    .fetch_one(&pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("GET_CURRENT_USER: Database error: {}", e);
-        e
-    })?;
-
+    AuthUser(user): AuthUser,
+    session: TowerSession,
+) -> Result<Json<ApiResponse<CurrentUserResponse>>, AppError> {
     tracing::info!("GET_CURRENT_USER: User found: email={}", user.email);
-    tracing::info!("GET_CURRENT_USER: Returning user data");
-    
+
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(UserResponse::from(user)),
+        data: Some(CurrentUserResponse {
+            user: UserResponse::from(user),
+            impersonation: impersonation::current(&pool, &session).await?,
+        }),
         message: None,
+        error: None,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::UserRole;
+    use crate::test_support::TestApp;
+
+    fn identity(sub: &str, email: &str, email_verified: Option<bool>) -> ExternalIdentity {
+        ExternalIdentity {
+            sub: sub.to_string(),
+            email: email.to_string(),
+            email_verified,
+            given_name: "Student".to_string(),
+            family_name: None,
+            picture: None,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs Postgres: set TEST_DATABASE_URL or DATABASE_URL"]
+    async fn new_subject_with_verified_email_keeps_the_account() {
+        let app = TestApp::spawn().await;
+        let student = app.user(UserRole::Student).clone();
+        let switched = identity("oidc-1234", &student.email.to_uppercase(), Some(true));
+
+        let user = find_or_create_user(&app.pool, &app.config, "oidc", &switched)
+            .await
+            .unwrap();
+        assert_eq!(user.id, student.id);
+        assert_eq!(user.google_id, "oidc-1234");
+
+        // From now on the new subject finds the account directly
+        let again = find_or_create_user(&app.pool, &app.config, "oidc", &switched)
+            .await
+            .unwrap();
+        assert_eq!(again.id, student.id);
+
+        let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(users, 4);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs Postgres: set TEST_DATABASE_URL or DATABASE_URL"]
+    async fn new_subject_with_unverified_email_is_refused() {
+        let app = TestApp::spawn().await;
+        let student = app.user(UserRole::Student).clone();
+
+        for email_verified in [None, Some(false)] {
+            let result = find_or_create_user(
+                &app.pool,
+                &app.config,
+                "oidc",
+                &identity("oidc-5678", &student.email, email_verified),
+            )
+            .await;
+            assert!(matches!(result, Err(AppError::Conflict(_))));
+        }
+
+        let google_id: String = sqlx::query_scalar("SELECT google_id FROM users WHERE id = $1")
+            .bind(student.id)
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(google_id, student.google_id);
+    }
+}
@@ -0,0 +1,32 @@
+use clap::{Parser, Subcommand};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Aegis backend server")]
+pub struct Cli {
+    // Defaults to `serve` when omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the HTTP server
+    Serve,
+    /// Manage the database schema
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+    /// Check the audit log hash chain for missing or altered entries
+    VerifyAudit,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateAction {
+    /// Apply all pending migrations
+    Up,
+    /// List migrations and whether they have been applied
+    Status,
+    /// Revert the most recently applied migration
+    Down,
+}
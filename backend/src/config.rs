@@ -0,0 +1,1202 @@
+use crate::cloudinary::CloudinaryConfig;
+use crate::structs::UserRole;
+use anyhow::Context;
+use http::HeaderValue;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+use tower_sessions::cookie::SameSite;
+
+// Path of the optional TOML config file; values from the environment take precedence over it
+const CONFIG_FILE_ENV: &str = "APP_CONFIG_FILE";
+
+const DEFAULT_PORT: u16 = 8000;
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_FRONTEND_URL: &str = "http://localhost:4173";
+const DEFAULT_COOKIE_NAME: &str = "aegis_session";
+const DEFAULT_EMAIL_DOMAINS: [&str; 2] = ["iitmandi.ac.in", "students.iitmandi.ac.in"];
+// Without ROLE_RULES: student addresses get Student, everyone else DEFAULT_ROLE
+const DEFAULT_STUDENT_DOMAIN: &str = "students.iitmandi.ac.in";
+const DEFAULT_ROLE: UserRole = UserRole::Faculty;
+const DEFAULT_MAX_PHOTO_BYTES: usize = 5 * 1024 * 1024;
+const DEFAULT_MAX_PHOTOS: usize = 5;
+const DEFAULT_SERVICE_NAME: &str = "aegis-backend";
+const DEFAULT_CLOUDINARY_API_URL: &str = "https://api.cloudinary.com";
+const MIN_METRICS_TOKEN_LEN: usize = 32;
+
+const DEFAULT_RATE_LIMIT: RateLimitRule = RateLimitRule {
+    per_minute: 300,
+    burst: 60,
+};
+const GRIEVANCE_CREATE_RATE_LIMIT: RateLimitRule = RateLimitRule {
+    per_minute: 5,
+    burst: 5,
+};
+const UPVOTES_RATE_LIMIT: RateLimitRule = RateLimitRule {
+    per_minute: 30,
+    burst: 10,
+};
+// Each upload costs Cloudinary quota
+const UPLOADS_RATE_LIMIT: RateLimitRule = RateLimitRule {
+    per_minute: 10,
+    burst: 5,
+};
+
+// ============================================================================
+// TYPED CONFIGURATION
+// ============================================================================
+
+// Everything the server needs to run, loaded and validated once at startup
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub app_env: AppEnv,
+    pub database_url: String,
+    // Apply pending migrations at startup; when off, startup only checks the schema is current
+    pub auto_migrate: bool,
+    pub port: u16,
+    // How long in-flight requests may take to finish after SIGTERM
+    pub shutdown_timeout: Duration,
+    // Default redirect target after login when the request carries no origin
+    pub frontend_url: String,
+    pub cors_origins: Vec<HeaderValue>,
+    // Origins the login flow may send the browser back to; anything else is rejected
+    pub redirect_origins: Vec<String>,
+    pub identity_provider: IdentityProviderConfig,
+    // Photo uploads are disabled when Cloudinary isn't configured
+    pub cloudinary: Option<CloudinaryConfig>,
+    pub cookie: CookieConfig,
+    // Lowercase domains (without '@') allowed to sign in
+    pub allowed_email_domains: Vec<String>,
+    // Checked in order on first login; the first match decides the new user's role
+    pub role_rules: Vec<RoleRule>,
+    // Role for new users no rule matches
+    pub default_role: UserRole,
+    pub uploads: UploadConfig,
+    pub telemetry: TelemetryConfig,
+    pub rate_limits: RateLimitConfig,
+    // Bearer token Prometheus must send to `/metrics`; the endpoint is off when unset
+    pub metrics_token: Option<String>,
+}
+
+// Deployment mode from APP_ENV. Anything that lets a user skip real sign-in or
+// change their own role is refused unless this is Development.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppEnv {
+    Development,
+    Production,
+}
+
+// Where users sign in, chosen with AUTH_PROVIDER
+#[derive(Debug, Clone)]
+pub enum IdentityProviderConfig {
+    Google(OAuthClientConfig),
+    // Any OpenID Connect issuer, endpoints come from its discovery document
+    Oidc {
+        issuer_url: String,
+        client: OAuthClientConfig,
+        // Treat a missing `email_verified` claim as verified, for issuers that leave it
+        // out but only hand out addresses they own
+        assume_email_verified: bool,
+    },
+    // Local development and tests: log in as an existing user with no external calls.
+    // Only compiled into `dev-routes` and test builds, like the dev routes.
+    #[cfg(any(test, feature = "dev-routes"))]
+    Dev,
+}
+
+#[derive(Debug, Clone)]
+pub struct OAuthClientConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CookieConfig {
+    pub name: String,
+    pub secure: bool,
+    pub same_site: SameSite,
+    pub domain: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RoleRule {
+    pub role: UserRole,
+    pub matcher: EmailMatcher,
+}
+
+#[derive(Debug, Clone)]
+pub enum EmailMatcher {
+    // Lowercase domain without '@'
+    Domain(String),
+    // Case-insensitive, matched against the whole address
+    Pattern(Regex),
+    // Lowercase addresses
+    Emails(Vec<String>),
+}
+
+impl EmailMatcher {
+    fn matches(&self, email: &str) -> bool {
+        match self {
+            EmailMatcher::Domain(domain) => email.ends_with(&format!("@{}", domain)),
+            EmailMatcher::Pattern(pattern) => pattern.is_match(email),
+            EmailMatcher::Emails(emails) => emails.iter().any(|e| e == email),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UploadConfig {
+    pub max_photo_bytes: usize,
+    pub max_photos: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    // Key anonymous clients by the first X-Forwarded-For address; only enable behind a trusted proxy
+    pub trust_forwarded_for: bool,
+    pub default: RateLimitRule,
+    pub grievance_create: RateLimitRule,
+    pub upvotes: RateLimitRule,
+    pub uploads: RateLimitRule,
+}
+
+// Token bucket: refills at `per_minute`, holds at most `burst` tokens
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitRule {
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    pub log_format: LogFormat,
+    // Base URL of an OTLP/HTTP collector (e.g. http://localhost:4318); tracing export is off when unset
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+impl AppConfig {
+    // Load from the TOML file named by APP_CONFIG_FILE (if any), then apply env overrides
+    pub fn load() -> anyhow::Result<Self> {
+        RawConfig::load()?.validate()
+    }
+
+    // Logging settings only, so telemetry can start before the rest of the config is validated
+    pub fn load_telemetry() -> anyhow::Result<TelemetryConfig> {
+        let mut problems = Vec::new();
+        let telemetry = RawConfig::load()?.telemetry.validate(&mut problems);
+        report_problems(problems)?;
+        Ok(telemetry)
+    }
+
+    // From the given variables alone, ignoring the environment and APP_CONFIG_FILE
+    #[cfg(test)]
+    pub fn from_vars(vars: &[(&str, &str)]) -> anyhow::Result<Self> {
+        let mut raw = RawConfig::default();
+        raw.apply_env(|key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        });
+        raw.validate()
+    }
+
+    // Just the database URL, for subcommands that don't run the server
+    pub fn load_database_url() -> anyhow::Result<String> {
+        RawConfig::load()?
+            .database_url
+            .filter(|url| !url.trim().is_empty())
+            .ok_or_else(|| anyhow::anyhow!("DATABASE_URL must be set"))
+    }
+
+    pub fn is_allowed_email(&self, email: &str) -> bool {
+        let email = email.to_lowercase();
+        self.allowed_email_domains
+            .iter()
+            .any(|domain| email.ends_with(&format!("@{}", domain)))
+    }
+
+    // Role given to a user the first time they sign in
+    pub fn role_for_email(&self, email: &str) -> UserRole {
+        let email = email.to_lowercase();
+        self.role_rules
+            .iter()
+            .find(|rule| rule.matcher.matches(&email))
+            .map(|rule| rule.role.clone())
+            .unwrap_or_else(|| self.default_role.clone())
+    }
+}
+
+// ============================================================================
+// RAW (UNVALIDATED) CONFIGURATION
+// ============================================================================
+
+// Mirrors the TOML layout; every field is optional until validation
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawConfig {
+    app_env: Option<String>,
+    database_url: Option<String>,
+    auto_migrate: Option<String>,
+    port: Option<String>,
+    shutdown_timeout_secs: Option<String>,
+    frontend_url: Option<String>,
+    cors_origins: Option<Vec<String>>,
+    redirect_origins: Option<Vec<String>>,
+    allowed_email_domains: Option<Vec<String>>,
+    role_rules: Option<Vec<RawRoleRule>>,
+    default_role: Option<String>,
+    // ROLE_RULES from the environment, a JSON array parsed during validation
+    #[serde(skip)]
+    role_rules_json: Option<String>,
+    auth_provider: Option<String>,
+    google: RawOAuthClient,
+    oidc: RawOidc,
+    cloudinary: RawCloudinary,
+    cookie: RawCookie,
+    uploads: RawUploads,
+    telemetry: RawTelemetry,
+    rate_limits: RawRateLimits,
+    metrics_token: Option<String>,
+}
+
+// Exactly one of `domain`, `pattern` or `emails` must be set
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRoleRule {
+    role: String,
+    domain: Option<String>,
+    pattern: Option<String>,
+    emails: Option<Vec<String>>,
+}
+
+impl RawRoleRule {
+    fn validate(self, problems: &mut Vec<String>, index: usize) -> Option<RoleRule> {
+        let name = format!("ROLE_RULES[{}]", index);
+        let role = parse_role(problems, &self.role, &name);
+        let matcher = match (self.domain, self.pattern, self.emails) {
+            (Some(domain), None, None) => {
+                EmailMatcher::Domain(domain.trim().trim_start_matches('@').to_lowercase())
+            }
+            (None, Some(pattern), None) => {
+                // Anchored so a pattern can't match just part of an address
+                match RegexBuilder::new(&format!("^(?:{})$", pattern))
+                    .case_insensitive(true)
+                    .build()
+                {
+                    Ok(regex) => EmailMatcher::Pattern(regex),
+                    Err(e) => {
+                        problems.push(format!("{} has an invalid pattern: {}", name, e));
+                        return None;
+                    }
+                }
+            }
+            (None, None, Some(emails)) => EmailMatcher::Emails(
+                emails.iter().map(|e| e.trim().to_lowercase()).collect(),
+            ),
+            _ => {
+                problems.push(format!(
+                    "{} must set exactly one of domain, pattern or emails",
+                    name
+                ));
+                return None;
+            }
+        };
+        Some(RoleRule {
+            role: role?,
+            matcher,
+        })
+    }
+}
+
+fn parse_role(problems: &mut Vec<String>, value: &str, name: &str) -> Option<UserRole> {
+    match value.trim().to_lowercase().as_str() {
+        "student" => Some(UserRole::Student),
+        "faculty" => Some(UserRole::Faculty),
+        "authority" => Some(UserRole::Authority),
+        "admin" => Some(UserRole::Admin),
+        other => {
+            problems.push(format!(
+                "{} role must be student, faculty, authority or admin, got {:?}",
+                name, other
+            ));
+            None
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawOAuthClient {
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    redirect_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawOidc {
+    issuer_url: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    redirect_url: Option<String>,
+    assume_email_verified: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawCloudinary {
+    cloud_name: Option<String>,
+    api_key: Option<String>,
+    api_secret: Option<String>,
+    api_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawCookie {
+    name: Option<String>,
+    secure: Option<String>,
+    same_site: Option<String>,
+    domain: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawUploads {
+    max_photo_bytes: Option<String>,
+    max_photos: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawRateLimits {
+    enabled: Option<String>,
+    trust_forwarded_for: Option<String>,
+    default: RawRateLimitRule,
+    grievance_create: RawRateLimitRule,
+    upvotes: RawRateLimitRule,
+    uploads: RawRateLimitRule,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawRateLimitRule {
+    per_minute: Option<String>,
+    burst: Option<String>,
+}
+
+impl RawRateLimitRule {
+    fn validate(
+        self,
+        problems: &mut Vec<String>,
+        group: &str,
+        default: RateLimitRule,
+    ) -> RateLimitRule {
+        let prefix = format!("RATE_LIMIT_{}", group.to_uppercase());
+        let rule = RateLimitRule {
+            per_minute: parse_or(
+                problems,
+                self.per_minute,
+                &format!("{}_PER_MINUTE", prefix),
+                default.per_minute,
+            ),
+            burst: parse_or(
+                problems,
+                self.burst,
+                &format!("{}_BURST", prefix),
+                default.burst,
+            ),
+        };
+        if rule.per_minute == 0 || rule.burst == 0 {
+            problems.push(format!("{} limits must be greater than zero", prefix));
+        }
+        rule
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawTelemetry {
+    log_format: Option<String>,
+    otlp_endpoint: Option<String>,
+    service_name: Option<String>,
+}
+
+impl RawTelemetry {
+    fn validate(self, problems: &mut Vec<String>) -> TelemetryConfig {
+        let log_format = match self.log_format.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("text") => LogFormat::Text,
+            Some("json") => LogFormat::Json,
+            Some(other) => {
+                problems.push(format!("LOG_FORMAT must be text or json, got {:?}", other));
+                LogFormat::Text
+            }
+        };
+
+        let otlp_endpoint = self
+            .otlp_endpoint
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| url.trim_end_matches('/').to_string());
+        if let Some(url) = &otlp_endpoint {
+            if !is_http_origin(url) {
+                problems.push(format!(
+                    "OTEL_EXPORTER_OTLP_ENDPOINT must be an http(s) URL, got {:?}",
+                    url
+                ));
+            }
+        }
+
+        TelemetryConfig {
+            log_format,
+            otlp_endpoint,
+            service_name: self
+                .service_name
+                .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string()),
+        }
+    }
+}
+
+// Numbers and booleans are read as strings so TOML and env values share one parser
+fn scalars_to_strings(value: toml::Value) -> toml::Value {
+    match value {
+        toml::Value::Table(table) => toml::Value::Table(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, scalars_to_strings(v)))
+                .collect(),
+        ),
+        value @ (toml::Value::String(_) | toml::Value::Array(_)) => value,
+        other => toml::Value::String(other.to_string()),
+    }
+}
+
+impl RawConfig {
+    fn load() -> anyhow::Result<Self> {
+        let mut raw = match std::env::var(CONFIG_FILE_ENV) {
+            Ok(path) => Self::from_file(Path::new(&path))?,
+            Err(_) => Self::default(),
+        };
+        raw.apply_env(|key| std::env::var(key).ok());
+        Ok(raw)
+    }
+
+    fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        Self::from_toml(&contents)
+            .with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    fn from_toml(contents: &str) -> anyhow::Result<Self> {
+        let table: toml::Table = toml::from_str(contents)?;
+        Ok(scalars_to_strings(toml::Value::Table(table)).try_into()?)
+    }
+
+    fn apply_env(&mut self, get: impl Fn(&str) -> Option<String>) {
+        let set = |slot: &mut Option<String>, key: &str| {
+            if let Some(value) = get(key) {
+                *slot = Some(value);
+            }
+        };
+
+        set(&mut self.app_env, "APP_ENV");
+        set(&mut self.database_url, "DATABASE_URL");
+        set(&mut self.auto_migrate, "AUTO_MIGRATE");
+        set(&mut self.port, "PORT");
+        set(&mut self.shutdown_timeout_secs, "SHUTDOWN_TIMEOUT_SECS");
+        set(&mut self.frontend_url, "FRONTEND_URL");
+        set(&mut self.auth_provider, "AUTH_PROVIDER");
+        set(&mut self.google.client_id, "GOOGLE_CLIENT_ID");
+        set(&mut self.google.client_secret, "GOOGLE_CLIENT_SECRET");
+        set(&mut self.google.redirect_url, "GOOGLE_REDIRECT_URL");
+        set(&mut self.oidc.issuer_url, "OIDC_ISSUER_URL");
+        set(&mut self.oidc.client_id, "OIDC_CLIENT_ID");
+        set(&mut self.oidc.client_secret, "OIDC_CLIENT_SECRET");
+        set(&mut self.oidc.redirect_url, "OIDC_REDIRECT_URL");
+        set(
+            &mut self.oidc.assume_email_verified,
+            "OIDC_ASSUME_EMAIL_VERIFIED",
+        );
+        set(&mut self.cloudinary.cloud_name, "CLOUDINARY_CLOUD_NAME");
+        set(&mut self.cloudinary.api_key, "CLOUDINARY_API_KEY");
+        set(&mut self.cloudinary.api_secret, "CLOUDINARY_API_SECRET");
+        set(&mut self.cloudinary.api_url, "CLOUDINARY_API_URL");
+        set(&mut self.cookie.name, "COOKIE_NAME");
+        set(&mut self.cookie.secure, "COOKIE_SECURE");
+        set(&mut self.cookie.same_site, "COOKIE_SAME_SITE");
+        set(&mut self.cookie.domain, "COOKIE_DOMAIN");
+        set(&mut self.uploads.max_photo_bytes, "UPLOAD_MAX_PHOTO_BYTES");
+        set(&mut self.uploads.max_photos, "UPLOAD_MAX_PHOTOS");
+        set(&mut self.rate_limits.enabled, "RATE_LIMIT_ENABLED");
+        set(
+            &mut self.rate_limits.trust_forwarded_for,
+            "RATE_LIMIT_TRUST_FORWARDED_FOR",
+        );
+        for (group, rule) in [
+            ("DEFAULT", &mut self.rate_limits.default),
+            ("GRIEVANCE_CREATE", &mut self.rate_limits.grievance_create),
+            ("UPVOTES", &mut self.rate_limits.upvotes),
+            ("UPLOADS", &mut self.rate_limits.uploads),
+        ] {
+            set(
+                &mut rule.per_minute,
+                &format!("RATE_LIMIT_{}_PER_MINUTE", group),
+            );
+            set(&mut rule.burst, &format!("RATE_LIMIT_{}_BURST", group));
+        }
+        set(&mut self.telemetry.log_format, "LOG_FORMAT");
+        set(&mut self.telemetry.otlp_endpoint, "OTEL_EXPORTER_OTLP_ENDPOINT");
+        set(&mut self.telemetry.service_name, "OTEL_SERVICE_NAME");
+        set(&mut self.metrics_token, "METRICS_TOKEN");
+
+        // Lists are comma-separated in the environment
+        if let Some(value) = get("CORS_ORIGINS") {
+            self.cors_origins = Some(split_list(&value));
+        }
+        if let Some(value) = get("AUTH_REDIRECT_ORIGINS") {
+            self.redirect_origins = Some(split_list(&value));
+        }
+        if let Some(value) = get("ALLOWED_EMAIL_DOMAINS") {
+            self.allowed_email_domains = Some(split_list(&value));
+        }
+        set(&mut self.default_role, "DEFAULT_ROLE");
+        set(&mut self.role_rules_json, "ROLE_RULES");
+    }
+
+    // Check every field and report all problems at once rather than the first one
+    fn validate(self) -> anyhow::Result<AppConfig> {
+        let mut problems = Vec::new();
+
+        // Unset means production so a forgotten variable never opens up dev features
+        let app_env = match self.app_env.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("production") => AppEnv::Production,
+            Some("development") => AppEnv::Development,
+            Some(other) => {
+                problems.push(format!(
+                    "APP_ENV must be development or production, got {:?}",
+                    other
+                ));
+                AppEnv::Production
+            }
+        };
+
+        let oidc_assume_email_verified = parse_or(
+            &mut problems,
+            self.oidc.assume_email_verified,
+            "OIDC_ASSUME_EMAIL_VERIFIED",
+            false,
+        );
+        let mut required = |value: Option<String>, name: &str| match value {
+            Some(v) if !v.trim().is_empty() => v,
+            _ => {
+                problems.push(format!("{} must be set", name));
+                String::new()
+            }
+        };
+
+        let database_url = required(self.database_url, "DATABASE_URL");
+        // Only the selected provider's settings are required
+        let identity_provider = match self.auth_provider.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("google") => IdentityProviderConfig::Google(OAuthClientConfig {
+                client_id: required(self.google.client_id, "GOOGLE_CLIENT_ID"),
+                client_secret: required(self.google.client_secret, "GOOGLE_CLIENT_SECRET"),
+                redirect_url: required(self.google.redirect_url, "GOOGLE_REDIRECT_URL"),
+            }),
+            Some("oidc") => IdentityProviderConfig::Oidc {
+                issuer_url: required(self.oidc.issuer_url, "OIDC_ISSUER_URL")
+                    .trim_end_matches('/')
+                    .to_string(),
+                client: OAuthClientConfig {
+                    client_id: required(self.oidc.client_id, "OIDC_CLIENT_ID"),
+                    client_secret: required(self.oidc.client_secret, "OIDC_CLIENT_SECRET"),
+                    redirect_url: required(self.oidc.redirect_url, "OIDC_REDIRECT_URL"),
+                },
+                assume_email_verified: oidc_assume_email_verified,
+            },
+            #[cfg(any(test, feature = "dev-routes"))]
+            Some("dev") => IdentityProviderConfig::Dev,
+            #[cfg(not(any(test, feature = "dev-routes")))]
+            Some("dev") => {
+                problems.push(
+                    "AUTH_PROVIDER=dev is only available in builds with the dev-routes feature"
+                        .to_string(),
+                );
+                unusable_provider()
+            }
+            Some(other) => {
+                problems.push(format!(
+                    "AUTH_PROVIDER must be google, oidc or dev, got {:?}",
+                    other
+                ));
+                unusable_provider()
+            }
+        };
+        #[cfg(any(test, feature = "dev-routes"))]
+        if matches!(identity_provider, IdentityProviderConfig::Dev) && app_env != AppEnv::Development {
+            problems.push("AUTH_PROVIDER=dev requires APP_ENV=development".to_string());
+        }
+        if let IdentityProviderConfig::Oidc { issuer_url, .. } = &identity_provider {
+            if !issuer_url.is_empty() && !is_http_origin(issuer_url) {
+                problems.push(format!(
+                    "OIDC_ISSUER_URL must be an http(s) URL, got {:?}",
+                    issuer_url
+                ));
+            }
+        }
+
+        let auto_migrate = parse_or(&mut problems, self.auto_migrate, "AUTO_MIGRATE", true);
+        let port = parse_or(&mut problems, self.port, "PORT", DEFAULT_PORT);
+        let shutdown_timeout = Duration::from_secs(parse_or(
+            &mut problems,
+            self.shutdown_timeout_secs,
+            "SHUTDOWN_TIMEOUT_SECS",
+            DEFAULT_SHUTDOWN_TIMEOUT_SECS,
+        ));
+
+        let frontend_url = self
+            .frontend_url
+            .unwrap_or_else(|| DEFAULT_FRONTEND_URL.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        if !is_http_origin(&frontend_url) {
+            problems.push(format!(
+                "FRONTEND_URL must be an http(s) URL, got {:?}",
+                frontend_url
+            ));
+        }
+
+        let origins = self.cors_origins.unwrap_or_else(|| {
+            let mut origins = vec![DEFAULT_FRONTEND_URL.to_string()];
+            if frontend_url != DEFAULT_FRONTEND_URL {
+                origins.push(frontend_url.clone());
+            }
+            origins
+        });
+        let mut cors_origins = Vec::new();
+        for origin in &origins {
+            let origin = origin.trim_end_matches('/');
+            match HeaderValue::from_str(origin) {
+                Ok(value) if is_http_origin(origin) => cors_origins.push(value),
+                _ => problems.push(format!(
+                    "CORS_ORIGINS contains an invalid origin {:?}",
+                    origin
+                )),
+            }
+        }
+
+        // By default the frontends allowed to call the API are the ones a login may return to.
+        // FRONTEND_URL is always allowed since it's the fallback target.
+        let mut redirect_origins = Vec::new();
+        for origin in self.redirect_origins.unwrap_or(origins) {
+            let origin = origin.trim_end_matches('/').to_string();
+            if !is_http_origin(&origin) {
+                problems.push(format!(
+                    "AUTH_REDIRECT_ORIGINS contains an invalid origin {:?}",
+                    origin
+                ));
+            }
+            redirect_origins.push(origin);
+        }
+        if !redirect_origins.contains(&frontend_url) {
+            redirect_origins.push(frontend_url.clone());
+        }
+
+        let cloudinary_api_url = self
+            .cloudinary
+            .api_url
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| DEFAULT_CLOUDINARY_API_URL.to_string());
+        if !is_http_origin(&cloudinary_api_url) {
+            problems.push(format!(
+                "CLOUDINARY_API_URL must be an http(s) URL, got {:?}",
+                cloudinary_api_url
+            ));
+        }
+        let cloudinary = match (
+            self.cloudinary.cloud_name,
+            self.cloudinary.api_key,
+            self.cloudinary.api_secret,
+        ) {
+            (Some(cloud_name), Some(api_key), Some(api_secret)) => Some(CloudinaryConfig {
+                cloud_name,
+                api_key,
+                api_secret,
+                api_url: cloudinary_api_url,
+            }),
+            (None, None, None) => None,
+            _ => {
+                problems.push(
+                    "CLOUDINARY_CLOUD_NAME, CLOUDINARY_API_KEY and CLOUDINARY_API_SECRET must be set together"
+                        .to_string(),
+                );
+                None
+            }
+        };
+
+        // Defaults match what browsers received before this was configurable:
+        // a cross-site cookie, since the frontend and API live on different origins
+        let same_site = match self
+            .cookie
+            .same_site
+            .as_deref()
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            None | Some("none") => SameSite::None,
+            Some("lax") => SameSite::Lax,
+            Some("strict") => SameSite::Strict,
+            Some(other) => {
+                problems.push(format!(
+                    "COOKIE_SAME_SITE must be one of none, lax, strict, got {:?}",
+                    other
+                ));
+                SameSite::None
+            }
+        };
+        let secure = parse_or(&mut problems, self.cookie.secure, "COOKIE_SECURE", true);
+        if same_site == SameSite::None && !secure {
+            problems.push("COOKIE_SECURE must be true when COOKIE_SAME_SITE is none".to_string());
+        }
+        let cookie = CookieConfig {
+            name: self
+                .cookie
+                .name
+                .unwrap_or_else(|| DEFAULT_COOKIE_NAME.to_string()),
+            secure,
+            same_site,
+            domain: self.cookie.domain.filter(|d| !d.trim().is_empty()),
+        };
+
+        let allowed_email_domains: Vec<String> = self
+            .allowed_email_domains
+            .unwrap_or_else(|| {
+                DEFAULT_EMAIL_DOMAINS
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect()
+            })
+            .into_iter()
+            .map(|d| d.trim().trim_start_matches('@').to_lowercase())
+            .filter(|d| !d.is_empty())
+            .collect();
+        if allowed_email_domains.is_empty() {
+            problems.push("ALLOWED_EMAIL_DOMAINS must list at least one domain".to_string());
+        }
+
+        let raw_rules = match self.role_rules_json {
+            Some(json) => Some(serde_json::from_str::<Vec<RawRoleRule>>(&json).unwrap_or_else(|e| {
+                problems.push(format!("ROLE_RULES must be a JSON array of rules: {}", e));
+                Vec::new()
+            })),
+            None => self.role_rules,
+        };
+        let role_rules = match raw_rules {
+            Some(rules) => rules
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, rule)| rule.validate(&mut problems, i))
+                .collect(),
+            None => vec![RoleRule {
+                role: UserRole::Student,
+                matcher: EmailMatcher::Domain(DEFAULT_STUDENT_DOMAIN.to_string()),
+            }],
+        };
+        let default_role = match self.default_role {
+            Some(role) => parse_role(&mut problems, &role, "DEFAULT_ROLE").unwrap_or(DEFAULT_ROLE),
+            None => DEFAULT_ROLE,
+        };
+
+        let uploads = UploadConfig {
+            max_photo_bytes: parse_or(
+                &mut problems,
+                self.uploads.max_photo_bytes,
+                "UPLOAD_MAX_PHOTO_BYTES",
+                DEFAULT_MAX_PHOTO_BYTES,
+            ),
+            max_photos: parse_or(
+                &mut problems,
+                self.uploads.max_photos,
+                "UPLOAD_MAX_PHOTOS",
+                DEFAULT_MAX_PHOTOS,
+            ),
+        };
+        if uploads.max_photo_bytes == 0 || uploads.max_photos == 0 {
+            problems.push("Upload limits must be greater than zero".to_string());
+        }
+
+        let telemetry = self.telemetry.validate(&mut problems);
+
+        let raw_limits = self.rate_limits;
+        let rate_limits = RateLimitConfig {
+            enabled: parse_or(
+                &mut problems,
+                raw_limits.enabled,
+                "RATE_LIMIT_ENABLED",
+                true,
+            ),
+            trust_forwarded_for: parse_or(
+                &mut problems,
+                raw_limits.trust_forwarded_for,
+                "RATE_LIMIT_TRUST_FORWARDED_FOR",
+                false,
+            ),
+            default: raw_limits
+                .default
+                .validate(&mut problems, "default", DEFAULT_RATE_LIMIT),
+            grievance_create: raw_limits.grievance_create.validate(
+                &mut problems,
+                "grievance_create",
+                GRIEVANCE_CREATE_RATE_LIMIT,
+            ),
+            upvotes: raw_limits
+                .upvotes
+                .validate(&mut problems, "upvotes", UPVOTES_RATE_LIMIT),
+            uploads: raw_limits
+                .uploads
+                .validate(&mut problems, "uploads", UPLOADS_RATE_LIMIT),
+        };
+
+        let metrics_token = self.metrics_token.filter(|token| !token.trim().is_empty());
+        if metrics_token
+            .as_ref()
+            .is_some_and(|token| token.len() < MIN_METRICS_TOKEN_LEN)
+        {
+            problems.push(format!(
+                "METRICS_TOKEN must be at least {} characters",
+                MIN_METRICS_TOKEN_LEN
+            ));
+        }
+
+        report_problems(problems)?;
+
+        Ok(AppConfig {
+            app_env,
+            database_url,
+            auto_migrate,
+            port,
+            shutdown_timeout,
+            frontend_url,
+            cors_origins,
+            redirect_origins,
+            identity_provider,
+            cloudinary,
+            cookie,
+            allowed_email_domains,
+            role_rules,
+            default_role,
+            uploads,
+            telemetry,
+            rate_limits,
+            metrics_token,
+        })
+    }
+}
+
+// Stands in for an invalid AUTH_PROVIDER; validation fails before it is used
+fn unusable_provider() -> IdentityProviderConfig {
+    IdentityProviderConfig::Google(OAuthClientConfig {
+        client_id: String::new(),
+        client_secret: String::new(),
+        redirect_url: String::new(),
+    })
+}
+
+fn report_problems(problems: Vec<String>) -> anyhow::Result<()> {
+    if problems.is_empty() {
+        return Ok(());
+    }
+    anyhow::bail!("Invalid configuration:\n  - {}", problems.join("\n  - "))
+}
+
+fn parse_or<T: std::str::FromStr>(
+    problems: &mut Vec<String>,
+    value: Option<String>,
+    name: &str,
+    default: T,
+) -> T {
+    match value {
+        None => default,
+        Some(v) => v.trim().parse().unwrap_or_else(|_| {
+            problems.push(format!("{} has an invalid value {:?}", name, v));
+            default
+        }),
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn is_http_origin(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The smallest valid configuration, with `vars` taking precedence
+    fn load(vars: &[(&str, &str)]) -> anyhow::Result<AppConfig> {
+        let mut all = vars.to_vec();
+        all.extend([
+            ("APP_ENV", "development"),
+            ("AUTH_PROVIDER", "dev"),
+            ("DATABASE_URL", "postgres://localhost/aegis"),
+        ]);
+        AppConfig::from_vars(&all)
+    }
+
+    fn assert_rejected(vars: &[(&str, &str)], problems: &[&str]) {
+        let err = load(vars)
+            .expect_err("config should be rejected")
+            .to_string();
+        for problem in problems {
+            assert!(err.contains(problem), "{:?} missing from {}", problem, err);
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        let config = load(&[]).unwrap();
+        assert_eq!(config.port, DEFAULT_PORT);
+        assert_eq!(config.cookie.same_site, SameSite::None);
+        assert!(config.cookie.secure);
+        assert!(config.rate_limits.enabled);
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        assert_rejected(
+            &[
+                ("DATABASE_URL", ""),
+                ("PORT", "eighty"),
+                ("LOG_FORMAT", "xml"),
+            ],
+            &[
+                "DATABASE_URL must be set",
+                "PORT has an invalid value",
+                "LOG_FORMAT must be text or json",
+            ],
+        );
+    }
+
+    #[test]
+    fn same_site_none_needs_a_secure_cookie() {
+        assert_rejected(
+            &[("COOKIE_SAME_SITE", "none"), ("COOKIE_SECURE", "false")],
+            &["COOKIE_SECURE must be true"],
+        );
+
+        let config = load(&[("COOKIE_SAME_SITE", "lax"), ("COOKIE_SECURE", "false")]).unwrap();
+        assert_eq!(config.cookie.same_site, SameSite::Lax);
+        assert!(!config.cookie.secure);
+    }
+
+    #[test]
+    fn bad_rate_limits_are_rejected() {
+        assert_rejected(
+            &[
+                ("RATE_LIMIT_DEFAULT_PER_MINUTE", "lots"),
+                ("RATE_LIMIT_UPVOTES_BURST", "0"),
+                ("RATE_LIMIT_ENABLED", "maybe"),
+            ],
+            &[
+                "RATE_LIMIT_DEFAULT_PER_MINUTE has an invalid value",
+                "RATE_LIMIT_UPVOTES limits must be greater than zero",
+                "RATE_LIMIT_ENABLED has an invalid value",
+            ],
+        );
+    }
+
+    #[test]
+    fn bad_telemetry_is_rejected() {
+        assert_rejected(
+            &[
+                ("LOG_FORMAT", "yaml"),
+                ("OTEL_EXPORTER_OTLP_ENDPOINT", "localhost:4318"),
+            ],
+            &[
+                "LOG_FORMAT must be text or json",
+                "OTEL_EXPORTER_OTLP_ENDPOINT must be an http(s) URL",
+            ],
+        );
+
+        let config = load(&[
+            ("LOG_FORMAT", "JSON"),
+            ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318/"),
+        ])
+        .unwrap();
+        assert_eq!(config.telemetry.log_format, LogFormat::Json);
+        assert_eq!(
+            config.telemetry.otlp_endpoint.as_deref(),
+            Some("http://collector:4318")
+        );
+    }
+
+    #[test]
+    fn short_metrics_tokens_are_rejected() {
+        assert_rejected(
+            &[("METRICS_TOKEN", "secret")],
+            &["METRICS_TOKEN must be at least 32 characters"],
+        );
+        assert!(load(&[]).unwrap().metrics_token.is_none());
+    }
+
+    #[test]
+    fn the_first_matching_role_rule_wins() {
+        let config = load(&[(
+            "ROLE_RULES",
+            r#"[
+                {"role": "admin", "emails": ["Dean@IITMandi.ac.in"]},
+                {"role": "authority", "pattern": "dean.*@iitmandi\\.ac\\.in"},
+                {"role": "student", "domain": "@students.iitmandi.ac.in"},
+                {"role": "faculty", "domain": "iitmandi.ac.in"}
+            ]"#,
+        )])
+        .unwrap();
+
+        assert_eq!(
+            config.role_for_email("dean@iitmandi.ac.in"),
+            UserRole::Admin
+        );
+        assert_eq!(
+            config.role_for_email("DEAN@iitmandi.ac.in"),
+            UserRole::Admin
+        );
+        assert_eq!(
+            config.role_for_email("dean.academics@iitmandi.ac.in"),
+            UserRole::Authority
+        );
+        assert_eq!(
+            config.role_for_email("b21001@students.iitmandi.ac.in"),
+            UserRole::Student
+        );
+        assert_eq!(
+            config.role_for_email("prof@iitmandi.ac.in"),
+            UserRole::Faculty
+        );
+    }
+
+    #[test]
+    fn role_patterns_match_the_whole_address() {
+        let config = load(&[
+            (
+                "ROLE_RULES",
+                r#"[{"role": "authority", "pattern": ".*@iitmandi\\.ac\\.in"}]"#,
+            ),
+            ("DEFAULT_ROLE", "student"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            config.role_for_email("x@iitmandi.ac.in"),
+            UserRole::Authority
+        );
+        assert_eq!(
+            config.role_for_email("x@iitmandi.ac.in.evil.com"),
+            UserRole::Student
+        );
+        assert_eq!(
+            config.role_for_email("x@iitmandi.ac.in@evil.com"),
+            UserRole::Student
+        );
+    }
+
+    #[test]
+    fn unmatched_emails_get_the_default_role() {
+        let rules = r#"[{"role": "admin", "emails": ["root@iitmandi.ac.in"]}]"#;
+        let config = load(&[("ROLE_RULES", rules)]).unwrap();
+        assert_eq!(
+            config.role_for_email("root@iitmandi.ac.in"),
+            UserRole::Admin
+        );
+        assert_eq!(config.role_for_email("other@iitmandi.ac.in"), DEFAULT_ROLE);
+
+        let config = load(&[("ROLE_RULES", rules), ("DEFAULT_ROLE", "authority")]).unwrap();
+        assert_eq!(
+            config.role_for_email("other@iitmandi.ac.in"),
+            UserRole::Authority
+        );
+
+        // Without any rules student addresses are told apart from everyone else
+        let config = load(&[]).unwrap();
+        assert_eq!(
+            config.role_for_email("b21001@students.iitmandi.ac.in"),
+            UserRole::Student
+        );
+        assert_eq!(config.role_for_email("prof@iitmandi.ac.in"), DEFAULT_ROLE);
+    }
+
+    #[test]
+    fn bad_role_rules_are_rejected() {
+        assert_rejected(
+            &[(
+                "ROLE_RULES",
+                r#"[
+                    {"role": "dean", "domain": "iitmandi.ac.in"},
+                    {"role": "admin", "domain": "iitmandi.ac.in", "emails": []},
+                    {"role": "admin", "pattern": "("}
+                ]"#,
+            )],
+            &[
+                "ROLE_RULES[0] role must be student, faculty, authority or admin",
+                "ROLE_RULES[1] must set exactly one of domain, pattern or emails",
+                "ROLE_RULES[2] has an invalid pattern",
+            ],
+        );
+    }
+
+    #[test]
+    fn environment_overrides_the_file() {
+        let mut raw = RawConfig::from_toml(
+            r#"
+            app_env = "development"
+            auth_provider = "dev"
+            database_url = "postgres://localhost/from-file"
+            port = 9000
+            cors_origins = ["https://file.example"]
+
+            [rate_limits.upvotes]
+            per_minute = 12
+            burst = 3
+            "#,
+        )
+        .unwrap();
+        raw.apply_env(|key| match key {
+            "PORT" => Some("9100".to_string()),
+            "CORS_ORIGINS" => Some("https://env.example, https://other.example".to_string()),
+            "RATE_LIMIT_UPVOTES_BURST" => Some("4".to_string()),
+            _ => None,
+        });
+        let config = raw.validate().unwrap();
+
+        assert_eq!(config.port, 9100);
+        assert_eq!(config.database_url, "postgres://localhost/from-file");
+        assert_eq!(
+            config.cors_origins,
+            ["https://env.example", "https://other.example"]
+        );
+        assert_eq!(config.rate_limits.upvotes.per_minute, 12);
+        assert_eq!(config.rate_limits.upvotes.burst, 4);
+    }
+
+    #[test]
+    fn unknown_file_keys_are_rejected() {
+        assert!(RawConfig::from_toml("prot = 9000").is_err());
+        assert!(RawConfig::from_toml("[cookie]\nsame_sight = \"lax\"").is_err());
+    }
+}
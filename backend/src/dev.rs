@@ -0,0 +1,149 @@
+// Routes for local development only. They are compiled in with the `dev-routes`
+// cargo feature and mounted only when APP_ENV=development, so release builds
+// don't contain them at all.
+
+use crate::audit::{self, Actor, Target};
+use crate::config::{AppConfig, AppEnv};
+use crate::error::AppError;
+use crate::extractors::AuthUser;
+use crate::state::AppState;
+use crate::structs::*;
+use axum::{
+    Router,
+    extract::State,
+    routing::{post, put},
+};
+use crate::extractors::Json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn routes(config: &AppConfig) -> Router<AppState> {
+    if config.app_env != AppEnv::Development {
+        tracing::info!("Built with dev-routes, but APP_ENV is not development; dev routes are off");
+        return Router::new();
+    }
+
+    tracing::warn!("========================================");
+    tracing::warn!("DEV_ROUTES: /api/user/role and /api/dev/seed are mounted");
+    tracing::warn!("DEV_ROUTES: any signed-in user can make themselves an admin");
+    tracing::warn!("========================================");
+
+    Router::new()
+        .route("/api/user/role", put(update_own_role))
+        .route("/api/dev/seed", post(seed_dummy_users))
+}
+
+// Every use goes to audit_logs and is shouted about in the server log
+async fn record_dev_use(
+    pool: &PgPool,
+    user_id: Option<Uuid>,
+    action: &str,
+    target: Option<Target>,
+    metadata: serde_json::Value,
+) -> Result<(), AppError> {
+    tracing::warn!("========================================");
+    tracing::warn!("DEV_ROUTE_USED: {} by user {:?}", action, user_id);
+    tracing::warn!("========================================");
+
+    let actor = user_id.map_or(Actor::System, Actor::User);
+    audit::record(pool, actor, action, target, metadata).await
+}
+
+// ============================================================================
+// UPDATE CURRENT USER ROLE (lets any user change their own role)
+// ============================================================================
+pub async fn update_own_role(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Json(payload): Json<UpdateUserRoleRequest>,
+) -> Result<Json<ApiResponse<UserResponse>>, AppError> {
+    sqlx::query("UPDATE users SET role = $1 WHERE id = $2")
+        .bind(&payload.role)
+        .bind(user.id)
+        .execute(&pool)
+        .await?;
+
+    record_dev_use(
+        &pool,
+        Some(user.id),
+        "DEV_UPDATE_OWN_ROLE",
+        Some(Target::User(user.id)),
+        serde_json::json!({
+            "old_role": user.role,
+            "new_role": payload.role,
+        }),
+    )
+    .await?;
+
+    let updated_user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_one(&pool)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(UserResponse::from(updated_user)),
+        message: Some("Your role has been updated".to_string()),
+        error: None,
+    }))
+}
+
+// ============================================================================
+// SEED DUMMY USERS
+// ============================================================================
+pub async fn seed_dummy_users(
+    State(pool): State<PgPool>,
+    user: Option<AuthUser>,
+) -> Result<Json<ApiResponse<String>>, AppError> {
+    let dummy_users = vec![
+        ("Dr. Strange", "strange@iitmandi.ac.in", UserRole::Faculty),
+        ("Tony Stark", "stark@iitmandi.ac.in", UserRole::Authority),
+        ("Steve Rogers", "rogers@students.iitmandi.ac.in", UserRole::Student),
+    ];
+
+    let mut seeded = Vec::new();
+    for (name, email, role) in dummy_users {
+        // Check if exists
+        let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)")
+            .bind(email)
+            .fetch_one(&pool)
+            .await?;
+
+        if !exists {
+            let parts: Vec<&str> = name.split_whitespace().collect();
+            let first_name = parts[0];
+            let last_name = parts.get(1).unwrap_or(&"");
+
+            sqlx::query(
+                r#"
+                INSERT INTO users (email, google_id, role, status, first_name, last_name, profile_picture)
+                VALUES ($1, $2, $3, 'active', $4, $5, '')
+                "#
+            )
+            .bind(email)
+            .bind(Uuid::new_v4().to_string()) // Random dummy google_id
+            .bind(role)
+            .bind(first_name)
+            .bind(last_name)
+            .execute(&pool)
+            .await?;
+            seeded.push(email);
+        }
+    }
+
+    record_dev_use(
+        &pool,
+        user.map(|AuthUser(u)| u.id),
+        "DEV_SEED_USERS",
+        None,
+        serde_json::json!({ "emails": seeded }),
+    )
+    .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some("Dummy users created. You can now assign them tasks.".to_string()),
+        message: None,
+        error: None,
+    }))
+}
\ No newline at end of file
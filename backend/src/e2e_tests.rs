@@ -0,0 +1,480 @@
+// End-to-end tests: whole workflows through the real router and database,
+// signed in as the seeded users. See test_support.rs for the harness.
+
+use crate::migrate::MIGRATOR;
+use crate::structs::UserRole;
+use crate::test_support::TestApp;
+use chrono::{Duration, Utc};
+use http::StatusCode;
+use serde_json::{Value, json};
+
+fn id(response: &Value) -> String {
+    response["data"]["id"]
+        .as_str()
+        .expect("response has data.id")
+        .to_string()
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres: set TEST_DATABASE_URL or DATABASE_URL"]
+async fn requests_without_a_session_are_rejected() {
+    let app = TestApp::spawn().await;
+    let client = app.anonymous();
+
+    let (status, _) = client.get("/api/grievances").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, me) = app.login_as(UserRole::Student).await.get("/auth/me").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        me["data"]["email"],
+        app.user(UserRole::Student).email.as_str()
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres: set TEST_DATABASE_URL or DATABASE_URL"]
+async fn grievance_workflow() {
+    let app = TestApp::spawn().await;
+    let student = app.login_as(UserRole::Student).await;
+    let faculty = app.login_as(UserRole::Faculty).await;
+    let authority = app.login_as(UserRole::Authority).await;
+    let admin = app.login_as(UserRole::Admin).await;
+    let faculty_id = app.user(UserRole::Faculty).id;
+
+    // Student files it
+    let (status, created) = student
+        .post(
+            "/api/grievances",
+            json!({
+                "title": "Broken tap",
+                "description": "The tap in B-block has been leaking for a week",
+                "category": "hostel",
+                "priority": "medium",
+                "location_type": "hostel",
+                "location_details": "B-block, second floor",
+                "is_anonymous": false,
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", created);
+    let grievance = id(&created);
+    assert_eq!(created["data"]["status"], "submitted");
+
+    let (status, photos) = student
+        .upload(
+            &format!("/api/grievances/{}/photos", grievance),
+            "photos",
+            &[b"not really a jpeg"],
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", photos);
+    let uploads = app.cloudinary.uploads();
+    assert_eq!(uploads.len(), 1);
+    assert!(uploads[0].starts_with(&format!("grievances/{}/", grievance)));
+
+    // Faculty only see grievances assigned to them
+    let (status, _) = faculty.get(&format!("/api/grievances/{}", grievance)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = student
+        .put(
+            &format!("/api/grievances/{}/assign", grievance),
+            json!({ "assigned_to": faculty_id }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, assigned) = authority
+        .put(
+            &format!("/api/grievances/{}/assign", grievance),
+            json!({ "assigned_to": faculty_id, "assigned_department": "Estate" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", assigned);
+
+    let (status, viewed) = faculty.get(&format!("/api/grievances/{}", grievance)).await;
+    assert_eq!(status, StatusCode::OK, "{}", viewed);
+    assert_eq!(viewed["data"]["photo_urls"], photos["data"]);
+
+    let (status, _) = authority
+        .put(
+            &format!("/api/grievances/{}/status", grievance),
+            json!({ "status": "in_progress", "remarks": "Plumber booked" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Internal notes are hidden from the student
+    let (status, _) = authority
+        .post(
+            &format!("/api/grievances/{}/comments", grievance),
+            json!({ "comment": "Second report from this block", "is_internal": true }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = faculty
+        .post(
+            &format!("/api/grievances/{}/comments", grievance),
+            json!({ "comment": "Will be fixed tomorrow", "is_internal": false }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, comments) = student
+        .get(&format!("/api/grievances/{}/comments", grievance))
+        .await;
+    let comments = comments["data"]["items"].as_array().unwrap();
+    assert_eq!(comments.len(), 1);
+    assert_eq!(comments[0]["comment"], "Will be fixed tomorrow");
+
+    let (_, comments) = authority
+        .get(&format!("/api/grievances/{}/comments", grievance))
+        .await;
+    assert_eq!(comments["data"]["items"].as_array().unwrap().len(), 2);
+
+    let (status, resolved) = authority
+        .put(
+            &format!("/api/grievances/{}/resolve", grievance),
+            json!({ "resolution_notes": "Tap replaced" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", resolved);
+    assert_eq!(resolved["data"]["status"], "resolved");
+
+    let (status, history) = student
+        .get(&format!("/api/grievances/{}/history", grievance))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let statuses: Vec<&Value> = history["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| &entry["new_status"])
+        .collect();
+    assert!(statuses.contains(&&json!("in_progress")), "{}", history);
+    assert!(statuses.contains(&&json!("resolved")), "{}", history);
+
+    // Every step is in the audit log
+    let (status, logs) = admin
+        .get(&format!("/api/admin/audit-logs?target_id={}", grievance))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", logs);
+    let actions: Vec<&str> = logs["data"]["logs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|log| log["action"].as_str())
+        .collect();
+    for action in [
+        "CREATE_GRIEVANCE",
+        "UPLOAD_GRIEVANCE_PHOTOS",
+        "ASSIGN_GRIEVANCE",
+        "UPDATE_GRIEVANCE_STATUS",
+        "RESOLVE_GRIEVANCE",
+    ] {
+        assert!(
+            actions.contains(&action),
+            "{} missing from {:?}",
+            action,
+            actions
+        );
+    }
+
+    let (_, verified) = admin.get("/api/admin/audit-logs/verify").await;
+    assert_eq!(verified["data"]["ok"], true, "{}", verified);
+
+    let (status, csv) = admin
+        .get(&format!(
+            "/api/admin/audit-logs/export/csv?target_id={}",
+            grievance
+        ))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let lines: Vec<&str> = csv.as_str().unwrap().lines().collect();
+    assert!(lines[0].starts_with("seq,created_at,action"), "{}", csv);
+    assert_eq!(lines.len(), actions.len() + 1, "{}", csv);
+
+    let (status, ndjson) = admin
+        .get(&format!(
+            "/api/admin/audit-logs/export/ndjson?target_id={}&limit=2",
+            grievance
+        ))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let lines: Vec<&str> = ndjson.as_str().unwrap().lines().collect();
+    assert_eq!(lines.len(), 2, "{}", ndjson);
+    let newest: Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(newest["action"], "RESOLVE_GRIEVANCE");
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres: set TEST_DATABASE_URL or DATABASE_URL"]
+async fn enrollment_and_attendance() {
+    let app = TestApp::spawn().await;
+    let student = app.login_as(UserRole::Student).await;
+    let faculty = app.login_as(UserRole::Faculty).await;
+    let admin = app.login_as(UserRole::Admin).await;
+    let student_id = app.user(UserRole::Student).id;
+
+    let (status, created) = admin
+        .post(
+            "/api/courses",
+            json!({
+                "code": "CS-201",
+                "title": "Data Structures",
+                "description": null,
+                "credits": 4,
+                "department": "CSE",
+                "course_type": "core",
+                "semester": "2026-odd",
+                "instructor_email": app.user(UserRole::Faculty).email,
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", created);
+    let course = id(&created);
+
+    let (status, enrolled) = student
+        .post("/api/courses/enroll", json!({ "course_id": course }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", enrolled);
+    let (status, _) = student
+        .post("/api/courses/enroll", json!({ "course_id": course }))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (_, enrollments) = student.get("/api/courses/my-enrollments").await;
+    let enrollments = enrollments["data"].as_array().unwrap();
+    assert_eq!(enrollments.len(), 1);
+    assert_eq!(enrollments[0]["code"], "CS-201");
+
+    for (date, attendance) in [("2026-08-03", "present"), ("2026-08-05", "absent")] {
+        let (status, marked) = faculty
+            .post(
+                "/api/attendance/mark",
+                json!({
+                    "student_id": student_id,
+                    "course_id": course,
+                    "date": date,
+                    "status": attendance,
+                    "remarks": null,
+                }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{}", marked);
+    }
+
+    let (status, summary) = student.get(&format!("/api/attendance/{}", course)).await;
+    assert_eq!(status, StatusCode::OK, "{}", summary);
+    assert_eq!(summary["data"]["total_classes"], 2);
+    assert_eq!(summary["data"]["present_count"], 1);
+    assert_eq!(summary["data"]["percentage"], 50.0);
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres: set TEST_DATABASE_URL or DATABASE_URL"]
+async fn opportunity_applications() {
+    let app = TestApp::spawn().await;
+    let student = app.login_as(UserRole::Student).await;
+    let faculty = app.login_as(UserRole::Faculty).await;
+
+    let (status, _) = student
+        .post(
+            "/api/opportunities",
+            json!({
+                "title": "Not allowed",
+                "description": "Students can't post",
+                "opportunity_type": "project",
+                "department": "CSE",
+                "required_skills": [],
+                "duration": "1 month",
+                "stipend": null,
+                "location": "Online",
+                "application_deadline": null,
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, created) = faculty
+        .post(
+            "/api/opportunities",
+            json!({
+                "title": "Summer research intern",
+                "description": "Work on distributed systems",
+                "opportunity_type": "research",
+                "department": "CSE",
+                "required_skills": ["rust", "postgres"],
+                "duration": "8 weeks",
+                "stipend": "10000/month",
+                "location": "North campus",
+                "application_deadline": null,
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", created);
+    let opportunity = id(&created);
+
+    let application = json!({
+        "resume_url": "https://example.com/resume.pdf",
+        "cover_letter": "I'd like to join",
+        "portfolio_url": null,
+    });
+    let (status, applied) = student
+        .post(
+            &format!("/api/opportunities/{}/apply", opportunity),
+            application.clone(),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", applied);
+    let (status, _) = student
+        .post(
+            &format!("/api/opportunities/{}/apply", opportunity),
+            application,
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, applicants) = faculty
+        .get(&format!("/api/opportunities/{}/applications", opportunity))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", applicants);
+    let applicants = applicants["data"].as_array().unwrap();
+    assert_eq!(applicants.len(), 1);
+    assert_eq!(
+        applicants[0]["student"]["email"],
+        app.user(UserRole::Student).email.as_str()
+    );
+    let application = applicants[0]["id"].as_str().unwrap();
+
+    // Only the poster (or an admin) manages the applications
+    let (status, _) = student
+        .put(
+            &format!("/api/applications/{}/status", application),
+            json!({ "status": "accepted", "faculty_remarks": null }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, updated) = faculty
+        .put(
+            &format!("/api/applications/{}/status", application),
+            json!({ "status": "accepted", "faculty_remarks": "Welcome aboard" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", updated);
+
+    let (_, mine) = student.get("/api/applications/my-applications").await;
+    let mine = mine["data"].as_array().unwrap();
+    assert_eq!(mine.len(), 1);
+    assert_eq!(mine[0]["status"], "accepted");
+    assert_eq!(mine[0]["opportunity"]["title"], "Summer research intern");
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres: set TEST_DATABASE_URL or DATABASE_URL"]
+async fn impersonation_is_limited_to_lesser_accounts() {
+    let app = TestApp::spawn().await;
+    let admin = app.login_as(UserRole::Admin).await;
+    let reason = json!({ "reason": "Checking a reported bug" });
+
+    // Admins can't view as each other
+    let other_admin: (uuid::Uuid,) = sqlx::query_as(
+        r#"
+        INSERT INTO users (email, google_id, role, first_name, last_name)
+        VALUES ('admin2@iitmandi.ac.in', 'test-admin2', 'admin', 'admin2', 'Test')
+        RETURNING id
+        "#,
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    let (status, refused) = admin
+        .post(
+            &format!("/api/admin/users/{}/impersonate", other_admin.0),
+            reason.clone(),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{}", refused);
+
+    let faculty = app.user(UserRole::Faculty);
+    let (status, started) = admin
+        .post(
+            &format!("/api/admin/users/{}/impersonate", faculty.id),
+            reason,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", started);
+    let (_, me) = admin.get("/auth/me").await;
+    assert_eq!(me["data"]["id"], faculty.id.to_string());
+
+    // The target gaining a grant the admin shouldn't borrow ends it
+    sqlx::query(
+        "INSERT INTO role_permissions (role, permission, scope) VALUES ('faculty', 'user.manage', 'any')",
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+    let (status, me) = admin.get("/auth/me").await;
+    assert_eq!(status, StatusCode::OK, "{}", me);
+    assert_eq!(me["data"]["id"], app.user(UserRole::Admin).id.to_string());
+    assert!(me["data"]["impersonation"].is_null(), "{}", me);
+}
+
+#[tokio::test]
+#[ignore = "needs Postgres: set TEST_DATABASE_URL or DATABASE_URL"]
+async fn grievances_without_timestamps_are_backfilled_and_listed() {
+    let app = TestApp::spawn().await;
+    let student = app.user(UserRole::Student).id;
+
+    // Back to the schema before the sort columns became NOT NULL, with rows it allowed
+    MIGRATOR.undo(&app.pool, 20261018180000).await.unwrap();
+    let mut filed = Vec::new();
+    for created_at in [Some(Utc::now() - Duration::days(1)), None, Some(Utc::now())] {
+        let (id,): (uuid::Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO grievances (submitted_by, title, description, category, created_at, updated_at, upvote_count)
+            VALUES ($1, 'Broken tap', 'Leaking', 'hostel', $2, $2, NULL)
+            RETURNING id
+            "#,
+        )
+        .bind(student)
+        .bind(created_at)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+        filed.push(id.to_string());
+    }
+    MIGRATOR.run(&app.pool).await.unwrap();
+
+    // One row per page, so every row has to be reachable through a cursor
+    let admin = app.login_as(UserRole::Admin).await;
+    let mut listed = Vec::new();
+    let mut cursor = None;
+    loop {
+        let path = match &cursor {
+            Some(cursor) => format!("/api/grievances?limit=1&cursor={}", cursor),
+            None => "/api/grievances?limit=1".to_string(),
+        };
+        let (status, page) = admin.get(&path).await;
+        assert_eq!(status, StatusCode::OK, "{}", page);
+        for item in page["data"]["items"].as_array().unwrap() {
+            listed.push(item["id"].as_str().unwrap().to_string());
+        }
+        match page["data"]["next_cursor"].as_str() {
+            Some(next) => cursor = Some(next.to_string()),
+            None => break,
+        }
+    }
+    listed.sort();
+    filed.sort();
+    assert_eq!(listed, filed);
+
+    let refused = sqlx::query(
+        "INSERT INTO grievances (title, description, category, created_at) VALUES ('t', 'd', 'hostel', NULL)",
+    )
+    .execute(&app.pool)
+    .await;
+    assert!(refused.is_err());
+}
@@ -1,7 +1,14 @@
+use crate::structs::{ApiResponse, ErrorBody};
+use crate::telemetry;
 use axum::{
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    http::{StatusCode, header},
+    response::{IntoResponse, Json, Response},
 };
+use serde_json::json;
+use uuid::Uuid;
+
+// Postgres SQLSTATE for unique_violation
+const PG_UNIQUE_VIOLATION: &str = "23505";
 
 #[derive(Debug)]
 pub enum AppError {
@@ -11,37 +18,112 @@ pub enum AppError {
     Forbidden,
     NotFound,
     BadRequest(String),
+    Conflict(String),
+    TooManyRequests { retry_after_secs: u64 },
     InternalServerError(String),
 }
 
+// Machine-readable code sent in `error.code`
+fn code_for(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limited",
+        s if s.is_client_error() => "client_error",
+        _ => "internal_error",
+    }
+}
+
+fn error_response(
+    status: StatusCode,
+    message: String,
+    details: Option<serde_json::Value>,
+) -> Response {
+    let body = ApiResponse::<()> {
+        success: false,
+        data: None,
+        message: Some(message),
+        error: Some(ErrorBody {
+            code: code_for(status),
+            details,
+        }),
+    };
+    (status, Json(body)).into_response()
+}
+
+// Log the real cause server-side and hand the client only the request id, which is
+// also on every log line of the request. Outside a request a fresh id stands in.
+fn internal_response(cause: &dyn std::fmt::Debug) -> Response {
+    let request_id =
+        telemetry::current_request_id().unwrap_or_else(|| Uuid::new_v4().to_string());
+    tracing::error!(%request_id, "Internal error: {:?}", cause);
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Internal Server Error".to_string(),
+        Some(json!({ "request_id": request_id })),
+    )
+}
+
+// Map well-known database failures to client errors; anything else stays a 500
+fn database_response(err: &sqlx::Error) -> Option<Response> {
+    match err {
+        sqlx::Error::RowNotFound => Some(error_response(
+            StatusCode::NOT_FOUND,
+            "Not Found".to_string(),
+            None,
+        )),
+        sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some(PG_UNIQUE_VIOLATION) => {
+            Some(error_response(
+                StatusCode::CONFLICT,
+                "Resource already exists".to_string(),
+                db_err.constraint().map(|c| json!({ "constraint": c })),
+            ))
+        }
+        _ => None,
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         match self {
             AppError::HttpError(status_code, err) => {
-                (status_code, format!("Error: {}", err)).into_response()
-            }
-            AppError::Internal(err) => {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Internal Server Error: {}", err),
-                )
-                    .into_response()
+                if status_code.is_server_error() {
+                    internal_response(&err)
+                } else {
+                    error_response(status_code, err.to_string(), None)
+                }
             }
+            AppError::Internal(err) => match err.downcast_ref::<sqlx::Error>() {
+                Some(db_err) => database_response(db_err).unwrap_or_else(|| internal_response(&err)),
+                None => internal_response(&err),
+            },
             AppError::Unauthorized => {
-                (StatusCode::UNAUTHORIZED, "Unauthorized").into_response()
+                error_response(StatusCode::UNAUTHORIZED, "Unauthorized".to_string(), None)
             }
             AppError::Forbidden => {
-                (StatusCode::FORBIDDEN, "Forbidden").into_response()
+                error_response(StatusCode::FORBIDDEN, "Forbidden".to_string(), None)
             }
             AppError::NotFound => {
-                (StatusCode::NOT_FOUND, "Not Found").into_response()
+                error_response(StatusCode::NOT_FOUND, "Not Found".to_string(), None)
             }
-            AppError::BadRequest(msg) => {
-                (StatusCode::BAD_REQUEST, msg).into_response()
-            }
-            AppError::InternalServerError(msg) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response()
+            AppError::BadRequest(msg) => error_response(StatusCode::BAD_REQUEST, msg, None),
+            AppError::Conflict(msg) => error_response(StatusCode::CONFLICT, msg, None),
+            AppError::TooManyRequests { retry_after_secs } => {
+                let mut response = error_response(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "Too many requests, please slow down".to_string(),
+                    Some(json!({ "retry_after": retry_after_secs })),
+                );
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, retry_after_secs.into());
+                response
             }
+            AppError::InternalServerError(msg) => internal_response(&msg),
         }
     }
 }
@@ -54,3 +136,36 @@ where
         AppError::Internal(err.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, http::Request, middleware, routing::get};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn internal_errors_quote_the_request_id() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async { Err::<(), _>(AppError::Internal(anyhow::anyhow!("boom"))) }),
+            )
+            .layer(middleware::from_fn(telemetry::scope_request_id));
+
+        let request = Request::builder()
+            .uri("/")
+            .header(telemetry::REQUEST_ID_HEADER, "5f0c8a9e-3d6b-4a51-9b7e-2c1d0e4f6a88")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["error"]["details"]["request_id"],
+            "5f0c8a9e-3d6b-4a51-9b7e-2c1d0e4f6a88"
+        );
+        assert!(!body.to_string().contains("boom"));
+    }
+}
@@ -0,0 +1,352 @@
+use crate::auth::SESSION_USER_ID_KEY;
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::impersonation;
+use crate::structs::{User, UserStatus};
+use crate::tokens;
+use axum::{
+    extract::{
+        ConnectInfo, FromRef, FromRequest, FromRequestParts, OptionalFromRequestParts, Request,
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+    },
+    http::{Extensions, HeaderMap, StatusCode, request::Parts},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use tower_sessions::Session;
+use uuid::Uuid;
+
+// ============================================================================
+// AUTHENTICATED USER
+// ============================================================================
+
+// The user making the request: the owner of a personal access token sent as
+// `Authorization: Bearer`, otherwise the session's user, or the user an admin is
+// impersonating. Loaded once per request (cached in the request extensions) and rejected
+// with 401 when there is neither, or 403 when the account is suspended or inactive or the
+// token's scopes don't cover the endpoint.
+#[derive(Debug, Clone)]
+pub struct AuthUser(pub User);
+
+impl<S> FromRequestParts<S> for AuthUser
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(auth_user) = parts.extensions.get::<AuthUser>() {
+            return Ok(auth_user.clone());
+        }
+
+        let pool = PgPool::from_ref(state);
+
+        let bearer = tokens::bearer_token(&parts.headers).map(str::to_string);
+
+        let user = match bearer {
+            Some(token) => {
+                tokens::authenticate(&pool, &token, &parts.method, parts.uri.path()).await?
+            }
+            None => {
+                let session = parts.extensions.get::<Session>().cloned().ok_or_else(|| {
+                    AppError::Internal(anyhow::anyhow!("Session layer is not installed"))
+                })?;
+
+                let user_id: Uuid = session
+                    .get(SESSION_USER_ID_KEY)
+                    .await?
+                    .ok_or(AppError::Unauthorized)?;
+
+                let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+                    .bind(user_id)
+                    .fetch_optional(&pool)
+                    .await?
+                    .ok_or(AppError::Unauthorized)?;
+
+                // An admin viewing the app as someone else acts as that user
+                impersonation::effective_user(&pool, &session, user, &parts.method, parts.uri.path())
+                    .await?
+            }
+        };
+
+        if !matches!(user.status, UserStatus::Active) {
+            tracing::warn!("Rejected request from {:?} user {}", user.status, user.id);
+            return Err(AppError::Forbidden);
+        }
+
+        let auth_user = AuthUser(user);
+        parts.extensions.insert(auth_user.clone());
+        Ok(auth_user)
+    }
+}
+
+// `Option<AuthUser>` for endpoints that also serve anonymous visitors
+impl<S> OptionalFromRequestParts<S> for AuthUser
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        match <AuthUser as FromRequestParts<S>>::from_request_parts(parts, state).await {
+            Ok(auth_user) => Ok(Some(auth_user)),
+            Err(AppError::Unauthorized) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// ============================================================================
+// DATABASE TRANSACTION
+// ============================================================================
+
+// A transaction for the handler's writes, used as `&mut *tx` wherever a query
+// takes an executor. Nothing is persisted until the handler calls `commit()`;
+// returning early (e.g. with `?`) drops it and rolls everything back. Take it
+// after `AuthUser` so a connection isn't held while the caller is checked.
+pub struct Tx(Transaction<'static, Postgres>);
+
+impl Tx {
+    pub async fn commit(self) -> Result<(), AppError> {
+        self.0.commit().await?;
+        Ok(())
+    }
+}
+
+impl Deref for Tx {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Tx {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<S> FromRequestParts<S> for Tx
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let tx = PgPool::from_ref(state).begin().await?;
+        Ok(Tx(tx))
+    }
+}
+
+// ============================================================================
+// CLIENT ADDRESS
+// ============================================================================
+
+// The peer address, or the first X-Forwarded-For hop when running behind a trusted proxy
+pub fn client_ip(headers: &HeaderMap, extensions: &Extensions, trust_forwarded_for: bool) -> IpAddr {
+    let forwarded = trust_forwarded_for
+        .then(|| {
+            headers
+                .get("x-forwarded-for")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split(',').next())
+                .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+        })
+        .flatten();
+
+    let peer = extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    forwarded
+        .or(peer)
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+}
+
+pub struct ClientIp(pub IpAddr);
+
+impl<S> FromRequestParts<S> for ClientIp
+where
+    Arc<AppConfig>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<AppConfig>::from_ref(state);
+        Ok(ClientIp(client_ip(
+            &parts.headers,
+            &parts.extensions,
+            config.rate_limits.trust_forwarded_for,
+        )))
+    }
+}
+
+// ============================================================================
+// BODY, QUERY AND PATH
+// ============================================================================
+
+// Drop-in replacements for axum's `Json`, `Query` and `Path` whose rejections
+// (malformed JSON, unknown filters, a bad UUID in the path) come back as the
+// usual JSON error body instead of plain text
+pub struct Json<T>(pub T);
+pub struct Query<T>(pub T);
+pub struct Path<T>(pub T);
+
+fn rejected(status: StatusCode, message: String) -> AppError {
+    AppError::HttpError(status, anyhow::anyhow!(message))
+}
+
+impl<T, S> FromRequest<S> for Json<T>
+where
+    axum::Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match axum::Json::<T>::from_request(request, state).await {
+            Ok(axum::Json(value)) => Ok(Json(value)),
+            Err(rejection) => Err(rejected(rejection.status(), rejection.body_text())),
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    axum::extract::Query<T>: FromRequestParts<S, Rejection = QueryRejection>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match axum::extract::Query::<T>::from_request_parts(parts, state).await {
+            Ok(axum::extract::Query(value)) => Ok(Query(value)),
+            Err(rejection) => Err(rejected(rejection.status(), rejection.body_text())),
+        }
+    }
+}
+
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    axum::extract::Path<T>: FromRequestParts<S, Rejection = PathRejection>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match axum::extract::Path::<T>::from_request_parts(parts, state).await {
+            Ok(axum::extract::Path(value)) => Ok(Path(value)),
+            Err(rejection) => Err(rejected(rejection.status(), rejection.body_text())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::GrievanceFilters;
+    use axum::{
+        Router,
+        body::Body,
+        http::header,
+        routing::{get, post},
+    };
+    use serde::Deserialize;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[derive(Deserialize)]
+    struct Page {
+        limit: Option<i64>,
+    }
+
+    async fn send(request: Request) -> (StatusCode, Value) {
+        let app = Router::new()
+            .route("/body", post(|Json(_): Json<Value>| async {}))
+            .route("/query", get(|Query(_): Query<Page>| async {}))
+            .route(
+                "/grievances",
+                get(|Query(_): Query<GrievanceFilters>| async {}),
+            )
+            .route("/items/{id}", get(|Path(_): Path<Uuid>| async {}));
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn get_request(uri: &str) -> Request {
+        Request::builder().uri(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn malformed_json_is_a_json_error() {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/body")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{\"title\":"))
+            .unwrap();
+        let (status, body) = send(request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"]["code"], "bad_request");
+    }
+
+    #[tokio::test]
+    async fn bad_query_is_a_json_error() {
+        let (status, body) = send(get_request("/query?limit=ten")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "bad_request");
+        assert!(
+            body["message"].as_str().unwrap().contains("limit"),
+            "{}",
+            body
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_filters_are_a_json_error() {
+        let (status, body) = send(get_request("/grievances?statsu=pending")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"]["code"], "bad_request");
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .contains("unknown field `statsu`"),
+            "{}",
+            body
+        );
+    }
+
+    #[tokio::test]
+    async fn bad_path_is_a_json_error() {
+        let (status, body) = send(get_request("/items/not-a-uuid")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "bad_request");
+    }
+}
@@ -1,52 +1,21 @@
-use crate::cloudinary::{CloudinaryConfig, CloudinaryService};
+use crate::cloudinary::CloudinaryService;
+use crate::config::AppConfig;
 use crate::error::AppError;
+use crate::extractors::{AuthUser, Json, Path, Query};
+use crate::listing::PageParams;
+use crate::loaders::{self, UserMap};
+use crate::monitoring;
+use crate::permissions::{Permission, Resource, allowed, authorize};
+use crate::repos::{GrievanceRepo, NewGrievance, Repos};
 use crate::structs::*;
 use axum::{
-    extract::{Path, Query, State, Multipart},
+    extract::{State, Multipart},
     http::StatusCode,
-    response::Json,
 };
-use sqlx::PgPool;
-use tower_sessions::Session;
+use std::sync::Arc;
 use uuid::Uuid;
 use base64::prelude::*;
 
-// Helper function to get current user from session
-async fn get_session_user(session: &Session, pool: &PgPool) -> Result<User, AppError> {
-    let user_id: Uuid = session
-        .get("user_id")
-        .await?
-        .ok_or(AppError::Unauthorized)?;
-
-    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-        .bind(user_id)
-        .fetch_one(pool)
-        .await?;
-
-    Ok(user)
-}
-
-// Helper function to check if user can view grievance details
-fn can_view_grievance(user: &User, grievance: &Grievance) -> bool {
-    match user.role {
-        UserRole::Admin | UserRole::Authority => true,
-        UserRole::Faculty => grievance.assigned_to == Some(user.id),
-        UserRole::Student => {
-            true
-        }
-    }
-}
-
-// Helper function to check if user can modify grievance
-fn can_modify_grievance(user: &User, grievance: &Grievance) -> bool {
-    match user.role {
-        UserRole::Admin => true,
-        UserRole::Authority => grievance.assigned_to == Some(user.id) || grievance.assigned_to.is_none(),
-        UserRole::Student => grievance.submitted_by == Some(user.id),
-        _ => false,
-    }
-}
-
 // Generate anonymous identifier
 fn generate_anonymous_identifier() -> String {
     format!("ANON-{}", Uuid::new_v4().to_string()[..8].to_uppercase())
@@ -56,16 +25,11 @@ fn generate_anonymous_identifier() -> String {
 // CREATE GRIEVANCE
 // ============================================================================
 pub async fn create_grievance(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Json(payload): Json<CreateGrievanceRequest>,
 ) -> Result<Json<ApiResponse<GrievanceResponse>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-
-    // Validate user role - students and faculty can submit grievances
-    if !matches!(user.role, UserRole::Student | UserRole::Faculty) {
-        return Err(AppError::Forbidden);
-    }
+    authorize(&repos, &user, Permission::GrievanceCreate, Resource::Global).await?;
 
     let submitted_by = if payload.is_anonymous { None } else { Some(user.id) };
     let anonymous_identifier = if payload.is_anonymous {
@@ -78,48 +42,15 @@ pub async fn create_grievance(
     tracing::info!("[CREATE_GRIEVANCE] submitted_by={:?}, anonymous={}, identifier={:?}", 
         submitted_by, payload.is_anonymous, anonymous_identifier);
     
-    let grievance = sqlx::query_as::<_, Grievance>(
-        r#"
-        INSERT INTO grievances (
-            submitted_by, is_anonymous, anonymous_identifier,
-            title, description, category, priority,
-            location_type, location_details
-        )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-        RETURNING *
-        "#,
-    )
-    .bind(submitted_by)
-    .bind(payload.is_anonymous)
-    .bind(anonymous_identifier)
-    .bind(&payload.title)
-    .bind(&payload.description)
-    .bind(&payload.category)
-    .bind(&payload.priority)
-    .bind(&payload.location_type)
-    .bind(&payload.location_details)
-    .fetch_one(&pool)
-    .await?;
+    let new = NewGrievance { submitted_by, anonymous_identifier, request: payload };
+    let grievance = repos.grievances.create(&user, new).await.map_err(|e| {
+        tracing::error!("[CREATE_GRIEVANCE] Insert failed: {:?}", e);
+        e
+    })?;
 
     tracing::info!("[CREATE_GRIEVANCE] Grievance created successfully: id={}", grievance.id);
 
-    // Log the creation
-    sqlx::query(
-        "INSERT INTO audit_logs (user_id, action, metadata) VALUES ($1, $2, $3)"
-    )
-    .bind(user.id)
-    .bind("CREATE_GRIEVANCE")
-    .bind(serde_json::json!({
-        "grievance_id": grievance.id,
-        "category": grievance.category,
-        "priority": grievance.priority,
-    }))
-    .execute(&pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("[CREATE_GRIEVANCE] Audit log insert failed: {:?}", e);
-        e
-    })?;
+    metrics::counter!(monitoring::GRIEVANCES_CREATED).increment(1);
 
     tracing::info!("[CREATE_GRIEVANCE] Building response object");
     let response = GrievanceResponse {
@@ -150,6 +81,7 @@ pub async fn create_grievance(
         success: true,
         data: Some(response),
         message: Some("Grievance created successfully".to_string()),
+        error: None,
     }))
 }
 
@@ -157,116 +89,24 @@ pub async fn create_grievance(
 // GET ALL GRIEVANCES (WITH FILTERS)
 // ============================================================================
 pub async fn get_grievances(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Query(filters): Query<GrievanceFilters>,
-) -> Result<Json<ApiResponse<Vec<GrievanceResponse>>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-
-    let page = filters.page.unwrap_or(1).max(1);
-    let limit = filters.limit.unwrap_or(20).clamp(1, 100);
-    let offset = (page - 1) * limit;
-
-    // Build dynamic query based on filters
+) -> Result<Json<ApiResponse<Paginated<GrievanceResponse>>>, AppError> {
     // Public feed: Everyone sees all grievances (like Reddit)
     // RBAC is enforced on ACTIONS (update, assign, delete), not viewing
-    let mut query = String::from(
-        r#"
-        SELECT g.* FROM grievances g
-        WHERE 1=1
-        "#,
-    );
-
-    // Apply filters
-    if let Some(status) = &filters.status {
-        query.push_str(&format!(" AND g.status = '{:?}'", status).to_lowercase());
-    }
-    if let Some(category) = &filters.category {
-        query.push_str(&format!(" AND g.category = '{:?}'", category).to_lowercase());
-    }
-    if let Some(priority) = &filters.priority {
-        query.push_str(&format!(" AND g.priority = '{:?}'", priority).to_lowercase());
-    }
-    if let Some(assigned_to) = filters.assigned_to {
-        query.push_str(&format!(" AND g.assigned_to = '{}'", assigned_to));
-    }
-    if let Some(ref dept) = filters.assigned_department {
-        query.push_str(&format!(" AND g.assigned_department = '{}'", dept));
-    }
-    if let Some(ref search) = filters.search {
-        query.push_str(&format!(
-            " AND (g.title ILIKE '%{}%' OR g.description ILIKE '%{}%')",
-            search, search
-        ));
-    }
-
-    query.push_str(" ORDER BY g.created_at DESC");
-    query.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
-
-    let grievances = sqlx::query_as::<_, Grievance>(&query)
-        .fetch_all(&pool)
-        .await?;
-
-    // Fetch related data for responses
-    let mut responses = Vec::new();
-    for grievance in grievances {
-        let submitter = if let Some(submitter_id) = grievance.submitted_by {
-            sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-                .bind(submitter_id)
-                .fetch_optional(&pool)
-                .await?
-                .map(UserResponse::from)
-        } else {
-            None
-        };
-
-        let assigned_user = if let Some(assigned_id) = grievance.assigned_to {
-            sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-                .bind(assigned_id)
-                .fetch_optional(&pool)
-                .await?
-                .map(UserResponse::from)
-        } else {
-            None
-        };
+    // Don't let the submitter filter unmask anonymous grievances
+    let hide_anonymous = filters.submitted_by.is_some_and(|id| id != user.id);
+    let mut page = repos.grievances.list(filters, hide_anonymous).await?;
 
-        // Check if user has upvoted
-        let user_has_upvoted = sqlx::query_scalar::<_, bool>(
-            "SELECT EXISTS(SELECT 1 FROM grievance_upvotes WHERE grievance_id = $1 AND user_id = $2)"
-        )
-        .bind(grievance.id)
-        .bind(user.id)
-        .fetch_one(&pool)
-        .await?;
-
-        responses.push(GrievanceResponse {
-            id: grievance.id,
-            submitter,
-            is_anonymous: grievance.is_anonymous,
-            title: grievance.title,
-            description: grievance.description,
-            category: grievance.category,
-            priority: grievance.priority,
-            status: grievance.status,
-            location_type: grievance.location_type,
-            location_details: grievance.location_details,
-            photo_urls: grievance.photo_urls.unwrap_or_default(),
-            assigned_to: assigned_user,
-            assigned_department: grievance.assigned_department,
-            resolution_notes: grievance.resolution_notes,
-            resolved_at: grievance.resolved_at,
-            view_count: grievance.view_count,
-            upvote_count: grievance.upvote_count,
-            user_has_upvoted,
-            created_at: grievance.created_at,
-            updated_at: grievance.updated_at,
-        });
-    }
+    let rows = std::mem::take(&mut page.items);
+    let responses = loaders::grievance_responses(&repos, user.id, rows).await?;
 
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(responses),
+        data: Some(page.with_items(responses)),
         message: None,
+        error: None,
     }))
 }
 
@@ -274,117 +114,48 @@ pub async fn get_grievances(
 // GET SINGLE GRIEVANCE BY ID
 // ============================================================================
 pub async fn get_grievance_by_id(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<GrievanceResponse>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-
-    let grievance = sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    let grievance = repos.grievances.find(id).await?.ok_or(AppError::NotFound)?;
 
     // Check if user can view this grievance
-    if !can_view_grievance(&user, &grievance) {
-        return Err(AppError::Forbidden);
-    }
+    authorize(&repos, &user, Permission::GrievanceView, Resource::Grievance(&grievance)).await?;
 
     // Increment view count
-    sqlx::query("UPDATE grievances SET view_count = view_count + 1 WHERE id = $1")
-        .bind(id)
-        .execute(&pool)
-        .await?;
-
-    // Fetch related data
-    let submitter = if let Some(submitter_id) = grievance.submitted_by {
-        sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-            .bind(submitter_id)
-            .fetch_optional(&pool)
-            .await?
-            .map(UserResponse::from)
-    } else {
-        None
-    };
+    repos.grievances.record_view(id).await?;
 
-    let assigned_user = if let Some(assigned_id) = grievance.assigned_to {
-        sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-            .bind(assigned_id)
-            .fetch_optional(&pool)
-            .await?
-            .map(UserResponse::from)
-    } else {
-        None
-    };
-
-    let user_has_upvoted = sqlx::query_scalar::<_, bool>(
-        "SELECT EXISTS(SELECT 1 FROM grievance_upvotes WHERE grievance_id = $1 AND user_id = $2)",
-    )
-    .bind(id)
-    .bind(user.id)
-    .fetch_one(&pool)
-    .await?;
-
-    let response = GrievanceResponse {
-        id: grievance.id,
-        submitter,
-        is_anonymous: grievance.is_anonymous,
-        title: grievance.title.clone(),
-        description: grievance.description.clone(),
-        category: grievance.category.clone(),
-        priority: grievance.priority.clone(),
-        status: grievance.status.clone(),
-        location_type: grievance.location_type.clone(),
-        location_details: grievance.location_details.clone(),
-        photo_urls: grievance.photo_urls.clone().unwrap_or_default(),
-        assigned_to: assigned_user,
-        assigned_department: grievance.assigned_department.clone(),
-        resolution_notes: grievance.resolution_notes.clone(),
-        resolved_at: grievance.resolved_at,
-        view_count: grievance.view_count + 1, // Include the increment
-        upvote_count: grievance.upvote_count,
-        user_has_upvoted,
-        created_at: grievance.created_at,
-        updated_at: grievance.updated_at,
-    };
+    let mut response = loaders::grievance_responses(&repos, user.id, vec![grievance])
+        .await?
+        .remove(0);
+    response.view_count += 1; // Include the increment
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some(response),
         message: None,
+        error: None,
     }))
 }
 
 // ============================================================================
-// UPDATE GRIEVANCE STATUS (Authority/Admin only)
+// UPDATE GRIEVANCE STATUS (grievance.update_status)
 // ============================================================================
 pub async fn update_grievance_status(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateGrievanceStatusRequest>,
 ) -> Result<Json<ApiResponse<GrievanceResponse>>, AppError> {
     tracing::info!("==== [START] update_grievance_status ====");
     tracing::info!("Target Grievance ID: {}", id);
     tracing::info!("Payload: {:?}", payload);
-
-    let user = get_session_user(&session, &pool).await.map_err(|e| {
-        tracing::error!("Auth error in update_status: {:?}", e);
-        e
-    })?;
-
     tracing::info!("User {} ({:?}) is attempting update", user.id, user.role);
 
-    // Only Authority and Admin can update status
-    if !matches!(user.role, UserRole::Authority | UserRole::Admin) {
-        tracing::error!("Forbidden: Role {:?} cannot update status", user.role);
-        return Err(AppError::Forbidden);
-    }
-
-    let grievance = sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&pool)
+    let grievance = repos
+        .grievances
+        .find(id)
         .await
         .map_err(|e| {
             tracing::error!("DB Error fetching grievance: {:?}", e);
@@ -395,45 +166,21 @@ pub async fn update_grievance_status(
             AppError::NotFound
         })?;
 
-    tracing::info!("Updating grievance status in DB...");
-    sqlx::query("UPDATE grievances SET status = $1 WHERE id = $2")
-        .bind(&payload.status)
-        .bind(id)
-        .execute(&pool)
-        .await
-        .map_err(|e| {
-            tracing::error!("SQLX Error updating status: {:?}", e);
-            e
-        })?;
-
-    tracing::info!("Inserting status history...");
-    sqlx::query(
-        r#"
-        INSERT INTO grievance_status_history 
-        (grievance_id, old_status, new_status, remarks, updated_by, updated_by_role)
-        VALUES ($1, $2, $3, $4, $5, $6)
-        "#,
+    authorize(
+        &repos,
+        &user,
+        Permission::GrievanceUpdateStatus,
+        Resource::Grievance(&grievance),
     )
-    .bind(id)
-    .bind(&grievance.status)
-    .bind(&payload.status)
-    .bind(&payload.remarks)
-    .bind(user.id)
-    .bind(&user.role)
-    .execute(&pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("SQLX Error inserting status history: {:?}", e);
-        e
-    })?;
+    .await?;
 
-    tracing::info!("Fetching updated grievance to return...");
-    let updated = sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
-        .bind(id)
-        .fetch_one(&pool)
+    tracing::info!("Updating grievance status and history in DB...");
+    let updated = repos
+        .grievances
+        .update_status(&user, &grievance, payload)
         .await
         .map_err(|e| {
-            tracing::error!("SQLX Error fetching updated grievance: {:?}", e);
+            tracing::error!("Error updating status: {:?}", e);
             e
         })?;
 
@@ -466,15 +213,16 @@ pub async fn update_grievance_status(
         success: true,
         data: Some(response),
         message: Some("Status updated successfully".to_string()),
+        error: None,
     }))
 }
 
 // ============================================================================
-// ASSIGN GRIEVANCE (Authority/Admin only)
+// ASSIGN GRIEVANCE (grievance.assign)
 // ============================================================================
 pub async fn assign_grievance(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Path(id): Path<Uuid>,
     Json(payload): Json<AssignGrievanceRequest>,
 ) -> Result<Json<ApiResponse<GrievanceResponse>>, AppError> {
@@ -482,22 +230,16 @@ pub async fn assign_grievance(
     tracing::info!("Target Grievance ID: {}", id);
     tracing::info!("Payload: {:?}", payload);
 
-    let user = get_session_user(&session, &pool).await.map_err(|e| {
-        tracing::error!("Auth error in assign: {:?}", e);
-        e
-    })?;
+    let grievance = repos.grievances.find(id).await?.ok_or(AppError::NotFound)?;
 
-    if !matches!(user.role, UserRole::Authority | UserRole::Admin) {
-        tracing::error!("Forbidden: Role {:?} cannot assign", user.role);
-        return Err(AppError::Forbidden);
-    }
+    authorize(&repos, &user, Permission::GrievanceAssign, Resource::Grievance(&grievance)).await?;
 
     // Verify assigned user exists and has appropriate role
     if let Some(assigned_id) = payload.assigned_to {
         tracing::info!("Verifying assignee {} exists...", assigned_id);
-        let assigned_user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-            .bind(assigned_id)
-            .fetch_optional(&pool)
+        let assigned_user = repos
+            .users
+            .find(assigned_id)
             .await
             .map_err(|e| {
                 tracing::error!("DB error looking up assignee: {:?}", e);
@@ -508,56 +250,20 @@ pub async fn assign_grievance(
                 AppError::NotFound
             })?;
 
-        if !matches!(
-            assigned_user.role,
-            UserRole::Authority | UserRole::Admin | UserRole::Faculty
-        ) {
+        if !allowed(&repos, &assigned_user, Permission::GrievanceHandle, Resource::Global).await? {
             tracing::error!("Invalid assignee role: {:?}", assigned_user.role);
             return Err(AppError::BadRequest(
-                "Can only assign to Authority, Admin, or Faculty".to_string(),
+                "This user can't be assigned grievances".to_string(),
             ));
         }
     }
 
     tracing::info!("Updating grievance assignment in DB...");
-    sqlx::query(
-        "UPDATE grievances SET assigned_to = $1, assigned_department = $2 WHERE id = $3",
-    )
-    .bind(payload.assigned_to)
-    .bind(&payload.assigned_department)
-    .bind(id)
-    .execute(&pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("SQLX Error updating assignment: {:?}", e);
+    let updated = repos.grievances.assign(&user, id, payload).await.map_err(|e| {
+        tracing::error!("Error updating assignment: {:?}", e);
         e
     })?;
 
-    tracing::info!("Logging assignment to audit_logs...");
-    sqlx::query("INSERT INTO audit_logs (user_id, action, metadata) VALUES ($1, $2, $3)")
-        .bind(user.id)
-        .bind("ASSIGN_GRIEVANCE")
-        .bind(serde_json::json!({
-            "grievance_id": id,
-            "assigned_to": payload.assigned_to,
-            "assigned_department": payload.assigned_department,
-        }))
-        .execute(&pool)
-        .await
-        .map_err(|e| {
-            tracing::error!("SQLX Error inserting audit log: {:?}", e);
-            e
-        })?;
-
-    let updated = sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
-        .bind(id)
-        .fetch_one(&pool)
-        .await
-        .map_err(|e| {
-            tracing::error!("SQLX Error fetching updated grievance: {:?}", e);
-            e
-        })?;
-
     tracing::info!("==== [SUCCESS] assign_grievance ====");
     
     let response = GrievanceResponse {
@@ -587,43 +293,26 @@ pub async fn assign_grievance(
         success: true,
         data: Some(response),
         message: Some("Grievance assigned successfully".to_string()),
+        error: None,
     }))
 }
 
 // ============================================================================
-// RESOLVE GRIEVANCE (Authority/Admin only)
+// RESOLVE GRIEVANCE (grievance.resolve)
 // ============================================================================
 pub async fn resolve_grievance(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Path(id): Path<Uuid>,
     Json(payload): Json<ResolveGrievanceRequest>,
 ) -> Result<Json<ApiResponse<GrievanceResponse>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
+    let grievance = repos.grievances.find(id).await?.ok_or(AppError::NotFound)?;
 
-    if !matches!(user.role, UserRole::Authority | UserRole::Admin) {
-        return Err(AppError::Forbidden);
-    }
+    authorize(&repos, &user, Permission::GrievanceResolve, Resource::Grievance(&grievance)).await?;
 
-    sqlx::query(
-        r#"
-        UPDATE grievances 
-        SET status = 'resolved', 
-            resolution_notes = $1, 
-            resolved_at = NOW(), 
-            resolved_by = $2
-        WHERE id = $3
-        "#,
-    )
-    .bind(&payload.resolution_notes)
-    .bind(user.id)
-    .bind(id)
-    .execute(&pool)
-    .await?;
-
-    let updated = sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
-        .bind(id)
-        .fetch_one(&pool)
+    let updated = repos
+        .grievances
+        .resolve(&user, &grievance, payload.resolution_notes)
         .await?;
 
     let response = GrievanceResponse {
@@ -653,6 +342,7 @@ pub async fn resolve_grievance(
         success: true,
         data: Some(response),
         message: Some("Grievance resolved successfully".to_string()),
+        error: None,
     }))
 }
 
@@ -660,120 +350,62 @@ pub async fn resolve_grievance(
 // UPVOTE/REMOVE UPVOTE GRIEVANCE
 // ============================================================================
 pub async fn toggle_upvote(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<()>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-
-    // Check if grievance exists
-    sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    let grievance = repos.grievances.find(id).await?.ok_or(AppError::NotFound)?;
 
-    // Check if user has already upvoted
-    let has_upvoted = sqlx::query_scalar::<_, bool>(
-        "SELECT EXISTS(SELECT 1 FROM grievance_upvotes WHERE grievance_id = $1 AND user_id = $2)",
-    )
-    .bind(id)
-    .bind(user.id)
-    .fetch_one(&pool)
-    .await?;
+    // Only grievances the user can see may be upvoted
+    authorize(&repos, &user, Permission::GrievanceView, Resource::Grievance(&grievance)).await?;
 
-    if has_upvoted {
-        // Remove upvote
-        sqlx::query("DELETE FROM grievance_upvotes WHERE grievance_id = $1 AND user_id = $2")
-            .bind(id)
-            .bind(user.id)
-            .execute(&pool)
-            .await?;
+    let upvoted = repos.grievances.toggle_upvote(&user, id).await?;
+    let message = if upvoted { "Upvoted successfully" } else { "Upvote removed" };
 
-        sqlx::query("UPDATE grievances SET upvote_count = upvote_count - 1 WHERE id = $1")
-            .bind(id)
-            .execute(&pool)
-            .await?;
-
-        Ok(Json(ApiResponse {
-            success: true,
-            data: None,
-            message: Some("Upvote removed".to_string()),
-        }))
-    } else {
-        // Add upvote
-        sqlx::query("INSERT INTO grievance_upvotes (grievance_id, user_id) VALUES ($1, $2)")
-            .bind(id)
-            .bind(user.id)
-            .execute(&pool)
-            .await?;
-
-        sqlx::query("UPDATE grievances SET upvote_count = upvote_count + 1 WHERE id = $1")
-            .bind(id)
-            .execute(&pool)
-            .await?;
-
-        Ok(Json(ApiResponse {
-            success: true,
-            data: None,
-            message: Some("Upvoted successfully".to_string()),
-        }))
-    }
+    Ok(Json(ApiResponse {
+        success: true,
+        data: None,
+        message: Some(message.to_string()),
+        error: None,
+    }))
 }
 
 // ============================================================================
 // GET GRIEVANCE STATUS HISTORY
 // ============================================================================
 pub async fn get_grievance_history(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<Vec<GrievanceStatusHistoryResponse>>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
+    let grievance = repos.grievances.find(id).await?.ok_or(AppError::NotFound)?;
 
-    let grievance = sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    authorize(&repos, &user, Permission::GrievanceView, Resource::Grievance(&grievance)).await?;
 
-    if !can_view_grievance(&user, &grievance) {
-        return Err(AppError::Forbidden);
-    }
+    let history = repos.grievances.history(id).await?;
 
-    let history = sqlx::query_as::<_, GrievanceStatusHistory>(
-        "SELECT * FROM grievance_status_history WHERE grievance_id = $1 ORDER BY created_at DESC",
+    let users = UserMap::load(
+        repos.users.as_ref(),
+        history.iter().filter_map(|e| e.updated_by),
     )
-    .bind(id)
-    .fetch_all(&pool)
     .await?;
-
-    let mut responses = Vec::new();
-    for entry in history {
-        let updated_by_user = if let Some(user_id) = entry.updated_by {
-            sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-                .bind(user_id)
-                .fetch_optional(&pool)
-                .await?
-                .map(UserResponse::from)
-        } else {
-            None
-        };
-
-        responses.push(GrievanceStatusHistoryResponse {
+    let responses: Vec<GrievanceStatusHistoryResponse> = history
+        .into_iter()
+        .map(|entry| GrievanceStatusHistoryResponse {
             id: entry.id,
             old_status: entry.old_status,
             new_status: entry.new_status,
             remarks: entry.remarks,
-            updated_by: updated_by_user,
+            updated_by: users.response(entry.updated_by),
             created_at: entry.created_at,
-        });
-    }
+        })
+        .collect();
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some(responses),
         message: None,
+        error: None,
     }))
 }
 
@@ -781,40 +413,29 @@ pub async fn get_grievance_history(
 // ADD COMMENT TO GRIEVANCE
 // ============================================================================
 pub async fn add_comment(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Path(id): Path<Uuid>,
     Json(payload): Json<CreateCommentRequest>,
 ) -> Result<Json<ApiResponse<GrievanceCommentResponse>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
+    let grievance = repos.grievances.find(id).await?.ok_or(AppError::NotFound)?;
 
-    let grievance = sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(AppError::NotFound)?;
-
-    if !can_view_grievance(&user, &grievance) {
-        return Err(AppError::Forbidden);
-    }
+    authorize(&repos, &user, Permission::GrievanceView, Resource::Grievance(&grievance)).await?;
 
-    // Only authorities and admins can add internal notes
+    // Internal notes need their own permission; otherwise the comment is public
     let is_internal = payload.is_internal
-        && matches!(user.role, UserRole::Authority | UserRole::Admin);
-
-    let comment = sqlx::query_as::<_, GrievanceComment>(
-        r#"
-        INSERT INTO grievance_comments (grievance_id, user_id, comment, is_internal)
-        VALUES ($1, $2, $3, $4)
-        RETURNING *
-        "#,
-    )
-    .bind(id)
-    .bind(user.id)
-    .bind(&payload.comment)
-    .bind(is_internal)
-    .fetch_one(&pool)
-    .await?;
+        && allowed(
+            &repos,
+            &user,
+            Permission::GrievanceInternalComments,
+            Resource::Grievance(&grievance),
+        )
+        .await?;
+
+    let comment = repos
+        .grievances
+        .add_comment(&user, id, payload.comment, is_internal)
+        .await?;
 
     let response = GrievanceCommentResponse {
         id: comment.id,
@@ -828,6 +449,7 @@ pub async fn add_comment(
         success: true,
         data: Some(response),
         message: Some("Comment added successfully".to_string()),
+        error: None,
     }))
 }
 
@@ -835,59 +457,41 @@ pub async fn add_comment(
 // GET COMMENTS FOR GRIEVANCE
 // ============================================================================
 pub async fn get_comments(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Path(id): Path<Uuid>,
-) -> Result<Json<ApiResponse<Vec<GrievanceCommentResponse>>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-
-    let grievance = sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(AppError::NotFound)?;
-
-    if !can_view_grievance(&user, &grievance) {
-        return Err(AppError::Forbidden);
-    }
-
-    // Filter internal comments based on user role
-    let comments = if matches!(user.role, UserRole::Authority | UserRole::Admin) {
-        sqlx::query_as::<_, GrievanceComment>(
-            "SELECT * FROM grievance_comments WHERE grievance_id = $1 ORDER BY created_at ASC",
-        )
-        .bind(id)
-        .fetch_all(&pool)
-        .await?
-    } else {
-        sqlx::query_as::<_, GrievanceComment>(
-            "SELECT * FROM grievance_comments WHERE grievance_id = $1 AND is_internal = false ORDER BY created_at ASC",
-        )
-        .bind(id)
-        .fetch_all(&pool)
-        .await?
-    };
-
-    let mut responses = Vec::new();
-    for comment in comments {
-        let comment_user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-            .bind(comment.user_id)
-            .fetch_one(&pool)
-            .await?;
+    Query(params): Query<PageParams>,
+) -> Result<Json<ApiResponse<Paginated<GrievanceCommentResponse>>>, AppError> {
+    let grievance = repos.grievances.find(id).await?.ok_or(AppError::NotFound)?;
+
+    authorize(&repos, &user, Permission::GrievanceView, Resource::Grievance(&grievance)).await?;
+
+    // Internal comments are only shown to those allowed to write them
+    let show_internal = allowed(
+        &repos,
+        &user,
+        Permission::GrievanceInternalComments,
+        Resource::Grievance(&grievance),
+    )
+    .await?;
+    let page = repos.grievances.comments(id, show_internal, params).await?;
 
-        responses.push(GrievanceCommentResponse {
+    let users = UserMap::load(repos.users.as_ref(), page.items.iter().map(|c| c.user_id)).await?;
+    let page = page.try_map(|comment| -> Result<_, AppError> {
+        Ok(GrievanceCommentResponse {
             id: comment.id,
-            user: UserResponse::from(comment_user),
+            user: users.required(comment.user_id)?,
             comment: comment.comment,
             is_internal: comment.is_internal,
             created_at: comment.created_at,
-        });
-    }
+        })
+    })?;
 
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(responses),
+        data: Some(page),
         message: None,
+        error: None,
     }))
 }
 
@@ -895,53 +499,32 @@ pub async fn get_comments(
 // GET DEPARTMENTS
 // ============================================================================
 pub async fn get_departments(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    _: AuthUser,
 ) -> Result<Json<ApiResponse<Vec<Department>>>, AppError> {
-    let _user = get_session_user(&session, &pool).await?;
-
-    let departments = sqlx::query_as::<_, Department>("SELECT * FROM departments ORDER BY name")
-        .fetch_all(&pool)
-        .await?;
+    let departments = repos.grievances.departments().await?;
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some(departments),
         message: None,
+        error: None,
     }))
 }
 
 // ============================================================================
-// DELETE GRIEVANCE (Student can delete their own, Admin can delete any)
+// DELETE GRIEVANCE
 // ============================================================================
 pub async fn delete_grievance(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    AuthUser(user): AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<(StatusCode, Json<ApiResponse<()>>), AppError> {
-    let user = get_session_user(&session, &pool).await?;
+    let grievance = repos.grievances.find(id).await?.ok_or(AppError::NotFound)?;
 
-    let grievance = sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    authorize(&repos, &user, Permission::GrievanceDelete, Resource::Grievance(&grievance)).await?;
 
-    // Students can delete their own grievances, Admins can delete any
-    let can_delete = match user.role {
-        UserRole::Admin => true,
-        UserRole::Student => grievance.submitted_by == Some(user.id),
-        _ => false,
-    };
-
-    if !can_delete {
-        return Err(AppError::Forbidden);
-    }
-
-    sqlx::query("DELETE FROM grievances WHERE id = $1")
-        .bind(id)
-        .execute(&pool)
-        .await?;
+    repos.grievances.delete(&user, &grievance).await?;
 
     Ok((
         StatusCode::OK,
@@ -949,6 +532,7 @@ pub async fn delete_grievance(
             success: true,
             data: None,
             message: Some("Grievance deleted successfully".to_string()),
+            error: None,
         }),
     ))
 }
@@ -957,28 +541,23 @@ pub async fn delete_grievance(
 // UPLOAD PHOTOS FOR GRIEVANCE
 // ============================================================================
 pub async fn upload_grievance_photos(
-    State(pool): State<PgPool>,
-    session: Session,
+    State(repos): State<Repos>,
+    State(config): State<Arc<AppConfig>>,
+    AuthUser(user): AuthUser,
     Path(id): Path<Uuid>,
     mut multipart: Multipart,
 ) -> Result<Json<ApiResponse<Vec<String>>>, AppError> {
-    let user = get_session_user(&session, &pool).await?;
-
     // Check if grievance exists and user has permission
-    let grievance = sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    let grievance = repos.grievances.find(id).await?.ok_or(AppError::NotFound)?;
 
-    if !can_modify_grievance(&user, &grievance) {
-        return Err(AppError::Forbidden);
-    }
+    authorize(&repos, &user, Permission::GrievanceEdit, Resource::Grievance(&grievance)).await?;
 
     // Initialize Cloudinary service
-    let cloudinary_config = CloudinaryConfig::from_env()
-        .map_err(|e| AppError::InternalServerError(format!("Cloudinary config error: {}", e)))?;
+    let cloudinary_config = config.cloudinary.clone().ok_or_else(|| {
+        AppError::InternalServerError("Photo uploads are not configured".to_string())
+    })?;
     let cloudinary = CloudinaryService::new(cloudinary_config);
+    let limits = &config.uploads;
 
     let mut uploaded_urls = Vec::new();
 
@@ -991,11 +570,25 @@ pub async fn upload_grievance_photos(
         let name = field.name().unwrap_or("").to_string();
 
         if name == "photos" {
+            if uploaded_urls.len() >= limits.max_photos {
+                return Err(AppError::BadRequest(format!(
+                    "At most {} photos can be uploaded at once",
+                    limits.max_photos
+                )));
+            }
+
             let data = field
                 .bytes()
                 .await
                 .map_err(|e| AppError::BadRequest(format!("Failed to read file: {}", e)))?;
 
+            if data.len() > limits.max_photo_bytes {
+                return Err(AppError::HttpError(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    anyhow::anyhow!("Each photo must be at most {} bytes", limits.max_photo_bytes),
+                ));
+            }
+
             // Convert bytes to base64
             let base64_data = base64::prelude::BASE64_STANDARD.encode(&data);
 
@@ -1018,20 +611,212 @@ pub async fn upload_grievance_photos(
         ));
     }
 
-    // Update grievance with photo URLs
-    let mut existing_urls = grievance.photo_urls.unwrap_or_default();
-    existing_urls.extend(uploaded_urls.clone());
-
-    sqlx::query("UPDATE grievances SET photo_urls = $1 WHERE id = $2")
-        .bind(&existing_urls)
-        .bind(id)
-        .execute(&pool)
-        .await?;
+    // Append the new photo URLs to the grievance
+    repos.grievances.add_photos(&user, id, &uploaded_urls).await?;
 
     Ok(Json(ApiResponse {
         success: true,
         data: Some(uploaded_urls),
         message: Some("Photos uploaded successfully".to_string()),
+        error: None,
     }))
 }
 
+
+// ============================================================================
+// UPVOTE COUNT RECONCILIATION
+// ============================================================================
+
+// Periodically rewrite `upvote_count` wherever it disagrees with
+// `grievance_upvotes`; errors are logged and retried on the next tick
+pub async fn reconcile_upvotes_periodically(
+    grievances: Arc<dyn GrievanceRepo>,
+    period: std::time::Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        match grievances.reconcile_upvote_counts().await {
+            Ok(0) => tracing::debug!("Upvote counts are consistent"),
+            Ok(n) => tracing::warn!("Corrected upvote_count on {} grievances", n),
+            Err(e) => tracing::error!("Failed to reconcile upvote counts: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repos::memory::{Memory, body};
+    use serde_json::json;
+
+    fn grievance_request(is_anonymous: bool) -> Json<CreateGrievanceRequest> {
+        body(json!({
+            "title": "Broken tap",
+            "description": "The tap on the second floor leaks",
+            "category": "hostel",
+            "priority": "medium",
+            "location_type": null,
+            "location_details": null,
+            "is_anonymous": is_anonymous,
+        }))
+    }
+
+    // A store where students may file grievances and view their own
+    fn setup() -> (Arc<Memory>, Repos, User) {
+        let (memory, repos) = Memory::with_grants(&[
+            (UserRole::Student, Permission::GrievanceCreate, PermissionScope::Any),
+            (UserRole::Student, Permission::GrievanceView, PermissionScope::Own),
+        ]);
+        let student = memory.add_user(UserRole::Student);
+        (memory, repos, student)
+    }
+
+    async fn file(repos: &Repos, user: &User, is_anonymous: bool) -> GrievanceResponse {
+        let response = create_grievance(
+            State(repos.clone()),
+            AuthUser(user.clone()),
+            grievance_request(is_anonymous),
+        )
+        .await
+        .unwrap();
+        response.0.data.unwrap()
+    }
+
+    #[tokio::test]
+    async fn create_needs_a_grant() {
+        let (memory, repos, _) = setup();
+        let faculty = memory.add_user(UserRole::Faculty);
+
+        let result =
+            create_grievance(State(repos), AuthUser(faculty), grievance_request(false)).await;
+        assert!(matches!(result, Err(AppError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn anonymous_grievances_have_no_submitter() {
+        let (_, repos, student) = setup();
+
+        let created = file(&repos, &student, true).await;
+        let stored = repos.grievances.find(created.id).await.unwrap().unwrap();
+        assert_eq!(stored.submitted_by, None);
+        assert!(stored.anonymous_identifier.unwrap().starts_with("ANON-"));
+    }
+
+    #[tokio::test]
+    async fn own_view_grant_covers_only_own_grievances() {
+        let (memory, repos, student) = setup();
+        let other = memory.add_user(UserRole::Student);
+        let mine = file(&repos, &student, false).await;
+        let theirs = file(&repos, &other, false).await;
+
+        let viewed =
+            get_grievance_by_id(State(repos.clone()), AuthUser(student.clone()), Path(mine.id))
+                .await
+                .unwrap();
+        assert_eq!(viewed.0.data.unwrap().view_count, 1);
+
+        let result = get_grievance_by_id(State(repos), AuthUser(student), Path(theirs.id)).await;
+        assert!(matches!(result, Err(AppError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn assignee_needs_the_handle_permission() {
+        let (memory, repos, student) = setup();
+        memory.grant(UserRole::Authority, Permission::GrievanceAssign, PermissionScope::Any);
+        memory.grant(UserRole::Faculty, Permission::GrievanceHandle, PermissionScope::Any);
+        let authority = memory.add_user(UserRole::Authority);
+        let faculty = memory.add_user(UserRole::Faculty);
+        let grievance = file(&repos, &student, false).await;
+
+        let result = assign_grievance(
+            State(repos.clone()),
+            AuthUser(authority.clone()),
+            Path(grievance.id),
+            body(json!({ "assigned_to": student.id, "assigned_department": null })),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        let assigned = assign_grievance(
+            State(repos.clone()),
+            AuthUser(authority),
+            Path(grievance.id),
+            body(json!({ "assigned_to": faculty.id, "assigned_department": "Estate" })),
+        )
+        .await
+        .unwrap();
+        assert_eq!(assigned.0.data.unwrap().assigned_department.as_deref(), Some("Estate"));
+        let stored = repos.grievances.find(grievance.id).await.unwrap().unwrap();
+        assert_eq!(stored.assigned_to, Some(faculty.id));
+    }
+
+    #[tokio::test]
+    async fn internal_comments_are_only_seen_by_those_allowed_to_write_them() {
+        let (memory, repos, student) = setup();
+        memory.grant(UserRole::Authority, Permission::GrievanceView, PermissionScope::Any);
+        memory.grant(
+            UserRole::Authority,
+            Permission::GrievanceInternalComments,
+            PermissionScope::Any,
+        );
+        let authority = memory.add_user(UserRole::Authority);
+        let grievance = file(&repos, &student, false).await;
+
+        let comment = |user: &User, text: &str| {
+            add_comment(
+                State(repos.clone()),
+                AuthUser(user.clone()),
+                Path(grievance.id),
+                body(json!({ "comment": text, "is_internal": true })),
+            )
+        };
+        // Students can't write internal notes, so theirs is posted publicly
+        let posted = comment(&student, "Any update?").await.unwrap();
+        assert!(!posted.0.data.unwrap().is_internal);
+        let posted = comment(&authority, "Plumber booked").await.unwrap();
+        assert!(posted.0.data.unwrap().is_internal);
+
+        let seen_by = |user: &User| {
+            get_comments(
+                State(repos.clone()),
+                AuthUser(user.clone()),
+                Path(grievance.id),
+                Query(PageParams::default()),
+            )
+        };
+        let student_view = seen_by(&student).await.unwrap().0.data.unwrap();
+        assert_eq!(student_view.items.len(), 1);
+        let authority_view = seen_by(&authority).await.unwrap().0.data.unwrap();
+        assert_eq!(authority_view.items.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn upvote_toggles() {
+        let (memory, repos, student) = setup();
+        let grievance = file(&repos, &student, false).await;
+
+        let toggle = || {
+            toggle_upvote(State(repos.clone()), AuthUser(student.clone()), Path(grievance.id))
+        };
+        let first = toggle().await.unwrap();
+        assert_eq!(first.0.message.as_deref(), Some("Upvoted successfully"));
+        assert_eq!(memory.upvote_count(grievance.id), 1);
+
+        let second = toggle().await.unwrap();
+        assert_eq!(second.0.message.as_deref(), Some("Upvote removed"));
+        assert_eq!(memory.upvote_count(grievance.id), 0);
+    }
+
+    #[tokio::test]
+    async fn upvoting_needs_view_access() {
+        let (memory, repos, student) = setup();
+        let grievance = file(&repos, &student, false).await;
+        let other = memory.add_user(UserRole::Student);
+
+        let result =
+            toggle_upvote(State(repos.clone()), AuthUser(other), Path(grievance.id)).await;
+        assert!(matches!(result, Err(AppError::Forbidden)));
+        assert_eq!(memory.upvote_count(grievance.id), 0);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::config::AppConfig;
+use crate::migrate;
+use axum::{extract::State, http::StatusCode, response::Json};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+// Upper bound on each readiness check so a hung database doesn't hang the probe
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks: Option<ReadinessChecks>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessChecks {
+    pub database: String,
+    pub migrations: String,
+    pub cloudinary: &'static str,
+}
+
+// ============================================================================
+// LIVENESS
+// ============================================================================
+
+// The process is up and serving requests; deliberately independent of the database
+pub async fn healthz() -> Json<HealthReport> {
+    Json(HealthReport {
+        status: "ok",
+        checks: None,
+    })
+}
+
+// ============================================================================
+// READINESS
+// ============================================================================
+
+// Ready to take traffic: database reachable and schema current.
+// Reports 503 once shutdown has started so load balancers stop routing here while we drain.
+pub async fn readyz(
+    State(pool): State<PgPool>,
+    State(config): State<Arc<AppConfig>>,
+    State(shutdown): State<CancellationToken>,
+) -> (StatusCode, Json<HealthReport>) {
+    let database =
+        match tokio::time::timeout(CHECK_TIMEOUT, sqlx::query("SELECT 1").execute(&pool)).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err("timed out".to_string()),
+        };
+
+    // Only meaningful when the database answered
+    let migrations = match &database {
+        Ok(()) => {
+            match tokio::time::timeout(CHECK_TIMEOUT, migrate::ensure_up_to_date(&pool)).await {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(_) => Err("timed out".to_string()),
+            }
+        }
+        Err(_) => Err("skipped".to_string()),
+    };
+
+    let ready = database.is_ok() && migrations.is_ok() && !shutdown.is_cancelled();
+    if !ready {
+        tracing::warn!(
+            "Readiness check failed: database={:?} migrations={:?} shutting_down={}",
+            database,
+            migrations,
+            shutdown.is_cancelled()
+        );
+    }
+
+    let describe = |result: Result<(), String>| match result {
+        Ok(()) => "ok".to_string(),
+        Err(e) => e,
+    };
+
+    let report = HealthReport {
+        status: if shutdown.is_cancelled() {
+            "shutting_down"
+        } else if ready {
+            "ok"
+        } else {
+            "unavailable"
+        },
+        checks: Some(ReadinessChecks {
+            database: describe(database),
+            migrations: describe(migrations),
+            // Uploads are optional, so this is informational and doesn't affect readiness
+            cloudinary: if config.cloudinary.is_some() {
+                "configured"
+            } else {
+                "not configured"
+            },
+        }),
+    };
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+// ============================================================================
+// SHUTDOWN
+// ============================================================================
+
+// Resolves on SIGTERM (what orchestrators send during a rolling deploy) or Ctrl+C
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received Ctrl+C, shutting down"),
+        _ = terminate => tracing::info!("Received SIGTERM, shutting down"),
+    }
+}
@@ -0,0 +1,374 @@
+use crate::config::{IdentityProviderConfig, OAuthClientConfig};
+use crate::error::AppError;
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+const GOOGLE_AUTHORIZATION_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_USERINFO_URL: &str = "https://www.googleapis.com/oauth2/v3/userinfo";
+
+// Where the dev provider sends the browser instead of a consent screen
+#[cfg(any(test, feature = "dev-routes"))]
+const DEV_CALLBACK_PATH: &str = "/auth/callback";
+
+// Profile of the signed-in user, in standard OIDC claim names.
+// `sub` is stable per provider account and is stored in `users.google_id`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExternalIdentity {
+    pub sub: String,
+    pub email: String,
+    #[serde(default)]
+    pub email_verified: Option<bool>,
+    #[serde(default)]
+    pub given_name: String,
+    pub family_name: Option<String>,
+    pub picture: Option<String>,
+}
+
+// What the login handler knows when it sends the browser off to sign in
+pub struct AuthorizationRequest<'a> {
+    pub state: &'a str,
+    pub code_challenge: &'a str,
+    // Provider-specific hint about which account to use, e.g. an email
+    pub login_hint: Option<&'a str>,
+}
+
+#[async_trait]
+pub trait IdentityProvider: Send + Sync {
+    // Recorded in the login audit entry
+    fn name(&self) -> &'static str;
+
+    // URL to redirect the browser to
+    async fn authorization_url(&self, request: AuthorizationRequest<'_>) -> Result<String, AppError>;
+
+    // Redeem the code from the callback for the user's profile
+    async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<ExternalIdentity, AppError>;
+}
+
+// `pool` is only used by the dev provider
+#[cfg_attr(not(any(test, feature = "dev-routes")), allow(unused_variables))]
+pub fn from_config(config: &IdentityProviderConfig, pool: PgPool) -> Arc<dyn IdentityProvider> {
+    match config {
+        IdentityProviderConfig::Google(client) => Arc::new(GoogleProvider::new(client.clone())),
+        IdentityProviderConfig::Oidc {
+            issuer_url,
+            client,
+            assume_email_verified,
+        } => Arc::new(OidcProvider::new(
+            issuer_url.clone(),
+            client.clone(),
+            *assume_email_verified,
+        )),
+        #[cfg(any(test, feature = "dev-routes"))]
+        IdentityProviderConfig::Dev => {
+            tracing::warn!("AUTH_PROVIDER=dev: anyone can sign in as any existing user");
+            Arc::new(DevProvider { pool })
+        }
+    }
+}
+
+// ============================================================================
+// OAUTH 2.0 AUTHORIZATION CODE FLOW (shared by Google and OIDC)
+// ============================================================================
+
+#[derive(Debug, Clone, Deserialize)]
+struct Endpoints {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TokenRequest<'a> {
+    code: &'a str,
+    client_id: &'a str,
+    client_secret: &'a str,
+    redirect_uri: &'a str,
+    grant_type: &'a str,
+    code_verifier: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+fn authorization_url(
+    endpoints: &Endpoints,
+    client: &OAuthClientConfig,
+    request: AuthorizationRequest<'_>,
+) -> String {
+    let mut url = format!(
+        "{}?client_id={}&redirect_uri={}&response_type=code&scope=openid%20email%20profile&state={}&code_challenge={}&code_challenge_method=S256",
+        endpoints.authorization_endpoint,
+        urlencoding::encode(&client.client_id),
+        urlencoding::encode(&client.redirect_url),
+        request.state,
+        request.code_challenge
+    );
+    if let Some(hint) = request.login_hint {
+        url.push_str(&format!("&login_hint={}", urlencoding::encode(hint)));
+    }
+    url
+}
+
+async fn exchange_code(
+    http: &reqwest::Client,
+    endpoints: &Endpoints,
+    client: &OAuthClientConfig,
+    code: &str,
+    code_verifier: &str,
+) -> Result<ExternalIdentity, AppError> {
+    tracing::info!("TOKEN_EXCHANGE: Starting token exchange with {}", endpoints.token_endpoint);
+    let token_response = http
+        .post(&endpoints.token_endpoint)
+        .form(&TokenRequest {
+            code,
+            client_id: &client.client_id,
+            client_secret: &client.client_secret,
+            redirect_uri: &client.redirect_url,
+            grant_type: "authorization_code",
+            code_verifier,
+        })
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| {
+            tracing::error!("TOKEN_EXCHANGE_ERROR: Token request failed: {}", e);
+            AppError::HttpError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
+        })?
+        .json::<TokenResponse>()
+        .await
+        .map_err(|e| {
+            tracing::error!("TOKEN_PARSE_ERROR: Failed to parse token response: {}", e);
+            AppError::HttpError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
+        })?;
+    tracing::info!("TOKEN_EXCHANGE: Success");
+
+    let identity = http
+        .get(&endpoints.userinfo_endpoint)
+        .bearer_auth(&token_response.access_token)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| {
+            tracing::error!("USER_INFO_ERROR: Failed to fetch user info: {}", e);
+            AppError::HttpError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
+        })?
+        .json::<ExternalIdentity>()
+        .await
+        .map_err(|e| {
+            tracing::error!("USER_INFO_PARSE_ERROR: Failed to parse user info: {}", e);
+            AppError::HttpError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
+        })?;
+
+    Ok(identity)
+}
+
+// Domain and role rules and matching an existing account by email all trust the
+// address, so the provider has to vouch for it. A missing claim only counts as
+// verified for issuers configured to `assume_verified`.
+fn require_verified_email(
+    mut identity: ExternalIdentity,
+    assume_verified: bool,
+) -> Result<ExternalIdentity, AppError> {
+    match identity.email_verified {
+        Some(true) => Ok(identity),
+        None if assume_verified => {
+            identity.email_verified = Some(true);
+            Ok(identity)
+        }
+        _ => Err(AppError::HttpError(
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Email address is not verified"),
+        )),
+    }
+}
+
+// ============================================================================
+// GOOGLE
+// ============================================================================
+
+pub struct GoogleProvider {
+    client: OAuthClientConfig,
+    endpoints: Endpoints,
+    http: reqwest::Client,
+}
+
+impl GoogleProvider {
+    pub fn new(client: OAuthClientConfig) -> Self {
+        Self {
+            client,
+            endpoints: Endpoints {
+                authorization_endpoint: GOOGLE_AUTHORIZATION_URL.to_string(),
+                token_endpoint: GOOGLE_TOKEN_URL.to_string(),
+                userinfo_endpoint: GOOGLE_USERINFO_URL.to_string(),
+            },
+            http: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for GoogleProvider {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    async fn authorization_url(&self, request: AuthorizationRequest<'_>) -> Result<String, AppError> {
+        Ok(authorization_url(&self.endpoints, &self.client, request))
+    }
+
+    async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<ExternalIdentity, AppError> {
+        let identity =
+            exchange_code(&self.http, &self.endpoints, &self.client, code, code_verifier).await?;
+        require_verified_email(identity, false)
+    }
+}
+
+// ============================================================================
+// GENERIC OIDC
+// ============================================================================
+
+pub struct OidcProvider {
+    issuer_url: String,
+    client: OAuthClientConfig,
+    // Fetched on first use so the server can start while the issuer is unreachable
+    endpoints: OnceCell<Endpoints>,
+    http: reqwest::Client,
+    assume_email_verified: bool,
+}
+
+impl OidcProvider {
+    pub fn new(issuer_url: String, client: OAuthClientConfig, assume_email_verified: bool) -> Self {
+        Self {
+            issuer_url,
+            client,
+            assume_email_verified,
+            endpoints: OnceCell::new(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    async fn endpoints(&self) -> Result<&Endpoints, AppError> {
+        self.endpoints
+            .get_or_try_init(|| async {
+                let url = format!("{}/.well-known/openid-configuration", self.issuer_url);
+                tracing::info!("OIDC_DISCOVERY: Fetching {}", url);
+                let endpoints = self
+                    .http
+                    .get(&url)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())?
+                    .json::<Endpoints>()
+                    .await?;
+                Ok(endpoints)
+            })
+            .await
+            .map_err(|e: reqwest::Error| {
+                tracing::error!("OIDC_DISCOVERY_ERROR: {}", e);
+                AppError::HttpError(StatusCode::INTERNAL_SERVER_ERROR, e.into())
+            })
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for OidcProvider {
+    fn name(&self) -> &'static str {
+        "oidc"
+    }
+
+    async fn authorization_url(&self, request: AuthorizationRequest<'_>) -> Result<String, AppError> {
+        Ok(authorization_url(self.endpoints().await?, &self.client, request))
+    }
+
+    async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<ExternalIdentity, AppError> {
+        let endpoints = self.endpoints().await?;
+        let identity = exchange_code(&self.http, endpoints, &self.client, code, code_verifier).await?;
+        require_verified_email(identity, self.assume_email_verified)
+    }
+}
+
+// ============================================================================
+// DEV
+// ============================================================================
+
+// Signs in as an existing user picked by email (`/auth/login?login_hint=<email>`).
+// The "code" is just that email, handed straight back to our own callback.
+// Not in release builds, see `IdentityProviderConfig::Dev`.
+#[cfg(any(test, feature = "dev-routes"))]
+pub struct DevProvider {
+    pool: PgPool,
+}
+
+#[cfg(any(test, feature = "dev-routes"))]
+#[async_trait]
+impl IdentityProvider for DevProvider {
+    fn name(&self) -> &'static str {
+        "dev"
+    }
+
+    async fn authorization_url(&self, request: AuthorizationRequest<'_>) -> Result<String, AppError> {
+        let email = request.login_hint.ok_or_else(|| {
+            AppError::BadRequest("Dev login needs login_hint=<email of an existing user>".to_string())
+        })?;
+        Ok(format!(
+            "{}?code={}&state={}",
+            DEV_CALLBACK_PATH,
+            urlencoding::encode(email),
+            request.state
+        ))
+    }
+
+    async fn exchange_code(&self, code: &str, _code_verifier: &str) -> Result<ExternalIdentity, AppError> {
+        let user = sqlx::query_as::<_, crate::structs::User>("SELECT * FROM users WHERE email = $1")
+            .bind(code)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::BadRequest(format!("No user with email {}", code)))?;
+
+        Ok(ExternalIdentity {
+            sub: user.google_id,
+            email: user.email,
+            email_verified: Some(true),
+            given_name: user.first_name,
+            family_name: Some(user.last_name),
+            picture: user.profile_picture,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(email_verified: Option<bool>) -> ExternalIdentity {
+        ExternalIdentity {
+            sub: "sub-1".to_string(),
+            email: "someone@iitmandi.ac.in".to_string(),
+            email_verified,
+            given_name: "Someone".to_string(),
+            family_name: None,
+            picture: None,
+        }
+    }
+
+    #[test]
+    fn only_verified_emails_are_accepted() {
+        assert!(require_verified_email(identity(Some(true)), false).is_ok());
+        assert!(require_verified_email(identity(None), false).is_err());
+        assert!(require_verified_email(identity(Some(false)), false).is_err());
+    }
+
+    #[test]
+    fn a_missing_claim_can_be_assumed_verified() {
+        let identity = require_verified_email(identity(None), true).unwrap();
+        assert_eq!(identity.email_verified, Some(true));
+        assert!(require_verified_email(self::identity(Some(false)), true).is_err());
+    }
+}
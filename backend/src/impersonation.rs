@@ -0,0 +1,304 @@
+use crate::audit::{self, Actor, Target};
+use crate::auth::SESSION_USER_ID_KEY;
+use crate::error::AppError;
+use crate::extractors::{AuthUser, Json, Path};
+use crate::permissions::{Permission, Resource, allowed, authorize, role_grants};
+use crate::structs::*;
+use axum::{
+    extract::State,
+    http::{Method, StatusCode},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tower_sessions::Session;
+use uuid::Uuid;
+
+// Stored next to the admin's own user_id, which never changes while impersonating
+pub const SESSION_IMPERSONATION_KEY: &str = "impersonating";
+const DEFAULT_DURATION_MINUTES: i64 = 15;
+const MAX_DURATION_MINUTES: i64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Impersonation {
+    user_id: Uuid,
+    expires_at: DateTime<Utc>,
+    allow_writes: bool,
+}
+
+// Entries are written under the impersonated user with the admin as impersonator
+async fn record(
+    pool: &PgPool,
+    user_id: Uuid,
+    impersonator_id: Uuid,
+    action: &str,
+    metadata: serde_json::Value,
+) -> Result<(), AppError> {
+    let actor = Actor::Impersonating { user: user_id, admin: impersonator_id };
+    audit::record(pool, actor, action, Some(Target::User(user_id)), metadata).await
+}
+
+// Why `admin` may not view as `target`, if they may not. Accounts that manage
+// users or roles are off limits, and so is anyone granted something the admin
+// isn't, apart from self-service grants like submitting a grievance.
+async fn refusal(pool: &PgPool, admin: &User, target: &User) -> Result<Option<String>, AppError> {
+    let target_grants = role_grants(pool, &target.role).await?;
+    let manager = target_grants.iter().any(|g| {
+        matches!(
+            g.permission,
+            Permission::UserImpersonate | Permission::UserManage | Permission::RoleManage
+        )
+    });
+    if manager {
+        return Ok(Some(
+            "Users who manage other users or roles can't be impersonated".to_string(),
+        ));
+    }
+
+    let admin_grants = role_grants(pool, &admin.role).await?;
+    let beyond = target_grants.iter().find(|g| {
+        let covered = admin_grants.iter().any(|a| {
+            a.permission == g.permission && (a.scope == g.scope || a.scope == PermissionScope::Any)
+        });
+        !covered && !g.permission.self_service()
+    });
+    Ok(beyond.map(|g| {
+        format!(
+            "This user has {} ({:?}), which you don't",
+            g.permission.as_str(),
+            g.scope
+        )
+    }))
+}
+
+fn is_write(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+// The user a session request acts as: `admin` (the session's own user) unless
+// they are impersonating someone. Called by `AuthUser`, so each request is
+// checked and logged once.
+pub async fn effective_user(
+    pool: &PgPool,
+    session: &Session,
+    admin: User,
+    method: &Method,
+    path: &str,
+) -> Result<User, AppError> {
+    let Some(impersonation) = session.get::<Impersonation>(SESSION_IMPERSONATION_KEY).await? else {
+        return Ok(admin);
+    };
+
+    let target = if impersonation.expires_at <= Utc::now() {
+        Err("expired")
+    } else if !matches!(admin.status, UserStatus::Active)
+        || !allowed(pool, &admin, Permission::UserImpersonate, Resource::Global).await?
+    {
+        // Losing the permission mid-way ends it as well
+        Err("revoked")
+    } else {
+        // So does the target going away or gaining grants the admin lacks
+        match sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+            .bind(impersonation.user_id)
+            .fetch_optional(pool)
+            .await?
+        {
+            None => Err("target_deleted"),
+            Some(target) if !matches!(target.status, UserStatus::Active) => Err("target_inactive"),
+            Some(target) => match refusal(pool, &admin, &target).await? {
+                Some(_) => Err("target_privileged"),
+                None => Ok(target),
+            },
+        }
+    };
+    let target = match target {
+        Ok(target) => target,
+        Err(reason) => {
+            session.remove::<Impersonation>(SESSION_IMPERSONATION_KEY).await?;
+            record(
+                pool,
+                impersonation.user_id,
+                admin.id,
+                "IMPERSONATION_ENDED",
+                serde_json::json!({ "reason": reason }),
+            )
+            .await?;
+            return Ok(admin);
+        }
+    };
+
+    let blocked = is_write(method) && !impersonation.allow_writes;
+    record(
+        pool,
+        target.id,
+        admin.id,
+        "IMPERSONATED_REQUEST",
+        serde_json::json!({ "method": method.as_str(), "path": path, "blocked": blocked }),
+    )
+    .await?;
+
+    if blocked {
+        tracing::warn!("Blocked {} {} by admin {} impersonating {}", method, path, admin.id, target.id);
+        return Err(AppError::HttpError(
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Changes are disabled while viewing as another user"),
+        ));
+    }
+
+    Ok(target)
+}
+
+// Banner details for `/auth/me`, `None` when the session isn't impersonating
+pub async fn current(pool: &PgPool, session: &Session) -> Result<Option<ImpersonationInfo>, AppError> {
+    let Some(impersonation) = session.get::<Impersonation>(SESSION_IMPERSONATION_KEY).await? else {
+        return Ok(None);
+    };
+    let Some(admin_id) = session.get::<Uuid>(SESSION_USER_ID_KEY).await? else {
+        return Ok(None);
+    };
+
+    let admin = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(admin_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(Some(ImpersonationInfo {
+        admin: UserResponse::from(admin),
+        expires_at: impersonation.expires_at,
+        allow_writes: impersonation.allow_writes,
+    }))
+}
+
+// ============================================================================
+// START IMPERSONATION (user.impersonate)
+// ============================================================================
+pub async fn start_impersonation(
+    State(pool): State<PgPool>,
+    AuthUser(admin_user): AuthUser,
+    session: Session,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<StartImpersonationRequest>,
+) -> Result<Json<ApiResponse<CurrentUserResponse>>, AppError> {
+    authorize(&pool, &admin_user, Permission::UserImpersonate, Resource::Global).await?;
+
+    if session
+        .get::<Impersonation>(SESSION_IMPERSONATION_KEY)
+        .await?
+        .is_some()
+    {
+        return Err(AppError::Conflict(
+            "Already viewing as another user, stop that first".to_string(),
+        ));
+    }
+    // The impersonation lives in the browser session, so tokens can't start one
+    if session.get::<Uuid>(SESSION_USER_ID_KEY).await? != Some(admin_user.id) {
+        return Err(AppError::BadRequest(
+            "Impersonation needs a browser session".to_string(),
+        ));
+    }
+    if admin_user.id == id {
+        return Err(AppError::BadRequest("Cannot impersonate yourself".to_string()));
+    }
+
+    let reason = payload.reason.trim();
+    if reason.is_empty() || reason.len() > 500 {
+        return Err(AppError::BadRequest(
+            "A reason of at most 500 characters is required".to_string(),
+        ));
+    }
+    let minutes = payload.duration_minutes.unwrap_or(DEFAULT_DURATION_MINUTES);
+    if !(1..=MAX_DURATION_MINUTES).contains(&minutes) {
+        return Err(AppError::BadRequest(format!(
+            "duration_minutes must be between 1 and {}",
+            MAX_DURATION_MINUTES
+        )));
+    }
+    if payload.allow_writes {
+        authorize(&pool, &admin_user, Permission::UserImpersonateWrite, Resource::Global).await?;
+    }
+
+    let target = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if !matches!(target.status, UserStatus::Active) {
+        return Err(AppError::BadRequest("User is not active".to_string()));
+    }
+    if let Some(why) = refusal(&pool, &admin_user, &target).await? {
+        tracing::warn!("Admin {} refused impersonating user {}: {}", admin_user.id, target.id, why);
+        return Err(AppError::HttpError(StatusCode::FORBIDDEN, anyhow::anyhow!(why)));
+    }
+
+    let impersonation = Impersonation {
+        user_id: target.id,
+        expires_at: Utc::now() + Duration::minutes(minutes),
+        allow_writes: payload.allow_writes,
+    };
+    session
+        .insert(SESSION_IMPERSONATION_KEY, &impersonation)
+        .await?;
+
+    record(
+        &pool,
+        target.id,
+        admin_user.id,
+        "IMPERSONATION_STARTED",
+        serde_json::json!({
+            "reason": reason,
+            "expires_at": impersonation.expires_at,
+            "allow_writes": impersonation.allow_writes,
+        }),
+    )
+    .await?;
+    tracing::warn!("Admin {} is now viewing as user {}", admin_user.id, target.id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(CurrentUserResponse {
+            user: UserResponse::from(target),
+            impersonation: Some(ImpersonationInfo {
+                admin: UserResponse::from(admin_user),
+                expires_at: impersonation.expires_at,
+                allow_writes: impersonation.allow_writes,
+            }),
+        }),
+        message: Some(format!("Viewing as this user for {} minutes", minutes)),
+        error: None,
+    }))
+}
+
+// ============================================================================
+// STOP IMPERSONATION
+// ============================================================================
+// Reads the session directly rather than through `AuthUser`, which would
+// resolve to the impersonated user and refuse the write
+pub async fn stop_impersonation(
+    State(pool): State<PgPool>,
+    session: Session,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    let admin_id = session
+        .get::<Uuid>(SESSION_USER_ID_KEY)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+    let impersonation = session
+        .remove::<Impersonation>(SESSION_IMPERSONATION_KEY)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Not viewing as another user".to_string()))?;
+
+    record(
+        &pool,
+        impersonation.user_id,
+        admin_id,
+        "IMPERSONATION_ENDED",
+        serde_json::json!({ "reason": "stopped" }),
+    )
+    .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: None,
+        message: Some("Stopped viewing as another user".to_string()),
+        error: None,
+    }))
+}
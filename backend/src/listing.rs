@@ -0,0 +1,356 @@
+// Shared filtering, sorting and keyset paging for list endpoints. Query string
+// values always go through `push_bind`; column names only come from the
+// `&'static str`s each endpoint declares. Pages continue from an opaque
+// `cursor` (sort value and id of the last row), so deep pages cost the same.
+
+use crate::error::AppError;
+use crate::structs::Paginated;
+use base64::prelude::*;
+use serde::de::{DeserializeOwned, IntoDeserializer};
+use serde::{Deserialize, Deserializer};
+use sqlx::postgres::PgRow;
+use sqlx::{Encode, FromRow, PgPool, Postgres, QueryBuilder, Row, Type};
+use uuid::Uuid;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+// A column rows can be ordered by, with the Postgres type to read a cursor's value back
+#[derive(Debug, Clone, Copy)]
+pub struct Column {
+    pub name: &'static str,
+    pub sql_type: &'static str,
+    pub nullable: bool,
+}
+
+impl Column {
+    pub const fn new(name: &'static str, sql_type: &'static str) -> Self {
+        Self {
+            name,
+            sql_type,
+            nullable: false,
+        }
+    }
+
+    // Rows without a value sort last in either direction
+    pub const fn nullable(name: &'static str, sql_type: &'static str) -> Self {
+        Self {
+            name,
+            sql_type,
+            nullable: true,
+        }
+    }
+}
+
+// A column a list endpoint allows sorting on
+pub trait SortField: Copy {
+    fn column(self) -> Column;
+}
+
+impl SortField for Column {
+    fn column(self) -> Column {
+        self
+    }
+}
+
+// `?sort=field` for ascending or `?sort=-field` for descending
+#[derive(Debug, Clone, Copy)]
+pub struct Sort<F> {
+    pub field: F,
+    pub descending: bool,
+}
+
+impl<F> Sort<F> {
+    pub fn asc(field: F) -> Self {
+        Self {
+            field,
+            descending: false,
+        }
+    }
+
+    pub fn desc(field: F) -> Self {
+        Self {
+            field,
+            descending: true,
+        }
+    }
+}
+
+impl<'de, F: DeserializeOwned> Deserialize<'de> for Sort<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        let (name, descending) = match raw.strip_prefix('-') {
+            Some(name) => (name.to_string(), true),
+            None => (raw, false),
+        };
+        let field = F::deserialize(name.into_deserializer())?;
+        Ok(Self { field, descending })
+    }
+}
+
+// `cursor`, `limit` and `count` from the query string
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PageParams {
+    // `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    // Also return how many rows match in total (costs an extra query)
+    pub count: Option<bool>,
+}
+
+// Position after the last row of a page. Keeps the sort it was made for so it
+// can't be replayed against a different ordering.
+#[derive(Debug, serde::Serialize, Deserialize)]
+struct Cursor {
+    sort: String,
+    desc: bool,
+    key: Option<String>,
+    id: Uuid,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        BASE64_URL_SAFE_NO_PAD.encode(json)
+    }
+
+    fn decode(raw: &str, column: Column, descending: bool) -> Result<Self, AppError> {
+        let invalid = || AppError::BadRequest("Invalid cursor".to_string());
+        let json = BASE64_URL_SAFE_NO_PAD.decode(raw).map_err(|_| invalid())?;
+        let cursor: Cursor = serde_json::from_slice(&json).map_err(|_| invalid())?;
+        if cursor.sort != column.name || cursor.desc != descending {
+            return Err(AppError::BadRequest(
+                "Cursor belongs to a different sort order".to_string(),
+            ));
+        }
+        Ok(cursor)
+    }
+}
+
+// Escapes `%`, `_` and `\` so a search term matches literally inside `ILIKE`
+fn like_pattern(term: &str) -> String {
+    let mut pattern = String::with_capacity(term.len() + 2);
+    pattern.push('%');
+    for c in term.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+type Condition = Box<dyn Fn(&mut QueryBuilder<'static, Postgres>) + Send + Sync>;
+
+// A list query being narrowed down by filters. Conditions are kept aside so
+// they back both the page and the optional total count.
+pub struct ListQuery {
+    select: &'static str,
+    from: &'static str,
+    id: &'static str,
+    sort: Column,
+    descending: bool,
+    conditions: Vec<Condition>,
+}
+
+impl ListQuery {
+    // `select` is the column list, `from` the tables (with joins) and `id` the
+    // unique column that breaks ties in the sort
+    pub fn new<F: SortField>(
+        select: &'static str,
+        from: &'static str,
+        id: &'static str,
+        sort: Sort<F>,
+    ) -> Self {
+        Self {
+            select,
+            from,
+            id,
+            sort: sort.field.column(),
+            descending: sort.descending,
+            conditions: Vec::new(),
+        }
+    }
+
+    // A fixed condition such as `" AND is_active = true"`
+    pub fn push(&mut self, sql: &'static str) -> &mut Self {
+        self.conditions.push(Box::new(move |q| {
+            q.push(sql);
+        }));
+        self
+    }
+
+    // A condition the endpoint always applies, e.g. `.bind(" AND course_id = ", id)`
+    pub fn bind<T>(&mut self, sql: &'static str, value: T) -> &mut Self
+    where
+        T: 'static + Encode<'static, Postgres> + Type<Postgres> + Clone + Send + Sync,
+    {
+        self.conditions.push(Box::new(move |q| {
+            q.push(sql).push_bind(value.clone());
+        }));
+        self
+    }
+
+    // `AND column = value` when the filter was given
+    pub fn eq<T>(&mut self, column: &'static str, value: Option<T>) -> &mut Self
+    where
+        T: 'static + Encode<'static, Postgres> + Type<Postgres> + Clone + Send + Sync,
+    {
+        if let Some(value) = value {
+            self.conditions.push(Box::new(move |q| {
+                q.push(" AND ")
+                    .push(column)
+                    .push(" = ")
+                    .push_bind(value.clone());
+            }));
+        }
+        self
+    }
+
+    // Case-insensitive substring match against any of `columns`; blank terms are ignored
+    pub fn search(&mut self, columns: &'static [&'static str], term: Option<&str>) -> &mut Self {
+        let Some(term) = term.map(str::trim).filter(|t| !t.is_empty()) else {
+            return self;
+        };
+        let pattern = like_pattern(term);
+
+        self.conditions.push(Box::new(move |q| {
+            q.push(" AND (");
+            for (i, column) in columns.iter().enumerate() {
+                if i > 0 {
+                    q.push(" OR ");
+                }
+                q.push(*column)
+                    .push(" ILIKE ")
+                    .push_bind(pattern.clone())
+                    .push(" ESCAPE '\\'");
+            }
+            q.push(")");
+        }));
+        self
+    }
+
+    fn push_conditions(&self, q: &mut QueryBuilder<'static, Postgres>) {
+        for condition in &self.conditions {
+            condition(q);
+        }
+    }
+
+    // Rows after `cursor` in the current order
+    fn push_keyset(&self, q: &mut QueryBuilder<'static, Postgres>, cursor: Cursor) {
+        let column = self.sort;
+        let op = if self.descending { " < " } else { " > " };
+        let cast = |q: &mut QueryBuilder<'static, Postgres>, key: String| {
+            q.push("CAST(")
+                .push_bind(key)
+                .push(" AS ")
+                .push(column.sql_type)
+                .push(")");
+        };
+
+        match (column.nullable, cursor.key) {
+            // Row comparison lets Postgres walk a (column, id) index
+            (false, Some(key)) => {
+                q.push(" AND (")
+                    .push(column.name)
+                    .push(", ")
+                    .push(self.id)
+                    .push(")")
+                    .push(op)
+                    .push("(");
+                cast(q, key);
+                q.push(", ").push_bind(cursor.id).push(")");
+            }
+            (true, Some(key)) => {
+                q.push(" AND (").push(column.name).push(op);
+                cast(q, key.clone());
+                q.push(" OR (").push(column.name).push(" = ");
+                cast(q, key);
+                q.push(" AND ")
+                    .push(self.id)
+                    .push(op)
+                    .push_bind(cursor.id)
+                    .push(") OR ")
+                    .push(column.name)
+                    .push(" IS NULL)");
+            }
+            (_, None) => {
+                q.push(" AND ")
+                    .push(column.name)
+                    .push(" IS NULL AND ")
+                    .push(self.id)
+                    .push(op)
+                    .push_bind(cursor.id);
+            }
+        }
+    }
+
+    // One page. `T` is read from the same row as the internal `sort_key` and
+    // `page_id` columns, which it can ignore.
+    pub async fn fetch<T>(self, pool: &PgPool, page: PageParams) -> Result<Paginated<T>, AppError>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let limit = page.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let cursor = page
+            .cursor
+            .as_deref()
+            .filter(|c| !c.is_empty())
+            .map(|c| Cursor::decode(c, self.sort, self.descending))
+            .transpose()?;
+
+        let total = if page.count.unwrap_or(false) {
+            let mut q = QueryBuilder::new(format!("SELECT COUNT(*) FROM {} WHERE TRUE", self.from));
+            self.push_conditions(&mut q);
+            Some(q.build_query_scalar::<i64>().fetch_one(pool).await?)
+        } else {
+            None
+        };
+
+        let mut q = QueryBuilder::new(format!(
+            "SELECT {}, ({})::text AS sort_key, {} AS page_id FROM {} WHERE TRUE",
+            self.select, self.sort.name, self.id, self.from
+        ));
+        self.push_conditions(&mut q);
+        if let Some(cursor) = cursor {
+            self.push_keyset(&mut q, cursor);
+        }
+        let direction = if self.descending { " DESC" } else { " ASC" };
+        q.push(" ORDER BY ").push(self.sort.name).push(direction);
+        if self.sort.nullable {
+            q.push(" NULLS LAST");
+        }
+        q.push(", ").push(self.id).push(direction);
+        q.push(" LIMIT ").push_bind(limit + 1);
+
+        let mut rows = q.build().fetch_all(pool).await?;
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+
+        let next_cursor = match rows.last() {
+            Some(last) if has_more => Some(
+                Cursor {
+                    sort: self.sort.name.to_string(),
+                    desc: self.descending,
+                    key: last.try_get("sort_key")?,
+                    id: last.try_get("page_id")?,
+                }
+                .encode(),
+            ),
+            _ => None,
+        };
+        let items = rows
+            .iter()
+            .map(T::from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Paginated {
+            items,
+            next_cursor,
+            has_more,
+            total,
+        })
+    }
+}
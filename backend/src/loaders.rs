@@ -0,0 +1,163 @@
+// Loads the rows a page of responses refers to in one repository call each
+// (a single `= ANY($1)` query in Postgres) rather than a query per row
+
+use crate::error::AppError;
+use crate::repos::{Repos, UserRepo};
+use crate::structs::*;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+// Users keyed by id
+pub struct UserMap(HashMap<Uuid, User>);
+
+impl UserMap {
+    // Ids are collected before the future starts, so a borrowing iterator isn't
+    // held across the query
+    pub fn load(
+        users: &dyn UserRepo,
+        ids: impl IntoIterator<Item = Uuid>,
+    ) -> impl Future<Output = Result<Self, AppError>> + Send + '_ {
+        let ids: Vec<Uuid> = ids.into_iter().collect::<HashSet<_>>().into_iter().collect();
+
+        async move {
+            if ids.is_empty() {
+                return Ok(Self(HashMap::new()));
+            }
+
+            let users = users.find_many(&ids).await?;
+            Ok(Self(users.into_iter().map(|u| (u.id, u)).collect()))
+        }
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&User> {
+        self.0.get(&id)
+    }
+
+    pub fn response(&self, id: Option<Uuid>) -> Option<UserResponse> {
+        id.and_then(|id| self.get(id))
+            .map(|u| UserResponse::from(u.clone()))
+    }
+
+    // For references that can't dangle, such as `opportunities.posted_by`
+    pub fn required(&self, id: Uuid) -> Result<UserResponse, AppError> {
+        self.response(Some(id)).ok_or_else(|| {
+            AppError::Internal(anyhow::anyhow!("User {} referenced but not found", id))
+        })
+    }
+}
+
+// `GrievanceResponse`s as seen by `viewer`, with submitters, assignees and the
+// viewer's upvotes loaded for the whole batch
+pub async fn grievance_responses(
+    repos: &Repos,
+    viewer: Uuid,
+    grievances: Vec<Grievance>,
+) -> Result<Vec<GrievanceResponse>, AppError> {
+    let users = UserMap::load(
+        repos.users.as_ref(),
+        grievances
+            .iter()
+            .flat_map(|g| [g.submitted_by, g.assigned_to])
+            .flatten(),
+    )
+    .await?;
+
+    let ids: Vec<Uuid> = grievances.iter().map(|g| g.id).collect();
+    let upvoted = repos.grievances.upvoted_by(viewer, &ids).await?;
+
+    Ok(grievances
+        .into_iter()
+        .map(|g| GrievanceResponse {
+            id: g.id,
+            submitter: users.response(g.submitted_by),
+            is_anonymous: g.is_anonymous,
+            title: g.title,
+            description: g.description,
+            category: g.category,
+            priority: g.priority,
+            status: g.status,
+            location_type: g.location_type,
+            location_details: g.location_details,
+            photo_urls: g.photo_urls.unwrap_or_default(),
+            assigned_to: users.response(g.assigned_to),
+            assigned_department: g.assigned_department,
+            resolution_notes: g.resolution_notes,
+            resolved_at: g.resolved_at,
+            view_count: g.view_count,
+            upvote_count: g.upvote_count,
+            user_has_upvoted: upvoted.contains(&g.id),
+            created_at: g.created_at,
+            updated_at: g.updated_at,
+        })
+        .collect())
+}
+
+// `OpportunityResponse`s with their posters, and `has_applied` for a signed-in `viewer`
+pub async fn opportunity_responses(
+    repos: &Repos,
+    viewer: Option<Uuid>,
+    opportunities: Vec<Opportunity>,
+) -> Result<Vec<OpportunityResponse>, AppError> {
+    let users = UserMap::load(
+        repos.users.as_ref(),
+        opportunities.iter().map(|op| op.posted_by),
+    )
+    .await?;
+
+    let applied: HashSet<Uuid> = match viewer {
+        Some(viewer) => {
+            let ids: Vec<Uuid> = opportunities.iter().map(|op| op.id).collect();
+            repos.opportunities.applied_to(viewer, &ids).await?
+        }
+        None => HashSet::new(),
+    };
+
+    opportunities
+        .into_iter()
+        .map(|op| {
+            Ok(OpportunityResponse {
+                id: op.id,
+                posted_by: users.required(op.posted_by)?,
+                title: op.title,
+                description: op.description,
+                opportunity_type: op.opportunity_type,
+                department: op.department,
+                required_skills: op.required_skills.unwrap_or_default(),
+                duration: op.duration,
+                stipend: op.stipend,
+                location: op.location,
+                application_deadline: op.application_deadline,
+                is_active: op.is_active,
+                created_at: op.created_at,
+                has_applied: applied.contains(&op.id),
+            })
+        })
+        .collect()
+}
+
+// `CourseResponse`s with their instructors
+pub async fn course_responses(
+    repos: &Repos,
+    courses: Vec<Course>,
+) -> Result<Vec<CourseResponse>, AppError> {
+    let users = UserMap::load(
+        repos.users.as_ref(),
+        courses.iter().filter_map(|c| c.instructor_id),
+    )
+    .await?;
+
+    Ok(courses
+        .into_iter()
+        .map(|course| CourseResponse {
+            id: course.id,
+            code: course.code,
+            title: course.title,
+            description: course.description,
+            credits: course.credits,
+            department: course.department,
+            course_type: course.course_type,
+            instructor: users.response(course.instructor_id),
+            semester: course.semester,
+        })
+        .collect())
+}
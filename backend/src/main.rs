@@ -1,179 +1,141 @@
 mod academic;
 mod admin;
+mod app;
+mod audit;
 mod auth;
+mod cli;
 mod cloudinary;
+mod config;
+#[cfg(feature = "dev-routes")]
+mod dev;
+#[cfg(test)]
+mod e2e_tests;
 mod error;
+mod extractors;
 mod grievances;
+mod health;
+mod identity;
+mod impersonation;
+mod listing;
+mod loaders;
+mod migrate;
+mod monitoring;
 mod opportunity;
-mod partitioned_cookies;
+mod permissions;
+mod rate_limit;
+mod repos;
+mod session_store;
+mod sessions;
+mod state;
 mod structs;
 mod telemetry;
+#[cfg(test)]
+mod test_support;
+mod tokens;
 
-use admin::{
-    get_all_users, get_audit_logs, get_system_stats, get_user_by_id, seed_dummy_users,
-    update_own_role, update_user_role, update_user_status,
-};
-use auth::{get_current_user, google_callback, google_login_initiate, logout};
-use axum::{
-    Json, Router, middleware,
-    routing::{delete, get, post, put},
-};
-use error::AppError;
-use grievances::{
-    add_comment, assign_grievance, create_grievance, delete_grievance, get_comments,
-    get_departments, get_grievance_by_id, get_grievance_history, get_grievances, resolve_grievance,
-    toggle_upvote, update_grievance_status, upload_grievance_photos,
-};
-use http::{HeaderName, Method};
-use partitioned_cookies::add_partitioned_attribute;
-use serde_json::json;
+use clap::Parser;
+use cli::{Cli, Command, MigrateAction};
+use config::AppConfig;
+use session_store::PgSessionStore;
 use sqlx::PgPool;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::net::TcpListener;
-use tower_http::cors::CorsLayer;
-use tower_sessions::{MemoryStore, SessionManagerLayer};
+
+// Expired sessions are swept hourly
+const SESSION_CLEANUP_INTERVAL_SECS: u64 = 60 * 60;
+// Drift between `upvote_count` and `grievance_upvotes` is corrected this often
+const UPVOTE_RECONCILE_INTERVAL_SECS: u64 = 15 * 60;
 
 #[tokio::main]
-async fn main() -> Result<(), AppError> {
-    telemetry::init_telemetry();
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let _telemetry = telemetry::init_telemetry(&AppConfig::load_telemetry()?)?;
 
-    let url = std::env::var("DATABASE_URL").unwrap();
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(AppConfig::load()?).await,
+        Command::Migrate { action } => {
+            let pool = connect(&AppConfig::load_database_url()?).await?;
+            match action {
+                MigrateAction::Up => migrate::up(&pool).await,
+                MigrateAction::Status => migrate::status(&pool).await,
+                MigrateAction::Down => migrate::down(&pool).await,
+            }
+        }
+        Command::VerifyAudit => {
+            let pool = connect(&AppConfig::load_database_url()?).await?;
+            audit::verify_command(&pool).await
+        }
+    }
+}
 
-    tracing::info!("Attempting to connect to database using URL: {:?}", url);
-    let pool = PgPool::connect(&url).await.map_err(|e| {
+async fn connect(database_url: &str) -> anyhow::Result<PgPool> {
+    tracing::info!("Attempting to connect to database");
+    let pool = PgPool::connect(database_url).await.map_err(|e| {
         tracing::error!("Failed to connect to database: {:?}", e);
         e
     })?;
     tracing::info!("Successfully connected to database.");
+    Ok(pool)
+}
 
-    let cors = CorsLayer::new()
-        .allow_origin([
-            "http://localhost:4173".parse().unwrap(),
-            std::env::var("FRONTEND_URL").unwrap().parse().unwrap(),
-        ])
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-        .allow_headers([
-            HeaderName::from_static("content-type"),
-            HeaderName::from_static("authorization"),
-            HeaderName::from_static("accept"),
-        ])
-        .allow_credentials(true);
+async fn serve(config: AppConfig) -> anyhow::Result<()> {
+    let config = Arc::new(config);
+    let pool = connect(&config.database_url).await?;
 
-    let session_store = MemoryStore::default();
-    let session_layer = SessionManagerLayer::new(session_store)
-        .with_name("aegis_session")
-        .with_secure(false)
-        .with_http_only(true)
-        .with_same_site(tower_sessions::cookie::SameSite::Lax)
-        .with_path("/");
+    if config.auto_migrate {
+        tracing::info!("Applying pending database migrations");
+        migrate::MIGRATOR.run(&pool).await?;
+    } else {
+        migrate::ensure_up_to_date(&pool).await?;
+    }
 
-    let app = Router::new()
-        .route(
-            "/",
-            get(|| async { Json(json!({"status": "ok", "message": "Backend is running"})) }),
-        )
-        // Auth routes
-        .route("/auth/google", get(google_login_initiate))
-        .route("/auth/google/callback", get(google_callback))
-        .route("/auth/logout", get(logout))
-        .route("/auth/me", get(get_current_user))
-        // Grievance routes
-        .route("/api/grievances", post(create_grievance))
-        .route("/api/grievances", get(get_grievances))
-        .route("/api/grievances/{id}", get(get_grievance_by_id))
-        .route("/api/grievances/{id}", delete(delete_grievance))
-        .route("/api/grievances/{id}/status", put(update_grievance_status))
-        .route("/api/grievances/{id}/assign", put(assign_grievance))
-        .route("/api/grievances/{id}/resolve", put(resolve_grievance))
-        .route("/api/grievances/{id}/upvote", post(toggle_upvote))
-        .route("/api/grievances/{id}/photos", post(upload_grievance_photos))
-        .route("/api/grievances/{id}/history", get(get_grievance_history))
-        .route("/api/grievances/{id}/comments", post(add_comment))
-        .route("/api/grievances/{id}/comments", get(get_comments))
-        .route("/api/departments", get(get_departments))
-        // Admin routes
-        .route("/api/admin/users", get(get_all_users))
-        .route("/api/admin/users/{id}", get(get_user_by_id))
-        .route("/api/admin/users/{id}/role", put(update_user_role))
-        .route("/api/admin/users/{id}/status", put(update_user_status))
-        .route("/api/admin/audit-logs", get(get_audit_logs))
-        .route("/api/admin/stats", get(get_system_stats))
-        // Dev/Testing route - allows users to change their own role
-        .route("/api/user/role", put(update_own_role))
-        .route("/api/dev/seed", post(seed_dummy_users))
-        // --- ACADEMIC ROUTES ---
-        // Course Management
-        .route(
-            "/api/courses",
-            post(academic::create_course).get(academic::get_courses),
-        )
-        .route("/api/courses/enroll", post(academic::enroll_course))
-        .route(
-            "/api/courses/my-enrollments",
-            get(academic::get_my_enrollments),
-        )
-        .route("/api/courses/{id}", get(academic::get_course_details))
-        // Attendance
-        .route("/api/attendance/mark", post(academic::mark_attendance))
-        .route("/api/attendance/{id}", get(academic::get_my_attendance))
-        // Resources (Vault)
-        .route(
-            "/api/courses/{id}/resources",
-            post(academic::create_resource).get(academic::get_course_resources),
-        )
-        // Calendar
-        .route(
-            "/api/events",
-            post(academic::create_event).get(academic::get_my_calendar),
-        )
-        // --- OPPORTUNITY ROUTES (PILLAR 4) ---
-        // 1. Opportunities (Faculty post, Students browse)
-        .route(
-            "/api/opportunities",
-            post(opportunity::create_opportunity).get(opportunity::get_opportunities),
-        )
-        // 2. Applications (Apply & View My History)
-        .route(
-            "/api/opportunities/{id}/apply",
-            post(opportunity::apply_opportunity),
-        )
-        .route(
-            "/api/applications/my-applications",
-            get(opportunity::get_my_applications),
-        )
-        // 3. Faculty Management (View Applicants & Update Status)
-        .route(
-            "/api/opportunities/{id}/applications",
-            get(opportunity::get_opportunity_applications),
-        )
-        .route(
-            "/api/applications/{id}/status",
-            put(opportunity::update_application_status),
-        )
-        // 4. The Scholar's Ledger (Personal Tasks)
-        .route(
-            "/api/tasks",
-            post(opportunity::create_task).get(opportunity::get_tasks),
-        )
-        .route(
-            "/api/tasks/{id}",
-            put(opportunity::update_task).delete(opportunity::delete_task),
-        )
-        .with_state(pool)
-        .layer(session_layer)
-        .layer(middleware::from_fn(add_partitioned_attribute))
-        .layer(cors);
+    let app::App { router, shutdown } = app::build_app(config.clone(), pool.clone())?;
 
-    let port = std::env::var("PORT")
-        .unwrap_or_else(|_| "8000".to_string())
-        .parse::<u16>()?;
+    tokio::spawn(
+        PgSessionStore::new(pool.clone())
+            .cleanup_expired(std::time::Duration::from_secs(SESSION_CLEANUP_INTERVAL_SECS)),
+    );
+    tokio::spawn(grievances::reconcile_upvotes_periodically(
+        Arc::new(repos::PgGrievanceRepo::new(pool)),
+        std::time::Duration::from_secs(UPVOTE_RECONCILE_INTERVAL_SECS),
+    ));
 
-    let address = SocketAddr::from(([0, 0, 0, 0], port));
+    let address = SocketAddr::from(([0, 0, 0, 0], config.port));
 
     let listener = TcpListener::bind(&address).await?;
     tracing::debug!("listening on {}", listener.local_addr()?);
 
-    axum::serve(listener, app).await?;
+    // On SIGTERM stop accepting connections and let in-flight requests finish,
+    // but don't wait on them longer than the configured timeout
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            health::shutdown_signal().await;
+            shutdown.cancel();
+        }
+    });
+
+    let server = axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown.clone().cancelled_owned());
+    let drain_deadline = async {
+        shutdown.cancelled().await;
+        tokio::time::sleep(config.shutdown_timeout).await;
+    };
+
+    tokio::select! {
+        result = server => result?,
+        _ = drain_deadline => {
+            tracing::warn!(
+                "In-flight requests still running after {:?}, shutting down anyway",
+                config.shutdown_timeout
+            );
+        }
+    }
 
+    tracing::info!("Server stopped");
     Ok(())
 }
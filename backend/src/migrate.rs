@@ -0,0 +1,144 @@
+use sqlx::PgPool;
+use sqlx::migrate::{AppliedMigration, Migrate, Migrator};
+use std::collections::HashMap;
+
+// The SQL files in `backend/migrations`, embedded at compile time.
+// New migrations should be reversible (`.up.sql` + `.down.sql`) so `migrate down` can undo them.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+#[derive(Debug, PartialEq)]
+enum MigrationState {
+    Applied,
+    Pending,
+    // Applied, but the embedded file no longer matches what was run
+    Modified,
+}
+
+struct MigrationStatus {
+    version: i64,
+    description: String,
+    reversible: bool,
+    state: MigrationState,
+}
+
+async fn applied_migrations(pool: &PgPool) -> anyhow::Result<Vec<AppliedMigration>> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+
+    if let Some(version) = conn.dirty_version().await? {
+        anyhow::bail!(
+            "Migration {} previously failed part-way; fix the database by hand before continuing",
+            version
+        );
+    }
+
+    Ok(conn.list_applied_migrations().await?)
+}
+
+async fn migration_status(pool: &PgPool) -> anyhow::Result<Vec<MigrationStatus>> {
+    let applied: HashMap<i64, AppliedMigration> = applied_migrations(pool)
+        .await?
+        .into_iter()
+        .map(|m| (m.version, m))
+        .collect();
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| {
+            let state = match applied.get(&m.version) {
+                None => MigrationState::Pending,
+                Some(a) if a.checksum == m.checksum => MigrationState::Applied,
+                Some(_) => MigrationState::Modified,
+            };
+            MigrationStatus {
+                version: m.version,
+                description: m.description.to_string(),
+                reversible: m.migration_type.is_reversible(),
+                state,
+            }
+        })
+        .collect())
+}
+
+// ============================================================================
+// STARTUP
+// ============================================================================
+
+// Refuse to serve against a schema that doesn't match the embedded migrations
+pub async fn ensure_up_to_date(pool: &PgPool) -> anyhow::Result<()> {
+    let status = migration_status(pool).await?;
+
+    let modified: Vec<_> = status
+        .iter()
+        .filter(|m| m.state == MigrationState::Modified)
+        .map(|m| m.version.to_string())
+        .collect();
+    if !modified.is_empty() {
+        anyhow::bail!("Applied migrations were modified after being run: {}", modified.join(", "));
+    }
+
+    let pending = status
+        .iter()
+        .filter(|m| m.state == MigrationState::Pending)
+        .count();
+    if pending > 0 {
+        anyhow::bail!(
+            "Database schema is behind by {} migration(s); run `backend migrate up` first",
+            pending
+        );
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// `backend migrate` SUBCOMMAND
+// ============================================================================
+
+pub async fn up(pool: &PgPool) -> anyhow::Result<()> {
+    MIGRATOR.run(pool).await?;
+    println!("Database schema is up to date");
+    Ok(())
+}
+
+pub async fn status(pool: &PgPool) -> anyhow::Result<()> {
+    for m in migration_status(pool).await? {
+        let state = match m.state {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Modified => "MODIFIED",
+        };
+        let reversible = if m.reversible { "" } else { " (irreversible)" };
+        println!("{:<16} {:<9} {}{}", m.version, state, m.description, reversible);
+    }
+    Ok(())
+}
+
+// Revert the most recently applied migration
+pub async fn down(pool: &PgPool) -> anyhow::Result<()> {
+    let mut applied: Vec<i64> = applied_migrations(pool)
+        .await?
+        .into_iter()
+        .map(|m| m.version)
+        .collect();
+    applied.sort_unstable();
+
+    let Some(latest) = applied.pop() else {
+        println!("No migrations have been applied");
+        return Ok(());
+    };
+
+    let reversible = MIGRATOR
+        .iter()
+        .any(|m| m.version == latest && m.migration_type.is_down_migration());
+    if !reversible {
+        anyhow::bail!("Migration {} has no down script and cannot be reverted", latest);
+    }
+
+    // `undo` reverts everything above the target, i.e. just the latest migration
+    let target = applied.last().copied().unwrap_or(0);
+    MIGRATOR.undo(pool, target).await?;
+    println!("Reverted migration {}", latest);
+    Ok(())
+}
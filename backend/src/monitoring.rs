@@ -0,0 +1,142 @@
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::tokens;
+use axum::{
+    body::Body,
+    extract::{MatchedPath, State},
+    http::{HeaderMap, Request, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+// Metric names, kept in one place so dashboards have a single reference
+pub const HTTP_REQUESTS: &str = "http_requests_total";
+pub const HTTP_REQUEST_DURATION: &str = "http_request_duration_seconds";
+pub const DB_POOL_CONNECTIONS: &str = "db_pool_connections";
+pub const DB_POOL_MAX_CONNECTIONS: &str = "db_pool_max_connections";
+pub const CLOUDINARY_UPLOAD_DURATION: &str = "cloudinary_upload_duration_seconds";
+pub const CLOUDINARY_UPLOAD_FAILURES: &str = "cloudinary_upload_failures_total";
+pub const GRIEVANCES_CREATED: &str = "grievances_created_total";
+pub const LOGINS: &str = "logins_total";
+pub const APPLICATIONS_SUBMITTED: &str = "applications_submitted_total";
+
+// Latency buckets in seconds, from fast DB reads up to slow photo uploads
+const DURATION_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+// Histograms are drained into their summaries on this interval
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+// A mutex rather than a OnceLock so concurrent first calls (parallel tests) don't both install
+static HANDLE: Mutex<Option<PrometheusHandle>> = Mutex::new(None);
+
+// Install the global Prometheus recorder. Safe to call more than once; later calls reuse it.
+pub fn install() -> anyhow::Result<PrometheusHandle> {
+    let mut installed = HANDLE.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(handle) = installed.as_ref() {
+        return Ok(handle.clone());
+    }
+
+    let handle = PrometheusBuilder::new()
+        .set_buckets(&DURATION_BUCKETS)?
+        .install_recorder()?;
+    describe();
+
+    let upkeep = handle.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            upkeep.run_upkeep();
+        }
+    });
+
+    *installed = Some(handle.clone());
+    Ok(handle)
+}
+
+fn describe() {
+    metrics::describe_counter!(HTTP_REQUESTS, "HTTP requests by method, route and status");
+    metrics::describe_histogram!(
+        HTTP_REQUEST_DURATION,
+        metrics::Unit::Seconds,
+        "HTTP request latency by method and route"
+    );
+    metrics::describe_gauge!(DB_POOL_CONNECTIONS, "Database pool connections by state");
+    metrics::describe_gauge!(DB_POOL_MAX_CONNECTIONS, "Configured database pool size");
+    metrics::describe_histogram!(
+        CLOUDINARY_UPLOAD_DURATION,
+        metrics::Unit::Seconds,
+        "Cloudinary image upload latency"
+    );
+    metrics::describe_counter!(CLOUDINARY_UPLOAD_FAILURES, "Failed Cloudinary uploads");
+    metrics::describe_counter!(GRIEVANCES_CREATED, "Grievances submitted");
+    metrics::describe_counter!(LOGINS, "Successful logins");
+    metrics::describe_counter!(APPLICATIONS_SUBMITTED, "Opportunity applications submitted");
+}
+
+// ============================================================================
+// HTTP MIDDLEWARE
+// ============================================================================
+
+// Record count and latency per route. Installed with `route_layer` so `MatchedPath` is
+// available and labels stay bounded to the route templates (e.g. `/api/grievances/{id}`).
+pub async fn track_http(request: Request<Body>, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    tracing::Span::current().record("route", path.as_str());
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    metrics::counter!(HTTP_REQUESTS, "method" => method.clone(), "path" => path.clone(), "status" => status)
+        .increment(1);
+    metrics::histogram!(HTTP_REQUEST_DURATION, "method" => method, "path" => path)
+        .record(started.elapsed().as_secs_f64());
+
+    response
+}
+
+// ============================================================================
+// SCRAPE ENDPOINT
+// ============================================================================
+
+// Route counts and domain counters aren't public, so scrapes need METRICS_TOKEN as a
+// bearer token. Without one configured the endpoint doesn't exist.
+pub async fn metrics_handler(
+    State(handle): State<PrometheusHandle>,
+    State(pool): State<PgPool>,
+    State(config): State<Arc<AppConfig>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    let expected = config.metrics_token.as_deref().ok_or(AppError::NotFound)?;
+    // Digests have a fixed length, so comparing them doesn't reveal how much of the token matched
+    let sent = tokens::bearer_token(&headers).map(|token| Sha256::digest(token.as_bytes()));
+    if sent != Some(Sha256::digest(expected.as_bytes())) {
+        return Err(AppError::Unauthorized);
+    }
+
+    // Pool gauges are sampled at scrape time rather than tracked on every acquire
+    let size = pool.size() as f64;
+    let idle = pool.num_idle() as f64;
+    metrics::gauge!(DB_POOL_CONNECTIONS, "state" => "idle").set(idle);
+    metrics::gauge!(DB_POOL_CONNECTIONS, "state" => "in_use").set(size - idle);
+    metrics::gauge!(DB_POOL_MAX_CONNECTIONS).set(pool.options().get_max_connections() as f64);
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        handle.render(),
+    ))
+}
@@ -0,0 +1,434 @@
+use crate::audit::{self, Target};
+use crate::error::AppError;
+use crate::extractors::{AuthUser, Json, Path};
+use crate::structs::*;
+use async_trait::async_trait;
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// Named actions that handlers check with `authorize`. Which roles may take them is
+// stored in `role_permissions` and can be changed by admins at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Permission {
+    #[serde(rename = "grievance.create")]
+    GrievanceCreate,
+    #[serde(rename = "grievance.view")]
+    GrievanceView,
+    #[serde(rename = "grievance.edit")]
+    GrievanceEdit,
+    #[serde(rename = "grievance.delete")]
+    GrievanceDelete,
+    #[serde(rename = "grievance.update_status")]
+    GrievanceUpdateStatus,
+    #[serde(rename = "grievance.assign")]
+    GrievanceAssign,
+    #[serde(rename = "grievance.resolve")]
+    GrievanceResolve,
+    #[serde(rename = "grievance.internal_comments")]
+    GrievanceInternalComments,
+    #[serde(rename = "grievance.handle")]
+    GrievanceHandle,
+    #[serde(rename = "course.create")]
+    CourseCreate,
+    #[serde(rename = "course.enroll")]
+    CourseEnroll,
+    #[serde(rename = "attendance.mark")]
+    AttendanceMark,
+    #[serde(rename = "resource.verify")]
+    ResourceVerify,
+    #[serde(rename = "event.create")]
+    EventCreate,
+    #[serde(rename = "opportunity.create")]
+    OpportunityCreate,
+    #[serde(rename = "opportunity.manage_applications")]
+    OpportunityManageApplications,
+    #[serde(rename = "user.view")]
+    UserView,
+    #[serde(rename = "user.manage")]
+    UserManage,
+    #[serde(rename = "user.impersonate")]
+    UserImpersonate,
+    #[serde(rename = "user.impersonate_write")]
+    UserImpersonateWrite,
+    #[serde(rename = "audit.view")]
+    AuditView,
+    #[serde(rename = "audit.export")]
+    AuditExport,
+    #[serde(rename = "stats.view")]
+    StatsView,
+    #[serde(rename = "role.manage")]
+    RoleManage,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 24] = [
+        Permission::GrievanceCreate,
+        Permission::GrievanceView,
+        Permission::GrievanceEdit,
+        Permission::GrievanceDelete,
+        Permission::GrievanceUpdateStatus,
+        Permission::GrievanceAssign,
+        Permission::GrievanceResolve,
+        Permission::GrievanceInternalComments,
+        Permission::GrievanceHandle,
+        Permission::CourseCreate,
+        Permission::CourseEnroll,
+        Permission::AttendanceMark,
+        Permission::ResourceVerify,
+        Permission::EventCreate,
+        Permission::OpportunityCreate,
+        Permission::OpportunityManageApplications,
+        Permission::UserView,
+        Permission::UserManage,
+        Permission::UserImpersonate,
+        Permission::UserImpersonateWrite,
+        Permission::AuditView,
+        Permission::AuditExport,
+        Permission::StatsView,
+        Permission::RoleManage,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::GrievanceCreate => "grievance.create",
+            Permission::GrievanceView => "grievance.view",
+            Permission::GrievanceEdit => "grievance.edit",
+            Permission::GrievanceDelete => "grievance.delete",
+            Permission::GrievanceUpdateStatus => "grievance.update_status",
+            Permission::GrievanceAssign => "grievance.assign",
+            Permission::GrievanceResolve => "grievance.resolve",
+            Permission::GrievanceInternalComments => "grievance.internal_comments",
+            Permission::GrievanceHandle => "grievance.handle",
+            Permission::CourseCreate => "course.create",
+            Permission::CourseEnroll => "course.enroll",
+            Permission::AttendanceMark => "attendance.mark",
+            Permission::ResourceVerify => "resource.verify",
+            Permission::EventCreate => "event.create",
+            Permission::OpportunityCreate => "opportunity.create",
+            Permission::OpportunityManageApplications => "opportunity.manage_applications",
+            Permission::UserView => "user.view",
+            Permission::UserManage => "user.manage",
+            Permission::UserImpersonate => "user.impersonate",
+            Permission::UserImpersonateWrite => "user.impersonate_write",
+            Permission::AuditView => "audit.view",
+            Permission::AuditExport => "audit.export",
+            Permission::StatsView => "stats.view",
+            Permission::RoleManage => "role.manage",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Permission::GrievanceCreate => "Submit grievances",
+            Permission::GrievanceView => "View grievance details, history and comments",
+            Permission::GrievanceEdit => "Attach photos to grievances",
+            Permission::GrievanceDelete => "Delete grievances",
+            Permission::GrievanceUpdateStatus => "Change a grievance's status",
+            Permission::GrievanceAssign => "Assign grievances to a person or department",
+            Permission::GrievanceResolve => "Resolve grievances",
+            Permission::GrievanceInternalComments => "Read and write internal grievance notes",
+            Permission::GrievanceHandle => "Be assigned grievances",
+            Permission::CourseCreate => "Create courses (own: as the instructor)",
+            Permission::CourseEnroll => "Enroll in courses",
+            Permission::AttendanceMark => "Mark attendance (own: only your own)",
+            Permission::ResourceVerify => "Publish academic resources without review",
+            Permission::EventCreate => "Create calendar events",
+            Permission::OpportunityCreate => "Post opportunities",
+            Permission::OpportunityManageApplications => "Review applications to opportunities",
+            Permission::UserView => "List and view user accounts",
+            Permission::UserManage => "Change user roles and status, sign users out",
+            Permission::UserImpersonate => "View the app as another user (read-only)",
+            Permission::UserImpersonateWrite => "Make changes while viewing as another user",
+            Permission::AuditView => "Read the audit log",
+            Permission::AuditExport => "Download the audit log as CSV or NDJSON",
+            Permission::StatsView => "View system statistics",
+            Permission::RoleManage => "Edit role permissions",
+        }
+    }
+
+    // Acts only on the user's own records, so borrowing it by impersonation
+    // reaches nothing beyond the impersonated account
+    pub fn self_service(self) -> bool {
+        matches!(self, Permission::GrievanceCreate | Permission::CourseEnroll)
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Permission::ALL.into_iter().find(|p| p.as_str() == name)
+    }
+}
+
+// What a permission is being checked against, to match scoped grants
+pub enum Resource<'a> {
+    // Not tied to any user; only an `any` grant covers it
+    Global,
+    Grievance(&'a Grievance),
+    // A record belonging to one user, e.g. an opportunity's poster or an attendance entry's student
+    OwnedBy(Uuid),
+}
+
+impl Resource<'_> {
+    fn covered_by(&self, scope: PermissionScope, user_id: Uuid) -> bool {
+        match (scope, self) {
+            (PermissionScope::Any, _) => true,
+            (PermissionScope::Own, Resource::Grievance(g)) => g.submitted_by == Some(user_id),
+            (PermissionScope::Own, Resource::OwnedBy(owner)) => *owner == user_id,
+            (PermissionScope::Assigned, Resource::Grievance(g)) => g.assigned_to == Some(user_id),
+            (PermissionScope::Unassigned, Resource::Grievance(g)) => g.assigned_to.is_none(),
+            _ => false,
+        }
+    }
+}
+
+// Where a role's grants are looked up. Handlers built on `Repos` go through
+// `UserRepo` so unit tests can hand out grants without a database.
+#[async_trait]
+pub trait Grants: Send + Sync {
+    async fn scopes(
+        &self,
+        role: &UserRole,
+        permission: Permission,
+    ) -> Result<Vec<PermissionScope>, AppError>;
+}
+
+#[async_trait]
+impl Grants for PgPool {
+    async fn scopes(
+        &self,
+        role: &UserRole,
+        permission: Permission,
+    ) -> Result<Vec<PermissionScope>, AppError> {
+        let scopes = sqlx::query_scalar::<_, PermissionScope>(
+            "SELECT scope FROM role_permissions WHERE role = $1 AND permission = $2",
+        )
+        .bind(role)
+        .bind(permission.as_str())
+        .fetch_all(self)
+        .await?;
+        Ok(scopes)
+    }
+}
+
+// Whether the user's role has a grant of `permission` covering `resource`
+pub async fn allowed(
+    grants: &impl Grants,
+    user: &User,
+    permission: Permission,
+    resource: Resource<'_>,
+) -> Result<bool, AppError> {
+    let scopes = grants.scopes(&user.role, permission).await?;
+
+    Ok(scopes
+        .into_iter()
+        .any(|scope| resource.covered_by(scope, user.id)))
+}
+
+// The authorization check for handlers: 403 unless `allowed`
+pub async fn authorize(
+    grants: &impl Grants,
+    user: &User,
+    permission: Permission,
+    resource: Resource<'_>,
+) -> Result<(), AppError> {
+    if allowed(grants, user, permission, resource).await? {
+        Ok(())
+    } else {
+        tracing::warn!(
+            "User {} ({:?}) denied {}",
+            user.id,
+            user.role,
+            permission.as_str()
+        );
+        Err(AppError::Forbidden)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct GrantRow {
+    role: UserRole,
+    permission: String,
+    scope: PermissionScope,
+}
+
+async fn grants_for(pool: &PgPool, role: Option<&UserRole>) -> Result<Vec<GrantRow>, AppError> {
+    let rows = sqlx::query_as::<_, GrantRow>(
+        r#"
+        SELECT role, permission, scope FROM role_permissions
+        WHERE $1::user_role IS NULL OR role = $1
+        ORDER BY role, permission, scope
+        "#,
+    )
+    .bind(role)
+    .fetch_all(pool)
+    .await?;
+
+    // Rows for permissions this build doesn't know about grant nothing
+    Ok(rows
+        .into_iter()
+        .filter(|row| Permission::parse(&row.permission).is_some())
+        .collect())
+}
+
+fn to_grant(row: &GrantRow) -> Option<PermissionGrant> {
+    Some(PermissionGrant {
+        permission: Permission::parse(&row.permission)?,
+        scope: row.scope,
+    })
+}
+
+// Everything a role is granted, e.g. to compare two roles
+pub async fn role_grants(pool: &PgPool, role: &UserRole) -> Result<Vec<PermissionGrant>, AppError> {
+    Ok(grants_for(pool, Some(role)).await?.iter().filter_map(to_grant).collect())
+}
+
+// ============================================================================
+// EFFECTIVE PERMISSIONS
+// ============================================================================
+
+// GET /api/me/permissions
+// Lets the frontend hide actions the user can't take. Scoped grants still
+// depend on the record, e.g. `grievance.delete` with scope `own`.
+pub async fn get_my_permissions(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+) -> Result<Json<ApiResponse<Vec<EffectivePermission>>>, AppError> {
+    let grants = grants_for(&pool, Some(&user.role)).await?;
+
+    let permissions = Permission::ALL
+        .into_iter()
+        .filter_map(|permission| {
+            let scopes: Vec<PermissionScope> = grants
+                .iter()
+                .filter(|row| row.permission == permission.as_str())
+                .map(|row| row.scope)
+                .collect();
+            (!scopes.is_empty()).then_some(EffectivePermission { permission, scopes })
+        })
+        .collect();
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(permissions),
+        message: None,
+        error: None,
+    }))
+}
+
+// ============================================================================
+// ROLE GRANTS (role.manage)
+// ============================================================================
+
+// GET /api/admin/permissions
+pub async fn get_role_permissions(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+) -> Result<Json<ApiResponse<PermissionsOverviewResponse>>, AppError> {
+    authorize(&pool, &user, Permission::RoleManage, Resource::Global).await?;
+
+    let grants = grants_for(&pool, None).await?;
+
+    let roles = [
+        UserRole::Student,
+        UserRole::Faculty,
+        UserRole::Authority,
+        UserRole::Admin,
+    ]
+    .into_iter()
+    .map(|role| RolePermissionsResponse {
+        grants: grants
+            .iter()
+            .filter(|row| row.role == role)
+            .filter_map(to_grant)
+            .collect(),
+        role,
+    })
+    .collect();
+
+    let permissions = Permission::ALL
+        .into_iter()
+        .map(|permission| PermissionInfo {
+            permission,
+            description: permission.description(),
+        })
+        .collect();
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(PermissionsOverviewResponse { permissions, roles }),
+        message: None,
+        error: None,
+    }))
+}
+
+// PUT /api/admin/permissions/{role}
+// Replaces every grant of the role. Checks read the table on each request,
+// so changes apply immediately.
+pub async fn update_role_permissions(
+    State(pool): State<PgPool>,
+    AuthUser(admin_user): AuthUser,
+    Path(role): Path<UserRole>,
+    Json(payload): Json<UpdateRolePermissionsRequest>,
+) -> Result<Json<ApiResponse<RolePermissionsResponse>>, AppError> {
+    authorize(&pool, &admin_user, Permission::RoleManage, Resource::Global).await?;
+
+    let mut grants = payload.grants;
+    grants.sort_by_key(|g| (g.permission, g.scope));
+    grants.dedup_by_key(|g| (g.permission, g.scope));
+
+    // Keep at least the editor's own role able to undo a mistake
+    if role == admin_user.role
+        && !grants
+            .iter()
+            .any(|g| g.permission == Permission::RoleManage && g.scope == PermissionScope::Any)
+    {
+        return Err(AppError::BadRequest(
+            "Your own role must keep role.manage".to_string(),
+        ));
+    }
+
+    let old_grants: Vec<PermissionGrant> = grants_for(&pool, Some(&role))
+        .await?
+        .iter()
+        .filter_map(to_grant)
+        .collect();
+
+    let permissions: Vec<&str> = grants.iter().map(|g| g.permission.as_str()).collect();
+    let scopes: Vec<PermissionScope> = grants.iter().map(|g| g.scope).collect();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM role_permissions WHERE role = $1")
+        .bind(&role)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO role_permissions (role, permission, scope)
+        SELECT $1, permission, scope FROM UNNEST($2::text[], $3::permission_scope[]) AS g(permission, scope)
+        "#,
+    )
+    .bind(&role)
+    .bind(&permissions)
+    .bind(&scopes)
+    .execute(&mut *tx)
+    .await?;
+    audit::record(
+        &mut *tx,
+        &admin_user,
+        "UPDATE_ROLE_PERMISSIONS",
+        Some(Target::Role(role.clone())),
+        serde_json::json!({
+            "old_grants": old_grants,
+            "new_grants": grants,
+        }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(RolePermissionsResponse { role, grants }),
+        message: Some("Role permissions updated".to_string()),
+        error: None,
+    }))
+}
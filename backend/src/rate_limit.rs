@@ -0,0 +1,267 @@
+use crate::audit::{self, Actor};
+use crate::auth::SESSION_USER_ID_KEY;
+use crate::config::{RateLimitConfig, RateLimitRule};
+use crate::error::AppError;
+use crate::extractors::client_ip;
+use crate::tokens;
+use axum::{
+    body::Body,
+    extract::State,
+    http::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tower_sessions::Session;
+use uuid::Uuid;
+
+// A client hitting the limit is written to audit_logs at most once per group in this window
+const AUDIT_COOLDOWN: Duration = Duration::from_secs(60);
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy)]
+pub enum RouteGroup {
+    Default,
+    GrievanceCreate,
+    Upvotes,
+    Uploads,
+}
+
+impl RouteGroup {
+    fn name(self) -> &'static str {
+        match self {
+            RouteGroup::Default => "default",
+            RouteGroup::GrievanceCreate => "grievance_create",
+            RouteGroup::Upvotes => "upvotes",
+            RouteGroup::Uploads => "uploads",
+        }
+    }
+}
+
+// Signed-in users and their personal access tokens are limited per account, everyone else per client IP
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ClientKey {
+    User(Uuid),
+    Ip(IpAddr),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    last_audited: Option<Instant>,
+}
+
+enum Decision {
+    Allow,
+    Deny { retry_after: Duration, audit: bool },
+}
+
+// ============================================================================
+// TOKEN BUCKETS
+// ============================================================================
+
+struct Buckets {
+    rule: RateLimitRule,
+    clients: Mutex<HashMap<ClientKey, Bucket>>,
+}
+
+impl Buckets {
+    fn new(rule: RateLimitRule) -> Self {
+        Self {
+            rule,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.rule.per_minute as f64 / 60.0
+    }
+
+    fn check(&self, key: ClientKey) -> Decision {
+        let now = Instant::now();
+        let burst = self.rule.burst as f64;
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+
+        let bucket = clients.entry(key).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+            last_audited: None,
+        });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec()).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Decision::Allow;
+        }
+
+        let retry_after = Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec());
+        let audit = bucket
+            .last_audited
+            .is_none_or(|at| now.duration_since(at) >= AUDIT_COOLDOWN);
+        if audit {
+            bucket.last_audited = Some(now);
+        }
+
+        Decision::Deny { retry_after, audit }
+    }
+
+    // Drop buckets that have refilled completely; they behave the same as a fresh one
+    fn remove_idle(&self) {
+        let full_after = Duration::from_secs_f64(self.rule.burst as f64 / self.refill_per_sec());
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        clients.retain(|_, bucket| {
+            now.duration_since(bucket.updated) < full_after.max(AUDIT_COOLDOWN)
+        });
+    }
+}
+
+// ============================================================================
+// MIDDLEWARE
+// ============================================================================
+
+// State for one route group's limiter, passed to `enforce` with `middleware::from_fn_with_state`
+#[derive(Clone)]
+pub struct RateLimiter {
+    group: RouteGroup,
+    buckets: Arc<Buckets>,
+    pool: PgPool,
+    enabled: bool,
+    trust_forwarded_for: bool,
+}
+
+// One limiter per route group, built once at startup
+pub struct RateLimiters {
+    default: RateLimiter,
+    grievance_create: RateLimiter,
+    upvotes: RateLimiter,
+    uploads: RateLimiter,
+}
+
+impl RateLimiters {
+    pub fn new(config: &RateLimitConfig, pool: PgPool) -> Self {
+        let limiter = |group: RouteGroup, rule: RateLimitRule| RateLimiter {
+            group,
+            buckets: Arc::new(Buckets::new(rule)),
+            pool: pool.clone(),
+            enabled: config.enabled,
+            trust_forwarded_for: config.trust_forwarded_for,
+        };
+
+        let limiters = Self {
+            default: limiter(RouteGroup::Default, config.default),
+            grievance_create: limiter(RouteGroup::GrievanceCreate, config.grievance_create),
+            upvotes: limiter(RouteGroup::Upvotes, config.upvotes),
+            uploads: limiter(RouteGroup::Uploads, config.uploads),
+        };
+
+        let buckets = [
+            limiters.default.buckets.clone(),
+            limiters.grievance_create.buckets.clone(),
+            limiters.upvotes.buckets.clone(),
+            limiters.uploads.buckets.clone(),
+        ];
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                buckets.iter().for_each(|b| b.remove_idle());
+            }
+        });
+
+        limiters
+    }
+
+    pub fn group(&self, group: RouteGroup) -> RateLimiter {
+        match group {
+            RouteGroup::Default => self.default.clone(),
+            RouteGroup::GrievanceCreate => self.grievance_create.clone(),
+            RouteGroup::Upvotes => self.upvotes.clone(),
+            RouteGroup::Uploads => self.uploads.clone(),
+        }
+    }
+}
+
+// Token requests have no session, so the token is resolved to its owner. An unknown
+// token counts against the IP like any other anonymous request.
+async fn client_key(
+    pool: &PgPool,
+    bearer: Option<String>,
+    session: Option<Session>,
+    ip: IpAddr,
+) -> ClientKey {
+    if let Some(token) = bearer {
+        match tokens::owner(pool, &token).await {
+            Ok(Some(user_id)) => return ClientKey::User(user_id),
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to look up token owner: {:?}", e),
+        }
+    } else if let Some(session) = session {
+        if let Ok(Some(user_id)) = session.get::<Uuid>(SESSION_USER_ID_KEY).await {
+            return ClientKey::User(user_id);
+        }
+    }
+    ClientKey::Ip(ip)
+}
+
+pub async fn enforce(
+    State(limiter): State<RateLimiter>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if !limiter.enabled {
+        return next.run(request).await;
+    }
+
+    let bearer = tokens::bearer_token(request.headers()).map(str::to_string);
+    let session = request.extensions().get::<Session>().cloned();
+    let ip = client_ip(
+        request.headers(),
+        request.extensions(),
+        limiter.trust_forwarded_for,
+    );
+    let key = client_key(&limiter.pool, bearer, session, ip).await;
+    let (retry_after, audit) = match limiter.buckets.check(key.clone()) {
+        Decision::Allow => return next.run(request).await,
+        Decision::Deny { retry_after, audit } => (retry_after, audit),
+    };
+
+    tracing::warn!(
+        "Rate limit hit: group={} client={:?} path={}",
+        limiter.group.name(),
+        key,
+        request.uri().path()
+    );
+
+    if audit {
+        let (actor, ip) = match &key {
+            ClientKey::User(id) => (Actor::User(*id), None),
+            ClientKey::Ip(ip) => (Actor::System, Some(ip.to_string())),
+        };
+        let metadata = serde_json::json!({
+            "group": limiter.group.name(),
+            "method": request.method().as_str(),
+            "path": request.uri().path(),
+            "ip": ip,
+        });
+        // Don't hold up the 429 on the audit write
+        tokio::spawn(async move {
+            let result = audit::record(&limiter.pool, actor, "rate_limited", None, metadata).await;
+            if let Err(e) = result {
+                tracing::error!("Failed to record rate limit hit: {:?}", e);
+            }
+        });
+    }
+
+    AppError::TooManyRequests {
+        retry_after_secs: retry_after.as_secs_f64().ceil().max(1.0) as u64,
+    }
+    .into_response()
+}
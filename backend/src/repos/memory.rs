@@ -0,0 +1,934 @@
+// In-memory repositories for unit tests, all backed by one `Memory` store.
+// Lists filter like Postgres but return one page in insertion order (no
+// sorting, cursors or `count`), and nothing is audited.
+
+use super::*;
+use crate::extractors::Json;
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use std::sync::Mutex;
+
+// A request body as a handler would receive it
+pub fn body<T: DeserializeOwned>(value: serde_json::Value) -> Json<T> {
+    Json(serde_json::from_value(value).unwrap())
+}
+
+#[derive(Default)]
+pub struct Memory {
+    data: Mutex<Data>,
+}
+
+#[derive(Default)]
+struct Data {
+    users: Vec<User>,
+    grants: Vec<(UserRole, Permission, PermissionScope)>,
+    grievances: Vec<Grievance>,
+    history: Vec<GrievanceStatusHistory>,
+    comments: Vec<GrievanceComment>,
+    // (grievance_id, user_id)
+    upvotes: HashSet<(Uuid, Uuid)>,
+    departments: Vec<Department>,
+    courses: Vec<Course>,
+    enrollments: Vec<CourseEnrollment>,
+    attendance: Vec<AttendanceLog>,
+    resources: Vec<AcademicResource>,
+    events: Vec<AcademicEvent>,
+    opportunities: Vec<Opportunity>,
+    applications: Vec<Application>,
+    tasks: Vec<PersonalTask>,
+}
+
+impl Memory {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    // A store where each role has just these grants, and repos over it
+    pub fn with_grants(grants: &[(UserRole, Permission, PermissionScope)]) -> (Arc<Self>, Repos) {
+        let memory = Self::new();
+        for (role, permission, scope) in grants {
+            memory.grant(role.clone(), *permission, *scope);
+        }
+        let repos = memory.repos();
+        (memory, repos)
+    }
+
+    pub fn repos(self: &Arc<Self>) -> Repos {
+        Repos {
+            users: self.clone(),
+            grievances: self.clone(),
+            courses: self.clone(),
+            opportunities: self.clone(),
+            tasks: self.clone(),
+        }
+    }
+
+    pub fn add_user(&self, role: UserRole) -> User {
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+        let user = User {
+            id,
+            email: format!("{}@iitmandi.ac.in", id.simple()),
+            google_id: id.to_string(),
+            role,
+            status: UserStatus::Active,
+            first_name: "Test".to_string(),
+            last_name: "User".to_string(),
+            profile_picture: None,
+            roll_number: None,
+            batch_year: None,
+            program: None,
+            department: None,
+            employee_id: None,
+            designation: None,
+            last_login_at: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.lock().users.push(user.clone());
+        user
+    }
+
+    pub fn grant(&self, role: UserRole, permission: Permission, scope: PermissionScope) {
+        self.lock().grants.push((role, permission, scope));
+    }
+
+    pub fn add_course(&self, code: &str, instructor_id: Option<Uuid>) -> Course {
+        let now = Utc::now();
+        let course = Course {
+            id: Uuid::new_v4(),
+            code: code.to_string(),
+            title: format!("Course {}", code),
+            description: None,
+            credits: 4,
+            department: "CSE".to_string(),
+            course_type: CourseType::Core,
+            instructor_id,
+            semester: "2026-odd".to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+        self.lock().courses.push(course.clone());
+        course
+    }
+
+    pub fn upvote_count(&self, grievance_id: Uuid) -> i32 {
+        let data = self.lock();
+        data.grievances
+            .iter()
+            .find(|g| g.id == grievance_id)
+            .map_or(0, |g| g.upvote_count)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Data> {
+        self.data.lock().unwrap()
+    }
+}
+
+fn one_page<T>(items: Vec<T>) -> Paginated<T> {
+    Paginated {
+        items,
+        next_cursor: None,
+        has_more: false,
+        total: None,
+    }
+}
+
+fn eq<T: PartialEq>(filter: &Option<T>, value: &T) -> bool {
+    filter.as_ref().is_none_or(|f| f == value)
+}
+
+// Case-insensitive substring match on any of `fields`, like `ListQuery::search`
+fn matches(search: Option<&str>, fields: &[&str]) -> bool {
+    let Some(search) = search.map(str::trim).filter(|s| !s.is_empty()) else {
+        return true;
+    };
+    let search = search.to_lowercase();
+    fields.iter().any(|f| f.to_lowercase().contains(&search))
+}
+
+fn not_found(what: &str, id: Uuid) -> AppError {
+    AppError::Internal(anyhow::anyhow!(
+        "{} {} not in the in-memory store",
+        what,
+        id
+    ))
+}
+
+#[async_trait]
+impl UserRepo for Memory {
+    async fn find(&self, id: Uuid) -> Result<Option<User>, AppError> {
+        Ok(self.lock().users.iter().find(|u| u.id == id).cloned())
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
+        Ok(self.lock().users.iter().find(|u| u.email == email).cloned())
+    }
+
+    async fn find_many(&self, ids: &[Uuid]) -> Result<Vec<User>, AppError> {
+        Ok(self
+            .lock()
+            .users
+            .iter()
+            .filter(|u| ids.contains(&u.id))
+            .cloned()
+            .collect())
+    }
+
+    async fn permission_scopes(
+        &self,
+        role: &UserRole,
+        permission: Permission,
+    ) -> Result<Vec<PermissionScope>, AppError> {
+        Ok(self
+            .lock()
+            .grants
+            .iter()
+            .filter(|(r, p, _)| r == role && *p == permission)
+            .map(|(_, _, scope)| *scope)
+            .collect())
+    }
+}
+
+#[async_trait]
+impl GrievanceRepo for Memory {
+    async fn create(&self, _actor: &User, new: NewGrievance) -> Result<Grievance, AppError> {
+        let request = new.request;
+        let now = Utc::now();
+        let grievance = Grievance {
+            id: Uuid::new_v4(),
+            submitted_by: new.submitted_by,
+            is_anonymous: request.is_anonymous,
+            anonymous_identifier: new.anonymous_identifier,
+            title: request.title,
+            description: request.description,
+            category: request.category,
+            priority: request.priority,
+            status: GrievanceStatus::Submitted,
+            location_type: request.location_type,
+            location_details: request.location_details,
+            photo_urls: None,
+            assigned_to: None,
+            assigned_department: None,
+            resolution_notes: None,
+            resolved_at: None,
+            resolved_by: None,
+            view_count: 0,
+            upvote_count: 0,
+            created_at: now,
+            updated_at: now,
+        };
+        self.lock().grievances.push(grievance.clone());
+        Ok(grievance)
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Grievance>, AppError> {
+        Ok(self.lock().grievances.iter().find(|g| g.id == id).cloned())
+    }
+
+    async fn list(
+        &self,
+        filters: GrievanceFilters,
+        hide_anonymous: bool,
+    ) -> Result<Paginated<Grievance>, AppError> {
+        let data = self.lock();
+        let items = data
+            .grievances
+            .iter()
+            .filter(|g| {
+                eq(&filters.status, &g.status)
+                    && eq(&filters.category, &g.category)
+                    && eq(&filters.priority, &g.priority)
+                    && filters
+                        .assigned_to
+                        .is_none_or(|id| g.assigned_to == Some(id))
+                    && filters
+                        .assigned_department
+                        .as_ref()
+                        .is_none_or(|d| g.assigned_department.as_ref() == Some(d))
+                    && filters
+                        .submitted_by
+                        .is_none_or(|id| g.submitted_by == Some(id))
+                    && !(hide_anonymous && g.is_anonymous)
+                    && matches(filters.search.as_deref(), &[&g.title, &g.description])
+            })
+            .cloned()
+            .collect();
+        Ok(one_page(items))
+    }
+
+    async fn record_view(&self, id: Uuid) -> Result<(), AppError> {
+        if let Some(g) = self.lock().grievances.iter_mut().find(|g| g.id == id) {
+            g.view_count += 1;
+        }
+        Ok(())
+    }
+
+    async fn update_status(
+        &self,
+        actor: &User,
+        grievance: &Grievance,
+        request: UpdateGrievanceStatusRequest,
+    ) -> Result<Grievance, AppError> {
+        let mut data = self.lock();
+        data.history.push(GrievanceStatusHistory {
+            id: Uuid::new_v4(),
+            grievance_id: grievance.id,
+            old_status: Some(grievance.status.clone()),
+            new_status: request.status.clone(),
+            remarks: request.remarks,
+            updated_by: Some(actor.id),
+            updated_by_role: Some(actor.role.clone()),
+            created_at: Utc::now(),
+        });
+        let g = data
+            .grievances
+            .iter_mut()
+            .find(|g| g.id == grievance.id)
+            .ok_or_else(|| not_found("Grievance", grievance.id))?;
+        g.status = request.status;
+        g.updated_at = Utc::now();
+        Ok(g.clone())
+    }
+
+    async fn assign(
+        &self,
+        _actor: &User,
+        id: Uuid,
+        request: AssignGrievanceRequest,
+    ) -> Result<Grievance, AppError> {
+        let mut data = self.lock();
+        let g = data
+            .grievances
+            .iter_mut()
+            .find(|g| g.id == id)
+            .ok_or_else(|| not_found("Grievance", id))?;
+        g.assigned_to = request.assigned_to;
+        g.assigned_department = request.assigned_department;
+        g.updated_at = Utc::now();
+        Ok(g.clone())
+    }
+
+    async fn resolve(
+        &self,
+        actor: &User,
+        grievance: &Grievance,
+        resolution_notes: String,
+    ) -> Result<Grievance, AppError> {
+        let mut data = self.lock();
+        let g = data
+            .grievances
+            .iter_mut()
+            .find(|g| g.id == grievance.id)
+            .ok_or_else(|| not_found("Grievance", grievance.id))?;
+        g.status = GrievanceStatus::Resolved;
+        g.resolution_notes = Some(resolution_notes);
+        g.resolved_at = Some(Utc::now());
+        g.resolved_by = Some(actor.id);
+        Ok(g.clone())
+    }
+
+    async fn delete(&self, _actor: &User, grievance: &Grievance) -> Result<(), AppError> {
+        let mut data = self.lock();
+        data.grievances.retain(|g| g.id != grievance.id);
+        data.history.retain(|h| h.grievance_id != grievance.id);
+        data.comments.retain(|c| c.grievance_id != grievance.id);
+        data.upvotes.retain(|(g, _)| *g != grievance.id);
+        Ok(())
+    }
+
+    async fn add_photos(&self, _actor: &User, id: Uuid, urls: &[String]) -> Result<(), AppError> {
+        if let Some(g) = self.lock().grievances.iter_mut().find(|g| g.id == id) {
+            g.photo_urls
+                .get_or_insert_with(Vec::new)
+                .extend_from_slice(urls);
+        }
+        Ok(())
+    }
+
+    async fn toggle_upvote(&self, actor: &User, id: Uuid) -> Result<bool, AppError> {
+        let mut data = self.lock();
+        let upvoted = if data.upvotes.remove(&(id, actor.id)) {
+            false
+        } else {
+            data.upvotes.insert((id, actor.id))
+        };
+        if let Some(g) = data.grievances.iter_mut().find(|g| g.id == id) {
+            g.upvote_count += if upvoted { 1 } else { -1 };
+        }
+        Ok(upvoted)
+    }
+
+    async fn upvoted_by(&self, user_id: Uuid, ids: &[Uuid]) -> Result<HashSet<Uuid>, AppError> {
+        Ok(self
+            .lock()
+            .upvotes
+            .iter()
+            .filter(|(g, u)| *u == user_id && ids.contains(g))
+            .map(|(g, _)| *g)
+            .collect())
+    }
+
+    async fn reconcile_upvote_counts(&self) -> Result<usize, AppError> {
+        let mut data = self.lock();
+        let Data {
+            grievances,
+            upvotes,
+            ..
+        } = &mut *data;
+        let mut corrected = 0;
+        for g in grievances.iter_mut() {
+            let actual = upvotes.iter().filter(|(id, _)| *id == g.id).count() as i32;
+            if g.upvote_count != actual {
+                g.upvote_count = actual;
+                corrected += 1;
+            }
+        }
+        Ok(corrected)
+    }
+
+    async fn history(&self, id: Uuid) -> Result<Vec<GrievanceStatusHistory>, AppError> {
+        let data = self.lock();
+        Ok(data
+            .history
+            .iter()
+            .rev()
+            .filter(|h| h.grievance_id == id)
+            .cloned()
+            .collect())
+    }
+
+    async fn add_comment(
+        &self,
+        actor: &User,
+        id: Uuid,
+        comment: String,
+        is_internal: bool,
+    ) -> Result<GrievanceComment, AppError> {
+        let now = Utc::now();
+        let comment = GrievanceComment {
+            id: Uuid::new_v4(),
+            grievance_id: id,
+            user_id: actor.id,
+            comment,
+            is_internal,
+            created_at: now,
+            updated_at: now,
+        };
+        self.lock().comments.push(comment.clone());
+        Ok(comment)
+    }
+
+    async fn comments(
+        &self,
+        id: Uuid,
+        include_internal: bool,
+        _page: PageParams,
+    ) -> Result<Paginated<GrievanceComment>, AppError> {
+        let data = self.lock();
+        let items = data
+            .comments
+            .iter()
+            .filter(|c| c.grievance_id == id && (include_internal || !c.is_internal))
+            .cloned()
+            .collect();
+        Ok(one_page(items))
+    }
+
+    async fn departments(&self) -> Result<Vec<Department>, AppError> {
+        Ok(self.lock().departments.clone())
+    }
+}
+
+#[async_trait]
+impl CourseRepo for Memory {
+    async fn create(
+        &self,
+        _actor: &User,
+        request: CreateCourseRequest,
+        instructor_id: Option<Uuid>,
+    ) -> Result<Course, AppError> {
+        let now = Utc::now();
+        let course = Course {
+            id: Uuid::new_v4(),
+            code: request.code,
+            title: request.title,
+            description: request.description,
+            credits: request.credits,
+            department: request.department,
+            course_type: request.course_type,
+            instructor_id,
+            semester: request.semester,
+            created_at: now,
+            updated_at: now,
+        };
+        self.lock().courses.push(course.clone());
+        Ok(course)
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Course>, AppError> {
+        Ok(self.lock().courses.iter().find(|c| c.id == id).cloned())
+    }
+
+    async fn list(&self, filters: CourseFilter) -> Result<Paginated<Course>, AppError> {
+        let data = self.lock();
+        let items = data
+            .courses
+            .iter()
+            .filter(|c| {
+                eq(&filters.semester, &c.semester)
+                    && eq(&filters.department, &c.department)
+                    && eq(&filters.course_type, &c.course_type)
+                    && filters
+                        .instructor_id
+                        .is_none_or(|id| c.instructor_id == Some(id))
+                    && matches(filters.search.as_deref(), &[&c.title, &c.code])
+            })
+            .cloned()
+            .collect();
+        Ok(one_page(items))
+    }
+
+    async fn enroll(&self, actor: &User, course_id: Uuid) -> Result<Option<Uuid>, AppError> {
+        let mut data = self.lock();
+        if data
+            .enrollments
+            .iter()
+            .any(|e| e.student_id == actor.id && e.course_id == course_id)
+        {
+            return Ok(None);
+        }
+        let enrollment = CourseEnrollment {
+            id: Uuid::new_v4(),
+            student_id: actor.id,
+            course_id,
+            enrolled_at: Utc::now(),
+        };
+        let id = enrollment.id;
+        data.enrollments.push(enrollment);
+        Ok(Some(id))
+    }
+
+    async fn enrolled_courses(&self, student_id: Uuid) -> Result<Vec<Course>, AppError> {
+        let data = self.lock();
+        Ok(data
+            .courses
+            .iter()
+            .filter(|c| {
+                data.enrollments
+                    .iter()
+                    .any(|e| e.student_id == student_id && e.course_id == c.id)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn enrollment_count(&self, course_id: Uuid) -> Result<i64, AppError> {
+        let data = self.lock();
+        Ok(data
+            .enrollments
+            .iter()
+            .filter(|e| e.course_id == course_id)
+            .count() as i64)
+    }
+
+    async fn find_enrollment(
+        &self,
+        student_id: Uuid,
+        course_id: Uuid,
+    ) -> Result<Option<CourseEnrollment>, AppError> {
+        let data = self.lock();
+        Ok(data
+            .enrollments
+            .iter()
+            .find(|e| e.student_id == student_id && e.course_id == course_id)
+            .cloned())
+    }
+
+    async fn mark_attendance(
+        &self,
+        _actor: &User,
+        enrollment: &CourseEnrollment,
+        date: NaiveDate,
+        status: AttendanceStatus,
+        remarks: Option<String>,
+    ) -> Result<Uuid, AppError> {
+        let mut data = self.lock();
+        if let Some(log) = data
+            .attendance
+            .iter_mut()
+            .find(|l| l.enrollment_id == enrollment.id && l.date == date)
+        {
+            log.status = status;
+            log.remarks = remarks;
+            return Ok(log.id);
+        }
+        let log = AttendanceLog {
+            id: Uuid::new_v4(),
+            enrollment_id: enrollment.id,
+            date,
+            status,
+            remarks,
+            created_at: Utc::now(),
+        };
+        let id = log.id;
+        data.attendance.push(log);
+        Ok(id)
+    }
+
+    async fn attendance(&self, enrollment_id: Uuid) -> Result<Vec<AttendanceLog>, AppError> {
+        let data = self.lock();
+        let mut logs: Vec<AttendanceLog> = data
+            .attendance
+            .iter()
+            .filter(|l| l.enrollment_id == enrollment_id)
+            .cloned()
+            .collect();
+        logs.sort_by(|a, b| b.date.cmp(&a.date));
+        Ok(logs)
+    }
+
+    async fn create_resource(
+        &self,
+        actor: &User,
+        course_id: Uuid,
+        request: CreateResourceRequest,
+        is_verified: bool,
+    ) -> Result<AcademicResource, AppError> {
+        let now = Utc::now();
+        let resource = AcademicResource {
+            id: Uuid::new_v4(),
+            course_id,
+            uploaded_by: Some(actor.id),
+            title: request.title,
+            description: request.description,
+            resource_type: request.resource_type,
+            file_url: request.file_url,
+            year: request.year,
+            tags: request.tags,
+            is_verified,
+            created_at: now,
+            updated_at: now,
+        };
+        self.lock().resources.push(resource.clone());
+        Ok(resource)
+    }
+
+    async fn resources(
+        &self,
+        course_id: Uuid,
+        filters: ResourceFilter,
+    ) -> Result<Paginated<AcademicResource>, AppError> {
+        let data = self.lock();
+        let items = data
+            .resources
+            .iter()
+            .filter(|r| {
+                r.course_id == course_id
+                    && eq(&filters.resource_type, &r.resource_type)
+                    && filters.year.is_none_or(|y| r.year == Some(y))
+                    && matches(
+                        filters.search.as_deref(),
+                        &[&r.title, r.description.as_deref().unwrap_or_default()],
+                    )
+            })
+            .cloned()
+            .collect();
+        Ok(one_page(items))
+    }
+
+    async fn create_event(
+        &self,
+        actor: &User,
+        request: CreateEventRequest,
+    ) -> Result<AcademicEvent, AppError> {
+        let now = Utc::now();
+        let event = AcademicEvent {
+            id: Uuid::new_v4(),
+            course_id: request.course_id,
+            created_by: Some(actor.id),
+            title: request.title,
+            description: request.description,
+            event_type: request.event_type,
+            start_time: request.start_time,
+            end_time: request.end_time,
+            created_at: now,
+            updated_at: now,
+        };
+        self.lock().events.push(event.clone());
+        Ok(event)
+    }
+
+    async fn calendar(
+        &self,
+        user_id: Uuid,
+        _page: PageParams,
+    ) -> Result<Paginated<AcademicEventResponse>, AppError> {
+        let data = self.lock();
+        let items = data
+            .events
+            .iter()
+            .filter(|e| {
+                e.course_id.is_none_or(|course_id| {
+                    data.enrollments
+                        .iter()
+                        .any(|en| en.student_id == user_id && en.course_id == course_id)
+                })
+            })
+            .map(|e| {
+                let course = e
+                    .course_id
+                    .and_then(|id| data.courses.iter().find(|c| c.id == id));
+                AcademicEventResponse {
+                    id: e.id,
+                    title: e.title.clone(),
+                    description: e.description.clone(),
+                    event_type: e.event_type.clone(),
+                    start_time: e.start_time,
+                    end_time: e.end_time,
+                    course_code: course.map(|c| c.code.clone()),
+                    course_title: course.map(|c| c.title.clone()),
+                }
+            })
+            .collect();
+        Ok(one_page(items))
+    }
+}
+
+#[async_trait]
+impl OpportunityRepo for Memory {
+    async fn create(
+        &self,
+        actor: &User,
+        request: CreateOpportunityRequest,
+    ) -> Result<Opportunity, AppError> {
+        let now = Utc::now();
+        let opportunity = Opportunity {
+            id: Uuid::new_v4(),
+            posted_by: actor.id,
+            title: request.title,
+            description: request.description,
+            opportunity_type: request.opportunity_type,
+            department: request.department,
+            required_skills: Some(request.required_skills),
+            duration: Some(request.duration),
+            stipend: request.stipend,
+            location: Some(request.location),
+            application_deadline: request.application_deadline,
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        };
+        self.lock().opportunities.push(opportunity.clone());
+        Ok(opportunity)
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Opportunity>, AppError> {
+        Ok(self
+            .lock()
+            .opportunities
+            .iter()
+            .find(|o| o.id == id)
+            .cloned())
+    }
+
+    async fn find_many(&self, ids: &[Uuid]) -> Result<Vec<Opportunity>, AppError> {
+        let data = self.lock();
+        Ok(data
+            .opportunities
+            .iter()
+            .filter(|o| ids.contains(&o.id))
+            .cloned()
+            .collect())
+    }
+
+    async fn list_active(
+        &self,
+        filters: OpportunityFilter,
+    ) -> Result<Paginated<Opportunity>, AppError> {
+        let data = self.lock();
+        let items = data
+            .opportunities
+            .iter()
+            .filter(|o| {
+                o.is_active
+                    && eq(&filters.department, &o.department)
+                    && eq(&filters.opportunity_type, &o.opportunity_type)
+                    && eq(&filters.posted_by, &o.posted_by)
+                    && matches(filters.search.as_deref(), &[&o.title, &o.description])
+            })
+            .cloned()
+            .collect();
+        Ok(one_page(items))
+    }
+
+    async fn apply(
+        &self,
+        actor: &User,
+        opportunity_id: Uuid,
+        request: ApplyRequest,
+    ) -> Result<Option<Uuid>, AppError> {
+        let mut data = self.lock();
+        if data
+            .applications
+            .iter()
+            .any(|a| a.opportunity_id == opportunity_id && a.student_id == actor.id)
+        {
+            return Ok(None);
+        }
+        let now = Utc::now();
+        let application = Application {
+            id: Uuid::new_v4(),
+            opportunity_id,
+            student_id: actor.id,
+            resume_url: Some(request.resume_url),
+            cover_letter: request.cover_letter,
+            portfolio_url: request.portfolio_url,
+            status: ApplicationStatus::Submitted,
+            faculty_remarks: None,
+            applied_at: now,
+            updated_at: now,
+        };
+        let id = application.id;
+        data.applications.push(application);
+        Ok(Some(id))
+    }
+
+    async fn applied_to(&self, student_id: Uuid, ids: &[Uuid]) -> Result<HashSet<Uuid>, AppError> {
+        let data = self.lock();
+        Ok(data
+            .applications
+            .iter()
+            .filter(|a| a.student_id == student_id && ids.contains(&a.opportunity_id))
+            .map(|a| a.opportunity_id)
+            .collect())
+    }
+
+    async fn applications_by(&self, student_id: Uuid) -> Result<Vec<Application>, AppError> {
+        let data = self.lock();
+        Ok(data
+            .applications
+            .iter()
+            .rev()
+            .filter(|a| a.student_id == student_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn applications_for(&self, opportunity_id: Uuid) -> Result<Vec<Application>, AppError> {
+        let data = self.lock();
+        Ok(data
+            .applications
+            .iter()
+            .filter(|a| a.opportunity_id == opportunity_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_application(&self, id: Uuid) -> Result<Option<Application>, AppError> {
+        Ok(self
+            .lock()
+            .applications
+            .iter()
+            .find(|a| a.id == id)
+            .cloned())
+    }
+
+    async fn update_application_status(
+        &self,
+        _actor: &User,
+        application: &Application,
+        request: UpdateApplicationStatusRequest,
+    ) -> Result<(), AppError> {
+        let mut data = self.lock();
+        let a = data
+            .applications
+            .iter_mut()
+            .find(|a| a.id == application.id)
+            .ok_or_else(|| not_found("Application", application.id))?;
+        a.status = request.status;
+        a.faculty_remarks = request.faculty_remarks;
+        a.updated_at = Utc::now();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TaskRepo for Memory {
+    async fn create(
+        &self,
+        actor: &User,
+        request: CreateTaskRequest,
+    ) -> Result<PersonalTask, AppError> {
+        let now = Utc::now();
+        let task = PersonalTask {
+            id: Uuid::new_v4(),
+            user_id: actor.id,
+            title: request.title,
+            description: request.description,
+            status: TaskStatus::Pending,
+            priority: request.priority,
+            progress_percentage: Some(0),
+            due_date: request.due_date,
+            tags: Some(request.tags),
+            created_at: now,
+            updated_at: now,
+        };
+        self.lock().tasks.push(task.clone());
+        Ok(task)
+    }
+
+    async fn list(
+        &self,
+        user_id: Uuid,
+        _page: PageParams,
+    ) -> Result<Paginated<PersonalTask>, AppError> {
+        let data = self.lock();
+        Ok(one_page(
+            data.tasks
+                .iter()
+                .filter(|t| t.user_id == user_id)
+                .cloned()
+                .collect(),
+        ))
+    }
+
+    async fn update(
+        &self,
+        actor: &User,
+        id: Uuid,
+        request: UpdateTaskRequest,
+    ) -> Result<Option<PersonalTask>, AppError> {
+        let mut data = self.lock();
+        let Some(task) = data
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == id && t.user_id == actor.id)
+        else {
+            return Ok(None);
+        };
+        if let Some(title) = request.title {
+            task.title = title;
+        }
+        if let Some(status) = request.status {
+            task.status = status;
+        }
+        if let Some(priority) = request.priority {
+            task.priority = priority;
+        }
+        task.description = request.description.or(task.description.take());
+        task.progress_percentage = request.progress_percentage.or(task.progress_percentage);
+        task.due_date = request.due_date.or(task.due_date);
+        task.tags = request.tags.or(task.tags.take());
+        task.updated_at = Utc::now();
+        Ok(Some(task.clone()))
+    }
+
+    async fn delete(&self, actor: &User, id: Uuid) -> Result<bool, AppError> {
+        let mut data = self.lock();
+        let before = data.tasks.len();
+        data.tasks
+            .retain(|t| !(t.id == id && t.user_id == actor.id));
+        Ok(data.tasks.len() < before)
+    }
+}
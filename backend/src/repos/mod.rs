@@ -0,0 +1,242 @@
+// Data access for the grievance, academic, opportunity and task handlers, one
+// trait per domain, so handlers can be unit tested against the in-memory fakes.
+// Writes record their `audit_logs` entry in the same transaction.
+
+#[cfg(test)]
+pub mod memory;
+mod postgres;
+
+use crate::error::AppError;
+use crate::listing::PageParams;
+use crate::permissions::{Grants, Permission};
+use crate::structs::*;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub use postgres::{PgCourseRepo, PgGrievanceRepo, PgOpportunityRepo, PgTaskRepo, PgUserRepo};
+
+#[async_trait]
+pub trait UserRepo: Send + Sync {
+    async fn find(&self, id: Uuid) -> Result<Option<User>, AppError>;
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, AppError>;
+    async fn find_many(&self, ids: &[Uuid]) -> Result<Vec<User>, AppError>;
+    // Scopes the role has been granted `permission` with, see `permissions::Grants`
+    async fn permission_scopes(
+        &self,
+        role: &UserRole,
+        permission: Permission,
+    ) -> Result<Vec<PermissionScope>, AppError>;
+}
+
+// A grievance to be filed; `submitted_by` is `None` for anonymous ones
+#[derive(Debug)]
+pub struct NewGrievance {
+    pub submitted_by: Option<Uuid>,
+    pub anonymous_identifier: Option<String>,
+    pub request: CreateGrievanceRequest,
+}
+
+#[async_trait]
+pub trait GrievanceRepo: Send + Sync {
+    async fn create(&self, actor: &User, new: NewGrievance) -> Result<Grievance, AppError>;
+    async fn find(&self, id: Uuid) -> Result<Option<Grievance>, AppError>;
+    // `hide_anonymous` leaves anonymous grievances out whatever the filters say
+    async fn list(
+        &self,
+        filters: GrievanceFilters,
+        hide_anonymous: bool,
+    ) -> Result<Paginated<Grievance>, AppError>;
+    async fn record_view(&self, id: Uuid) -> Result<(), AppError>;
+    // Also appends to the grievance's status history
+    async fn update_status(
+        &self,
+        actor: &User,
+        grievance: &Grievance,
+        request: UpdateGrievanceStatusRequest,
+    ) -> Result<Grievance, AppError>;
+    async fn assign(
+        &self,
+        actor: &User,
+        id: Uuid,
+        request: AssignGrievanceRequest,
+    ) -> Result<Grievance, AppError>;
+    async fn resolve(
+        &self,
+        actor: &User,
+        grievance: &Grievance,
+        resolution_notes: String,
+    ) -> Result<Grievance, AppError>;
+    async fn delete(&self, actor: &User, grievance: &Grievance) -> Result<(), AppError>;
+    async fn add_photos(&self, actor: &User, id: Uuid, urls: &[String]) -> Result<(), AppError>;
+    // Removes the actor's upvote if there is one and adds it otherwise.
+    // Returns whether the grievance is upvoted by them afterwards.
+    async fn toggle_upvote(&self, actor: &User, id: Uuid) -> Result<bool, AppError>;
+    // Which of `ids` the user has upvoted
+    async fn upvoted_by(&self, user_id: Uuid, ids: &[Uuid]) -> Result<HashSet<Uuid>, AppError>;
+    // Rewrites `upvote_count` wherever it disagrees with the upvotes themselves,
+    // returning how many grievances were corrected
+    async fn reconcile_upvote_counts(&self) -> Result<usize, AppError>;
+    async fn history(&self, id: Uuid) -> Result<Vec<GrievanceStatusHistory>, AppError>;
+    async fn add_comment(
+        &self,
+        actor: &User,
+        id: Uuid,
+        comment: String,
+        is_internal: bool,
+    ) -> Result<GrievanceComment, AppError>;
+    async fn comments(
+        &self,
+        id: Uuid,
+        include_internal: bool,
+        page: PageParams,
+    ) -> Result<Paginated<GrievanceComment>, AppError>;
+    async fn departments(&self) -> Result<Vec<Department>, AppError>;
+}
+
+#[async_trait]
+pub trait CourseRepo: Send + Sync {
+    async fn create(
+        &self,
+        actor: &User,
+        request: CreateCourseRequest,
+        instructor_id: Option<Uuid>,
+    ) -> Result<Course, AppError>;
+    async fn find(&self, id: Uuid) -> Result<Option<Course>, AppError>;
+    async fn list(&self, filters: CourseFilter) -> Result<Paginated<Course>, AppError>;
+    // Enrolls the actor; `None` when they already are
+    async fn enroll(&self, actor: &User, course_id: Uuid) -> Result<Option<Uuid>, AppError>;
+    async fn enrolled_courses(&self, student_id: Uuid) -> Result<Vec<Course>, AppError>;
+    async fn enrollment_count(&self, course_id: Uuid) -> Result<i64, AppError>;
+    async fn find_enrollment(
+        &self,
+        student_id: Uuid,
+        course_id: Uuid,
+    ) -> Result<Option<CourseEnrollment>, AppError>;
+    // Records or replaces the enrollment's entry for `date`
+    async fn mark_attendance(
+        &self,
+        actor: &User,
+        enrollment: &CourseEnrollment,
+        date: NaiveDate,
+        status: AttendanceStatus,
+        remarks: Option<String>,
+    ) -> Result<Uuid, AppError>;
+    async fn attendance(&self, enrollment_id: Uuid) -> Result<Vec<AttendanceLog>, AppError>;
+    async fn create_resource(
+        &self,
+        actor: &User,
+        course_id: Uuid,
+        request: CreateResourceRequest,
+        is_verified: bool,
+    ) -> Result<AcademicResource, AppError>;
+    async fn resources(
+        &self,
+        course_id: Uuid,
+        filters: ResourceFilter,
+    ) -> Result<Paginated<AcademicResource>, AppError>;
+    async fn create_event(
+        &self,
+        actor: &User,
+        request: CreateEventRequest,
+    ) -> Result<AcademicEvent, AppError>;
+    // Global events plus those of the courses the user is enrolled in
+    async fn calendar(
+        &self,
+        user_id: Uuid,
+        page: PageParams,
+    ) -> Result<Paginated<AcademicEventResponse>, AppError>;
+}
+
+#[async_trait]
+pub trait OpportunityRepo: Send + Sync {
+    async fn create(
+        &self,
+        actor: &User,
+        request: CreateOpportunityRequest,
+    ) -> Result<Opportunity, AppError>;
+    async fn find(&self, id: Uuid) -> Result<Option<Opportunity>, AppError>;
+    async fn find_many(&self, ids: &[Uuid]) -> Result<Vec<Opportunity>, AppError>;
+    async fn list_active(
+        &self,
+        filters: OpportunityFilter,
+    ) -> Result<Paginated<Opportunity>, AppError>;
+    // Applies as the actor; `None` when they already have
+    async fn apply(
+        &self,
+        actor: &User,
+        opportunity_id: Uuid,
+        request: ApplyRequest,
+    ) -> Result<Option<Uuid>, AppError>;
+    // Which of `ids` the student has applied to
+    async fn applied_to(&self, student_id: Uuid, ids: &[Uuid]) -> Result<HashSet<Uuid>, AppError>;
+    async fn applications_by(&self, student_id: Uuid) -> Result<Vec<Application>, AppError>;
+    async fn applications_for(&self, opportunity_id: Uuid) -> Result<Vec<Application>, AppError>;
+    async fn find_application(&self, id: Uuid) -> Result<Option<Application>, AppError>;
+    async fn update_application_status(
+        &self,
+        actor: &User,
+        application: &Application,
+        request: UpdateApplicationStatusRequest,
+    ) -> Result<(), AppError>;
+}
+
+// Tasks are private, so every method is limited to the actor's own
+#[async_trait]
+pub trait TaskRepo: Send + Sync {
+    async fn create(
+        &self,
+        actor: &User,
+        request: CreateTaskRequest,
+    ) -> Result<PersonalTask, AppError>;
+    async fn list(
+        &self,
+        user_id: Uuid,
+        page: PageParams,
+    ) -> Result<Paginated<PersonalTask>, AppError>;
+    // `None` when the actor has no such task
+    async fn update(
+        &self,
+        actor: &User,
+        id: Uuid,
+        request: UpdateTaskRequest,
+    ) -> Result<Option<PersonalTask>, AppError>;
+    // `false` when the actor has no such task
+    async fn delete(&self, actor: &User, id: Uuid) -> Result<bool, AppError>;
+}
+
+// The repositories handlers work with, extracted as `State<Repos>`
+#[derive(Clone)]
+pub struct Repos {
+    pub users: Arc<dyn UserRepo>,
+    pub grievances: Arc<dyn GrievanceRepo>,
+    pub courses: Arc<dyn CourseRepo>,
+    pub opportunities: Arc<dyn OpportunityRepo>,
+    pub tasks: Arc<dyn TaskRepo>,
+}
+
+impl Repos {
+    pub fn postgres(pool: PgPool) -> Self {
+        Self {
+            users: Arc::new(PgUserRepo::new(pool.clone())),
+            grievances: Arc::new(PgGrievanceRepo::new(pool.clone())),
+            courses: Arc::new(PgCourseRepo::new(pool.clone())),
+            opportunities: Arc::new(PgOpportunityRepo::new(pool.clone())),
+            tasks: Arc::new(PgTaskRepo::new(pool)),
+        }
+    }
+}
+
+#[async_trait]
+impl Grants for Repos {
+    async fn scopes(
+        &self,
+        role: &UserRole,
+        permission: Permission,
+    ) -> Result<Vec<PermissionScope>, AppError> {
+        self.users.permission_scopes(role, permission).await
+    }
+}
@@ -0,0 +1,1169 @@
+use super::*;
+use crate::audit::{self, Target};
+use crate::listing::{Column, ListQuery, Sort};
+
+#[derive(Clone)]
+pub struct PgUserRepo {
+    pool: PgPool,
+}
+
+impl PgUserRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepo for PgUserRepo {
+    async fn find(&self, id: Uuid) -> Result<Option<User>, AppError> {
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(user)
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(user)
+    }
+
+    async fn find_many(&self, ids: &[Uuid]) -> Result<Vec<User>, AppError> {
+        let users = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ANY($1)")
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(users)
+    }
+
+    async fn permission_scopes(
+        &self,
+        role: &UserRole,
+        permission: Permission,
+    ) -> Result<Vec<PermissionScope>, AppError> {
+        self.pool.scopes(role, permission).await
+    }
+}
+
+// ============================================================================
+// GRIEVANCES
+// ============================================================================
+
+#[derive(Clone)]
+pub struct PgGrievanceRepo {
+    pool: PgPool,
+}
+
+impl PgGrievanceRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl GrievanceRepo for PgGrievanceRepo {
+    async fn create(&self, actor: &User, new: NewGrievance) -> Result<Grievance, AppError> {
+        let request = new.request;
+        let mut tx = self.pool.begin().await?;
+        let grievance = sqlx::query_as::<_, Grievance>(
+            r#"
+            INSERT INTO grievances (
+                submitted_by, is_anonymous, anonymous_identifier,
+                title, description, category, priority,
+                location_type, location_details
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+        )
+        .bind(new.submitted_by)
+        .bind(request.is_anonymous)
+        .bind(new.anonymous_identifier)
+        .bind(&request.title)
+        .bind(&request.description)
+        .bind(&request.category)
+        .bind(&request.priority)
+        .bind(&request.location_type)
+        .bind(&request.location_details)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "CREATE_GRIEVANCE",
+            Some(Target::Grievance(grievance.id)),
+            serde_json::json!({
+                "category": grievance.category,
+                "priority": grievance.priority,
+            }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(grievance)
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Grievance>, AppError> {
+        let grievance = sqlx::query_as::<_, Grievance>("SELECT * FROM grievances WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(grievance)
+    }
+
+    async fn list(
+        &self,
+        filters: GrievanceFilters,
+        hide_anonymous: bool,
+    ) -> Result<Paginated<Grievance>, AppError> {
+        let sort = filters.sort.unwrap_or(Sort::desc(GrievanceSort::CreatedAt));
+        let mut query = ListQuery::new("g.*", "grievances g", "g.id", sort);
+        query
+            .eq("g.status", filters.status)
+            .eq("g.category", filters.category)
+            .eq("g.priority", filters.priority)
+            .eq("g.assigned_to", filters.assigned_to)
+            .eq("g.assigned_department", filters.assigned_department)
+            .eq("g.submitted_by", filters.submitted_by);
+        if hide_anonymous {
+            query.push(" AND NOT g.is_anonymous");
+        }
+        query.search(&["g.title", "g.description"], filters.search.as_deref());
+
+        let params = PageParams {
+            cursor: filters.cursor,
+            limit: filters.limit,
+            count: filters.count,
+        };
+        query.fetch(&self.pool, params).await
+    }
+
+    async fn record_view(&self, id: Uuid) -> Result<(), AppError> {
+        sqlx::query("UPDATE grievances SET view_count = view_count + 1 WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn update_status(
+        &self,
+        actor: &User,
+        grievance: &Grievance,
+        request: UpdateGrievanceStatusRequest,
+    ) -> Result<Grievance, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let updated = sqlx::query_as::<_, Grievance>(
+            "UPDATE grievances SET status = $1 WHERE id = $2 RETURNING *",
+        )
+        .bind(&request.status)
+        .bind(grievance.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO grievance_status_history
+            (grievance_id, old_status, new_status, remarks, updated_by, updated_by_role)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(grievance.id)
+        .bind(&grievance.status)
+        .bind(&request.status)
+        .bind(&request.remarks)
+        .bind(actor.id)
+        .bind(&actor.role)
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "UPDATE_GRIEVANCE_STATUS",
+            Some(Target::Grievance(grievance.id)),
+            serde_json::json!({
+                "old_status": grievance.status,
+                "new_status": request.status,
+            }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    async fn assign(
+        &self,
+        actor: &User,
+        id: Uuid,
+        request: AssignGrievanceRequest,
+    ) -> Result<Grievance, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let updated = sqlx::query_as::<_, Grievance>(
+            "UPDATE grievances SET assigned_to = $1, assigned_department = $2 WHERE id = $3 RETURNING *",
+        )
+        .bind(request.assigned_to)
+        .bind(&request.assigned_department)
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "ASSIGN_GRIEVANCE",
+            Some(Target::Grievance(id)),
+            serde_json::json!({
+                "assigned_to": request.assigned_to,
+                "assigned_department": request.assigned_department,
+            }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    async fn resolve(
+        &self,
+        actor: &User,
+        grievance: &Grievance,
+        resolution_notes: String,
+    ) -> Result<Grievance, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let updated = sqlx::query_as::<_, Grievance>(
+            r#"
+            UPDATE grievances
+            SET status = 'resolved',
+                resolution_notes = $1,
+                resolved_at = NOW(),
+                resolved_by = $2
+            WHERE id = $3
+            RETURNING *
+            "#,
+        )
+        .bind(&resolution_notes)
+        .bind(actor.id)
+        .bind(grievance.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "RESOLVE_GRIEVANCE",
+            Some(Target::Grievance(grievance.id)),
+            serde_json::json!({ "old_status": grievance.status }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    async fn delete(&self, actor: &User, grievance: &Grievance) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM grievances WHERE id = $1")
+            .bind(grievance.id)
+            .execute(&mut *tx)
+            .await?;
+
+        // The row is gone, so keep enough to tell what was deleted
+        audit::record(
+            &mut *tx,
+            actor,
+            "DELETE_GRIEVANCE",
+            Some(Target::Grievance(grievance.id)),
+            serde_json::json!({
+                "title": grievance.title,
+                "status": grievance.status,
+                "submitted_by": grievance.submitted_by,
+            }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn add_photos(&self, actor: &User, id: Uuid, urls: &[String]) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE grievances SET photo_urls = COALESCE(photo_urls, '{}') || $1 WHERE id = $2",
+        )
+        .bind(urls)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "UPLOAD_GRIEVANCE_PHOTOS",
+            Some(Target::Grievance(id)),
+            serde_json::json!({ "photo_urls": urls }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn toggle_upvote(&self, actor: &User, id: Uuid) -> Result<bool, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        // `upvote_count` only moves when a row was actually removed or added, so
+        // concurrent toggles can't push it out of step with `grievance_upvotes`
+        let removed =
+            sqlx::query("DELETE FROM grievance_upvotes WHERE grievance_id = $1 AND user_id = $2")
+                .bind(id)
+                .bind(actor.id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected()
+                > 0;
+
+        let (delta, action) = if removed {
+            (-1, "REMOVE_UPVOTE")
+        } else {
+            let added = sqlx::query(
+                "INSERT INTO grievance_upvotes (grievance_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            )
+            .bind(id)
+            .bind(actor.id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+                > 0;
+            (i32::from(added), "UPVOTE_GRIEVANCE")
+        };
+
+        if delta != 0 {
+            sqlx::query("UPDATE grievances SET upvote_count = upvote_count + $1 WHERE id = $2")
+                .bind(delta)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+            audit::record(
+                &mut *tx,
+                actor,
+                action,
+                Some(Target::Grievance(id)),
+                serde_json::Value::Null,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(!removed)
+    }
+
+    async fn upvoted_by(&self, user_id: Uuid, ids: &[Uuid]) -> Result<HashSet<Uuid>, AppError> {
+        let upvoted = sqlx::query_scalar::<_, Uuid>(
+            "SELECT grievance_id FROM grievance_upvotes WHERE user_id = $1 AND grievance_id = ANY($2)",
+        )
+        .bind(user_id)
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(upvoted.into_iter().collect())
+    }
+
+    // Runs under REPEATABLE READ so a toggle landing mid-pass makes it fail
+    // (and be retried next time) rather than overwriting the fresh count with
+    // a stale one.
+    async fn reconcile_upvote_counts(&self) -> Result<usize, AppError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ")
+            .execute(&mut *tx)
+            .await?;
+
+        let corrected = sqlx::query_as::<_, (Uuid, Option<i32>, i32)>(
+            r#"
+            WITH actual AS (
+                SELECT g.id, g.upvote_count AS old_count, COUNT(u.user_id)::int AS new_count
+                FROM grievances g
+                LEFT JOIN grievance_upvotes u ON u.grievance_id = g.id
+                GROUP BY g.id
+            )
+            UPDATE grievances g
+            SET upvote_count = actual.new_count
+            FROM actual
+            WHERE g.id = actual.id AND g.upvote_count IS DISTINCT FROM actual.new_count
+            RETURNING g.id, actual.old_count, actual.new_count
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for (id, old_count, new_count) in &corrected {
+            audit::record(
+                &mut *tx,
+                audit::Actor::System,
+                "RECONCILE_UPVOTE_COUNT",
+                Some(Target::Grievance(*id)),
+                serde_json::json!({ "old_count": old_count, "new_count": new_count }),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(corrected.len())
+    }
+
+    async fn history(&self, id: Uuid) -> Result<Vec<GrievanceStatusHistory>, AppError> {
+        let history = sqlx::query_as::<_, GrievanceStatusHistory>(
+            "SELECT * FROM grievance_status_history WHERE grievance_id = $1 ORDER BY created_at DESC",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(history)
+    }
+
+    async fn add_comment(
+        &self,
+        actor: &User,
+        id: Uuid,
+        comment: String,
+        is_internal: bool,
+    ) -> Result<GrievanceComment, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let comment = sqlx::query_as::<_, GrievanceComment>(
+            r#"
+            INSERT INTO grievance_comments (grievance_id, user_id, comment, is_internal)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(actor.id)
+        .bind(&comment)
+        .bind(is_internal)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "ADD_COMMENT",
+            Some(Target::Comment(comment.id)),
+            serde_json::json!({ "grievance_id": id, "is_internal": is_internal }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(comment)
+    }
+
+    async fn comments(
+        &self,
+        id: Uuid,
+        include_internal: bool,
+        page: PageParams,
+    ) -> Result<Paginated<GrievanceComment>, AppError> {
+        let sort = Sort::asc(Column::new("created_at", "timestamptz"));
+        let mut query = ListQuery::new("*", "grievance_comments", "id", sort);
+        query.bind(" AND grievance_id = ", id);
+        if !include_internal {
+            query.push(" AND is_internal = false");
+        }
+        query.fetch(&self.pool, page).await
+    }
+
+    async fn departments(&self) -> Result<Vec<Department>, AppError> {
+        let departments =
+            sqlx::query_as::<_, Department>("SELECT * FROM departments ORDER BY name")
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(departments)
+    }
+}
+
+// ============================================================================
+// COURSES, ATTENDANCE, RESOURCES AND EVENTS
+// ============================================================================
+
+#[derive(Clone)]
+pub struct PgCourseRepo {
+    pool: PgPool,
+}
+
+impl PgCourseRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl CourseRepo for PgCourseRepo {
+    async fn create(
+        &self,
+        actor: &User,
+        request: CreateCourseRequest,
+        instructor_id: Option<Uuid>,
+    ) -> Result<Course, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let course = sqlx::query_as::<_, Course>(
+            r#"
+            INSERT INTO courses (
+                code, title, description, credits, department, course_type, semester, instructor_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+        )
+        .bind(&request.code)
+        .bind(&request.title)
+        .bind(&request.description)
+        .bind(request.credits)
+        .bind(&request.department)
+        .bind(&request.course_type)
+        .bind(&request.semester)
+        .bind(instructor_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "CREATE_COURSE",
+            Some(Target::Course(course.id)),
+            serde_json::json!({ "code": course.code, "instructor_id": course.instructor_id }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(course)
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Course>, AppError> {
+        let course = sqlx::query_as::<_, Course>("SELECT * FROM courses WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(course)
+    }
+
+    async fn list(&self, filters: CourseFilter) -> Result<Paginated<Course>, AppError> {
+        let sort = filters.sort.unwrap_or(Sort::asc(CourseSort::Code));
+        let mut query = ListQuery::new("*", "courses", "id", sort);
+        query
+            .eq("semester", filters.semester)
+            .eq("department", filters.department)
+            .eq("course_type", filters.course_type)
+            .eq("instructor_id", filters.instructor_id)
+            .search(&["title", "code"], filters.search.as_deref());
+
+        let params = PageParams {
+            cursor: filters.cursor,
+            limit: filters.limit,
+            count: filters.count,
+        };
+        query.fetch(&self.pool, params).await
+    }
+
+    async fn enroll(&self, actor: &User, course_id: Uuid) -> Result<Option<Uuid>, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        // Nothing comes back when already enrolled, including by a concurrent request
+        let enrollment_id: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            INSERT INTO course_enrollments (student_id, course_id) VALUES ($1, $2)
+            ON CONFLICT (student_id, course_id) DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(actor.id)
+        .bind(course_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(enrollment_id) = enrollment_id {
+            audit::record(
+                &mut *tx,
+                actor,
+                "ENROLL_COURSE",
+                Some(Target::Enrollment(enrollment_id)),
+                serde_json::json!({ "course_id": course_id }),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(enrollment_id)
+    }
+
+    async fn enrolled_courses(&self, student_id: Uuid) -> Result<Vec<Course>, AppError> {
+        let courses = sqlx::query_as::<_, Course>(
+            r#"
+            SELECT c.* FROM courses c
+            INNER JOIN course_enrollments ce ON c.id = ce.course_id
+            WHERE ce.student_id = $1
+            ORDER BY c.semester DESC, c.code ASC
+            "#,
+        )
+        .bind(student_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(courses)
+    }
+
+    async fn enrollment_count(&self, course_id: Uuid) -> Result<i64, AppError> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM course_enrollments WHERE course_id = $1")
+                .bind(course_id)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(count)
+    }
+
+    async fn find_enrollment(
+        &self,
+        student_id: Uuid,
+        course_id: Uuid,
+    ) -> Result<Option<CourseEnrollment>, AppError> {
+        let enrollment = sqlx::query_as::<_, CourseEnrollment>(
+            "SELECT * FROM course_enrollments WHERE student_id = $1 AND course_id = $2",
+        )
+        .bind(student_id)
+        .bind(course_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(enrollment)
+    }
+
+    async fn mark_attendance(
+        &self,
+        actor: &User,
+        enrollment: &CourseEnrollment,
+        date: NaiveDate,
+        status: AttendanceStatus,
+        remarks: Option<String>,
+    ) -> Result<Uuid, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let attendance_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO attendance_logs (enrollment_id, date, status, remarks) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (enrollment_id, date)
+            DO UPDATE SET status = EXCLUDED.status, remarks = EXCLUDED.remarks
+            RETURNING id
+            "#,
+        )
+        .bind(enrollment.id)
+        .bind(date)
+        .bind(&status)
+        .bind(&remarks)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "MARK_ATTENDANCE",
+            Some(Target::Attendance(attendance_id)),
+            serde_json::json!({
+                "student_id": enrollment.student_id,
+                "course_id": enrollment.course_id,
+                "date": date,
+                "status": status,
+            }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(attendance_id)
+    }
+
+    async fn attendance(&self, enrollment_id: Uuid) -> Result<Vec<AttendanceLog>, AppError> {
+        let logs = sqlx::query_as::<_, AttendanceLog>(
+            "SELECT * FROM attendance_logs WHERE enrollment_id = $1 ORDER BY date DESC",
+        )
+        .bind(enrollment_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(logs)
+    }
+
+    async fn create_resource(
+        &self,
+        actor: &User,
+        course_id: Uuid,
+        request: CreateResourceRequest,
+        is_verified: bool,
+    ) -> Result<AcademicResource, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let resource = sqlx::query_as::<_, AcademicResource>(
+            r#"
+            INSERT INTO academic_resources (
+                course_id, uploaded_by, title, description, resource_type, file_url, year, tags, is_verified
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+        )
+        .bind(course_id)
+        .bind(actor.id)
+        .bind(&request.title)
+        .bind(&request.description)
+        .bind(&request.resource_type)
+        .bind(&request.file_url)
+        .bind(request.year)
+        .bind(&request.tags)
+        .bind(is_verified)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "CREATE_RESOURCE",
+            Some(Target::AcademicResource(resource.id)),
+            serde_json::json!({ "course_id": course_id, "is_verified": is_verified }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(resource)
+    }
+
+    async fn resources(
+        &self,
+        course_id: Uuid,
+        filters: ResourceFilter,
+    ) -> Result<Paginated<AcademicResource>, AppError> {
+        let sort = filters.sort.unwrap_or(Sort::desc(ResourceSort::CreatedAt));
+        let mut query = ListQuery::new("*", "academic_resources", "id", sort);
+        query
+            .bind(" AND course_id = ", course_id)
+            .eq("resource_type", filters.resource_type)
+            .eq("year", filters.year)
+            .search(&["title", "description"], filters.search.as_deref());
+
+        let params = PageParams {
+            cursor: filters.cursor,
+            limit: filters.limit,
+            count: filters.count,
+        };
+        query.fetch(&self.pool, params).await
+    }
+
+    async fn create_event(
+        &self,
+        actor: &User,
+        request: CreateEventRequest,
+    ) -> Result<AcademicEvent, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let event = sqlx::query_as::<_, AcademicEvent>(
+            r#"
+            INSERT INTO academic_events (
+                title, description, event_type, start_time, end_time, course_id, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(&request.title)
+        .bind(&request.description)
+        .bind(&request.event_type)
+        .bind(request.start_time)
+        .bind(request.end_time)
+        .bind(request.course_id)
+        .bind(actor.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "CREATE_EVENT",
+            Some(Target::Event(event.id)),
+            serde_json::json!({ "course_id": event.course_id, "event_type": event.event_type }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(event)
+    }
+
+    async fn calendar(
+        &self,
+        user_id: Uuid,
+        page: PageParams,
+    ) -> Result<Paginated<AcademicEventResponse>, AppError> {
+        // 1. Events where course_id IS NULL (Global events like "Diwali")
+        // 2. Events where course_id matches one of the student's enrollments
+        let sort = Sort::asc(Column::new("e.start_time", "timestamptz"));
+        let mut query = ListQuery::new(
+            "e.*, c.code as course_code, c.title as course_title",
+            "academic_events e LEFT JOIN courses c ON e.course_id = c.id",
+            "e.id",
+            sort,
+        );
+        query
+            .bind(
+                " AND (e.course_id IS NULL OR e.course_id IN \
+                 (SELECT course_id FROM course_enrollments WHERE student_id = ",
+                user_id,
+            )
+            .push("))");
+
+        query.fetch(&self.pool, page).await
+    }
+}
+
+// ============================================================================
+// OPPORTUNITIES AND APPLICATIONS
+// ============================================================================
+
+#[derive(Clone)]
+pub struct PgOpportunityRepo {
+    pool: PgPool,
+}
+
+impl PgOpportunityRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OpportunityRepo for PgOpportunityRepo {
+    async fn create(
+        &self,
+        actor: &User,
+        request: CreateOpportunityRequest,
+    ) -> Result<Opportunity, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let op = sqlx::query_as::<_, Opportunity>(
+            r#"
+            INSERT INTO opportunities (
+                posted_by, title, description, opportunity_type, department,
+                required_skills, duration, stipend, location, application_deadline
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING *
+            "#,
+        )
+        .bind(actor.id)
+        .bind(&request.title)
+        .bind(&request.description)
+        .bind(&request.opportunity_type)
+        .bind(&request.department)
+        .bind(&request.required_skills)
+        .bind(&request.duration)
+        .bind(&request.stipend)
+        .bind(&request.location)
+        .bind(request.application_deadline)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "CREATE_OPPORTUNITY",
+            Some(Target::Opportunity(op.id)),
+            serde_json::json!({ "title": op.title }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(op)
+    }
+
+    async fn find(&self, id: Uuid) -> Result<Option<Opportunity>, AppError> {
+        let op = sqlx::query_as::<_, Opportunity>("SELECT * FROM opportunities WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(op)
+    }
+
+    async fn find_many(&self, ids: &[Uuid]) -> Result<Vec<Opportunity>, AppError> {
+        let ops =
+            sqlx::query_as::<_, Opportunity>("SELECT * FROM opportunities WHERE id = ANY($1)")
+                .bind(ids)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(ops)
+    }
+
+    async fn list_active(
+        &self,
+        filters: OpportunityFilter,
+    ) -> Result<Paginated<Opportunity>, AppError> {
+        let sort = filters
+            .sort
+            .unwrap_or(Sort::desc(OpportunitySort::CreatedAt));
+        let mut query = ListQuery::new("*", "opportunities", "id", sort);
+        query
+            .push(" AND is_active = true")
+            .eq("department", filters.department)
+            .eq("opportunity_type", filters.opportunity_type)
+            .eq("posted_by", filters.posted_by)
+            .search(&["title", "description"], filters.search.as_deref());
+
+        let params = PageParams {
+            cursor: filters.cursor,
+            limit: filters.limit,
+            count: filters.count,
+        };
+        query.fetch(&self.pool, params).await
+    }
+
+    async fn apply(
+        &self,
+        actor: &User,
+        opportunity_id: Uuid,
+        request: ApplyRequest,
+    ) -> Result<Option<Uuid>, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        // Nothing comes back for a duplicate
+        let application_id: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            INSERT INTO applications (opportunity_id, student_id, resume_url, cover_letter, portfolio_url)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (opportunity_id, student_id) DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(opportunity_id)
+        .bind(actor.id)
+        .bind(&request.resume_url)
+        .bind(&request.cover_letter)
+        .bind(&request.portfolio_url)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(application_id) = application_id {
+            audit::record(
+                &mut *tx,
+                actor,
+                "APPLY_OPPORTUNITY",
+                Some(Target::Application(application_id)),
+                serde_json::json!({ "opportunity_id": opportunity_id }),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(application_id)
+    }
+
+    async fn applied_to(&self, student_id: Uuid, ids: &[Uuid]) -> Result<HashSet<Uuid>, AppError> {
+        let applied = sqlx::query_scalar::<_, Uuid>(
+            "SELECT opportunity_id FROM applications WHERE student_id = $1 AND opportunity_id = ANY($2)",
+        )
+        .bind(student_id)
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(applied.into_iter().collect())
+    }
+
+    async fn applications_by(&self, student_id: Uuid) -> Result<Vec<Application>, AppError> {
+        let applications = sqlx::query_as::<_, Application>(
+            "SELECT * FROM applications WHERE student_id = $1 ORDER BY applied_at DESC",
+        )
+        .bind(student_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(applications)
+    }
+
+    async fn applications_for(&self, opportunity_id: Uuid) -> Result<Vec<Application>, AppError> {
+        let applications = sqlx::query_as::<_, Application>(
+            "SELECT * FROM applications WHERE opportunity_id = $1 ORDER BY applied_at ASC",
+        )
+        .bind(opportunity_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(applications)
+    }
+
+    async fn find_application(&self, id: Uuid) -> Result<Option<Application>, AppError> {
+        let application =
+            sqlx::query_as::<_, Application>("SELECT * FROM applications WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(application)
+    }
+
+    async fn update_application_status(
+        &self,
+        actor: &User,
+        application: &Application,
+        request: UpdateApplicationStatusRequest,
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE applications SET status = $1, faculty_remarks = $2, updated_at = NOW() WHERE id = $3",
+        )
+        .bind(&request.status)
+        .bind(&request.faculty_remarks)
+        .bind(application.id)
+        .execute(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "UPDATE_APPLICATION_STATUS",
+            Some(Target::Application(application.id)),
+            serde_json::json!({ "old_status": application.status, "new_status": request.status }),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// PERSONAL TASKS
+// ============================================================================
+
+#[derive(Clone)]
+pub struct PgTaskRepo {
+    pool: PgPool,
+}
+
+impl PgTaskRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl TaskRepo for PgTaskRepo {
+    async fn create(
+        &self,
+        actor: &User,
+        request: CreateTaskRequest,
+    ) -> Result<PersonalTask, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let task = sqlx::query_as::<_, PersonalTask>(
+            r#"
+            INSERT INTO personal_tasks (user_id, title, description, priority, due_date, tags, status, progress_percentage)
+            VALUES ($1, $2, $3, $4, $5, $6, 'pending', 0)
+            RETURNING *
+            "#,
+        )
+        .bind(actor.id)
+        .bind(request.title)
+        .bind(request.description)
+        .bind(request.priority)
+        .bind(request.due_date)
+        .bind(request.tags)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        audit::record(
+            &mut *tx,
+            actor,
+            "CREATE_TASK",
+            Some(Target::Task(task.id)),
+            serde_json::Value::Null,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    async fn list(
+        &self,
+        user_id: Uuid,
+        page: PageParams,
+    ) -> Result<Paginated<PersonalTask>, AppError> {
+        let sort = Sort::asc(Column::nullable("due_date", "timestamptz"));
+        let mut query = ListQuery::new("*", "personal_tasks", "id", sort);
+        query.bind(" AND user_id = ", user_id);
+
+        query.fetch(&self.pool, page).await
+    }
+
+    async fn update(
+        &self,
+        actor: &User,
+        id: Uuid,
+        request: UpdateTaskRequest,
+    ) -> Result<Option<PersonalTask>, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        // Fields left out of the request keep their current value
+        let task = sqlx::query_as::<_, PersonalTask>(
+            r#"
+            UPDATE personal_tasks SET
+                title = COALESCE($1, title),
+                description = COALESCE($2, description),
+                status = COALESCE($3, status),
+                priority = COALESCE($4, priority),
+                progress_percentage = COALESCE($5, progress_percentage),
+                due_date = COALESCE($6, due_date),
+                tags = COALESCE($7, tags),
+                updated_at = NOW()
+            WHERE id = $8 AND user_id = $9
+            RETURNING *
+            "#,
+        )
+        .bind(request.title)
+        .bind(request.description)
+        .bind(request.status)
+        .bind(request.priority)
+        .bind(request.progress_percentage)
+        .bind(request.due_date)
+        .bind(request.tags)
+        .bind(id)
+        .bind(actor.id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if task.is_some() {
+            audit::record(
+                &mut *tx,
+                actor,
+                "UPDATE_TASK",
+                Some(Target::Task(id)),
+                serde_json::Value::Null,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(task)
+    }
+
+    async fn delete(&self, actor: &User, id: Uuid) -> Result<bool, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let deleted = sqlx::query("DELETE FROM personal_tasks WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(actor.id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+            > 0;
+
+        if deleted {
+            audit::record(
+                &mut *tx,
+                actor,
+                "DELETE_TASK",
+                Some(Target::Task(id)),
+                serde_json::Value::Null,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(deleted)
+    }
+}
@@ -0,0 +1,178 @@
+use crate::auth::SESSION_USER_ID_KEY;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use tower_sessions::{
+    cookie::time::OffsetDateTime,
+    session::{Id, Record},
+    session_store::{self, ExpiredDeletion, SessionStore},
+};
+use uuid::Uuid;
+
+// Session store backed by the `user_sessions` table.
+// `session_token` holds the tower-sessions id, `data` the serialized session map,
+// and `user_id` mirrors the logged-in user so sessions can be looked up per user.
+#[derive(Debug, Clone)]
+pub struct PgSessionStore {
+    pool: PgPool,
+}
+
+impl PgSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Periodically sweep expired sessions; errors are logged and retried on the next tick
+    pub async fn cleanup_expired(self, period: std::time::Duration) {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.delete_expired().await {
+                tracing::error!("Failed to delete expired sessions: {}", e);
+            }
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct SessionRow {
+    data: serde_json::Value,
+    expires_at: DateTime<Utc>,
+}
+
+fn backend_error(err: sqlx::Error) -> session_store::Error {
+    session_store::Error::Backend(err.to_string())
+}
+
+fn to_chrono(date: OffsetDateTime) -> DateTime<Utc> {
+    DateTime::from_timestamp(date.unix_timestamp(), date.nanosecond()).unwrap_or_else(Utc::now)
+}
+
+fn to_offset(date: DateTime<Utc>) -> session_store::Result<OffsetDateTime> {
+    let nanos = date
+        .timestamp_nanos_opt()
+        .ok_or_else(|| session_store::Error::Decode("expires_at out of range".to_string()))?;
+
+    OffsetDateTime::from_unix_timestamp_nanos(nanos as i128)
+        .map_err(|e| session_store::Error::Decode(e.to_string()))
+}
+
+// The user id lives inside the session data; copy it into its own column
+fn session_user_id(record: &Record) -> Option<Uuid> {
+    record
+        .data
+        .get(SESSION_USER_ID_KEY)
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+}
+
+fn encode_data(record: &Record) -> session_store::Result<serde_json::Value> {
+    serde_json::to_value(&record.data).map_err(|e| session_store::Error::Encode(e.to_string()))
+}
+
+#[async_trait]
+impl SessionStore for PgSessionStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        let data = encode_data(record)?;
+
+        // Regenerate the id until it doesn't collide with an existing session
+        loop {
+            let result = sqlx::query(
+                r#"
+                INSERT INTO user_sessions (user_id, session_token, data, expires_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (session_token) DO NOTHING
+                "#,
+            )
+            .bind(session_user_id(record))
+            .bind(record.id.to_string())
+            .bind(&data)
+            .bind(to_chrono(record.expiry_date))
+            .execute(&self.pool)
+            .await
+            .map_err(backend_error)?;
+
+            if result.rows_affected() > 0 {
+                return Ok(());
+            }
+
+            record.id = Id::default();
+        }
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        // Update only: a session revoked while one of its requests was in flight must stay
+        // deleted. New sessions go through `create`.
+        sqlx::query(
+            r#"
+            UPDATE user_sessions
+            SET user_id = $1, data = $3, expires_at = $4, last_seen_at = NOW()
+            WHERE session_token = $2
+            "#,
+        )
+        .bind(session_user_id(record))
+        .bind(record.id.to_string())
+        .bind(encode_data(record)?)
+        .bind(to_chrono(record.expiry_date))
+        .execute(&self.pool)
+        .await
+        .map_err(backend_error)?;
+
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let row = sqlx::query_as::<_, SessionRow>(
+            "SELECT data, expires_at FROM user_sessions WHERE session_token = $1 AND expires_at > NOW()",
+        )
+        .bind(session_id.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(backend_error)?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some(Record {
+            id: *session_id,
+            data: serde_json::from_value(row.data)
+                .map_err(|e| session_store::Error::Decode(e.to_string()))?,
+            expiry_date: to_offset(row.expires_at)?,
+        }))
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        sqlx::query("DELETE FROM user_sessions WHERE session_token = $1")
+            .bind(session_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(backend_error)?;
+
+        Ok(())
+    }
+}
+
+// Log a user out everywhere, e.g. after suspension or a role change
+pub async fn delete_user_sessions<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM user_sessions WHERE user_id = $1")
+        .bind(user_id)
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+#[async_trait]
+impl ExpiredDeletion for PgSessionStore {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        let result = sqlx::query("DELETE FROM user_sessions WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(backend_error)?;
+
+        tracing::debug!("Deleted {} expired sessions", result.rows_affected());
+        Ok(())
+    }
+}
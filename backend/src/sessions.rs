@@ -0,0 +1,120 @@
+use crate::audit::{self, Target};
+use crate::auth::{SESSION_DEVICE_KEY, SESSION_USER_ID_KEY};
+use crate::error::AppError;
+use crate::extractors::{AuthUser, Json, Path};
+use crate::structs::*;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use tower_sessions::Session;
+use uuid::Uuid;
+
+// When a signed-in session's expiry was last pushed back, as unix seconds
+const SESSION_TOUCHED_KEY: &str = "touched_at";
+// Expiry and `last_seen_at` are refreshed at most this often rather than on every request
+const TOUCH_INTERVAL_SECS: i64 = 60 * 60;
+
+// Middleware: sessions are only written when they change, so a signed-in session
+// is marked changed once per interval to keep it from expiring while in use
+pub async fn touch(session: Session, request: Request, next: Next) -> Response {
+    if let Err(e) = touch_if_stale(&session).await {
+        tracing::warn!("Failed to refresh session expiry: {}", e);
+    }
+    next.run(request).await
+}
+
+async fn touch_if_stale(session: &Session) -> Result<(), tower_sessions::session::Error> {
+    if session.get::<Uuid>(SESSION_USER_ID_KEY).await?.is_none() {
+        return Ok(());
+    }
+
+    let now = Utc::now().timestamp();
+    let touched_at = session.get::<i64>(SESSION_TOUCHED_KEY).await?;
+    if touched_at.is_none_or(|touched_at| now - touched_at >= TOUCH_INTERVAL_SECS) {
+        session.insert(SESSION_TOUCHED_KEY, now).await?;
+    }
+    Ok(())
+}
+
+#[derive(FromRow)]
+struct SessionListRow {
+    id: Uuid,
+    session_token: String,
+    device: Option<serde_json::Value>,
+    created_at: Option<DateTime<Utc>>,
+    last_seen_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+// GET /api/me/sessions
+pub async fn get_my_sessions(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    session: Session,
+) -> Result<Json<ApiResponse<Vec<ActiveSessionResponse>>>, AppError> {
+    let current_token = session.id().map(|id| id.to_string());
+
+    let rows = sqlx::query_as::<_, SessionListRow>(
+        r#"
+        SELECT id, session_token, data -> $2 AS device, created_at, last_seen_at, expires_at
+        FROM user_sessions
+        WHERE user_id = $1 AND expires_at > NOW()
+        ORDER BY last_seen_at DESC
+        "#,
+    )
+    .bind(user.id)
+    .bind(SESSION_DEVICE_KEY)
+    .fetch_all(&pool)
+    .await?;
+
+    // The session token is the cookie value, so only the row id leaves the server
+    let sessions = rows
+        .into_iter()
+        .map(|row| ActiveSessionResponse {
+            id: row.id,
+            device: row.device.and_then(|d| serde_json::from_value(d).ok()),
+            created_at: row.created_at,
+            last_seen_at: row.last_seen_at,
+            expires_at: row.expires_at,
+            current: current_token.as_deref() == Some(row.session_token.as_str()),
+        })
+        .collect();
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(sessions),
+        message: None,
+        error: None,
+    }))
+}
+
+// DELETE /api/me/sessions/{id}
+pub async fn revoke_my_session(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    let result = sqlx::query("DELETE FROM user_sessions WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user.id)
+        .execute(&pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    audit::record(&pool, &user, "session_revoked", Some(Target::Session(id)), serde_json::Value::Null)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: None,
+        message: Some("Session revoked".to_string()),
+        error: None,
+    }))
+}
@@ -0,0 +1,57 @@
+use crate::config::AppConfig;
+use crate::identity::IdentityProvider;
+use crate::repos::Repos;
+use axum::extract::FromRef;
+use metrics_exporter_prometheus::PrometheusHandle;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+// Shared application state.
+// Handlers extract just the part they need, e.g. `State<Repos>` or `State<Arc<AppConfig>>`.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: PgPool,
+    pub repos: Repos,
+    pub config: Arc<AppConfig>,
+    // Cancelled when the server starts shutting down
+    pub shutdown: CancellationToken,
+    pub metrics: PrometheusHandle,
+    pub identity: Arc<dyn IdentityProvider>,
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Repos {
+    fn from_ref(state: &AppState) -> Self {
+        state.repos.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<AppConfig> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for CancellationToken {
+    fn from_ref(state: &AppState) -> Self {
+        state.shutdown.clone()
+    }
+}
+
+impl FromRef<AppState> for PrometheusHandle {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn IdentityProvider> {
+    fn from_ref(state: &AppState) -> Self {
+        state.identity.clone()
+    }
+}
@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use crate::listing::{Column, Sort, SortField};
+use crate::permissions::Permission;
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq)]
 #[sqlx(type_name = "user_role", rename_all = "lowercase")]
@@ -50,7 +52,7 @@ pub struct User {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct UserResponse {
     pub id: Uuid,
     pub email: String,
@@ -80,15 +82,49 @@ pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
     pub message: Option<String>,
+    // Only present on failures, see `AppError`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct GoogleUserInfo {
-    pub sub: String,
-    pub email: String,
-    pub given_name: String,
-    pub family_name: Option<String>,
-    pub picture: Option<String>,
+// One page of a list endpoint, see `listing::ListQuery`
+#[derive(Debug, Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    // Pass back as `cursor` for the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+    // Only filled in when asked for with `count=true`
+    pub total: Option<i64>,
+}
+
+impl<T> Paginated<T> {
+    pub fn map<U>(mut self, f: impl FnMut(T) -> U) -> Paginated<U> {
+        let items = std::mem::take(&mut self.items).into_iter().map(f).collect();
+        self.with_items(items)
+    }
+
+    pub fn try_map<U, E>(mut self, f: impl FnMut(T) -> Result<U, E>) -> Result<Paginated<U>, E> {
+        let items = std::mem::take(&mut self.items).into_iter().map(f).collect::<Result<_, E>>()?;
+        Ok(self.with_items(items))
+    }
+
+    // Same page position with the rows swapped for their response DTOs
+    pub fn with_items<U>(self, items: Vec<U>) -> Paginated<U> {
+        Paginated {
+            items,
+            next_cursor: self.next_cursor,
+            has_more: self.has_more,
+            total: self.total,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
 }
 
 // ============================================================================
@@ -227,7 +263,7 @@ pub struct ResolveGrievanceRequest {
     pub resolution_notes: String,
 }
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Clone, Serialize, FromRow)]
 pub struct GrievanceStatusHistory {
     pub id: Uuid,
     pub grievance_id: Uuid,
@@ -275,7 +311,7 @@ pub struct CreateCommentRequest {
     pub is_internal: bool,
 }
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Clone, Serialize, FromRow)]
 pub struct Department {
     pub id: Uuid,
     pub name: String,
@@ -286,17 +322,42 @@ pub struct Department {
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct GrievanceFilters {
     pub status: Option<GrievanceStatus>,
     pub category: Option<GrievanceCategory>,
     pub priority: Option<GrievancePriority>,
     pub assigned_to: Option<Uuid>,
     pub assigned_department: Option<String>,
-    #[allow(dead_code)]
+    // Anonymous grievances only match for their own submitter
     pub submitted_by: Option<Uuid>,
     pub search: Option<String>,
-    pub page: Option<i64>,
+    pub sort: Option<Sort<GrievanceSort>>,
+    pub cursor: Option<String>,
     pub limit: Option<i64>,
+    pub count: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrievanceSort {
+    CreatedAt,
+    UpdatedAt,
+    UpvoteCount,
+    Priority,
+    Status,
+}
+
+impl SortField for GrievanceSort {
+    fn column(self) -> Column {
+        match self {
+            GrievanceSort::CreatedAt => Column::new("g.created_at", "timestamptz"),
+            GrievanceSort::UpdatedAt => Column::new("g.updated_at", "timestamptz"),
+            GrievanceSort::UpvoteCount => Column::new("g.upvote_count", "int4"),
+            GrievanceSort::Priority => Column::new("g.priority", "grievance_priority"),
+            GrievanceSort::Status => Column::new("g.status", "grievance_status"),
+        }
+    }
 }
 
 // ============================================================================
@@ -352,29 +413,123 @@ impl From<User> for UserListResponse {
 pub struct AuditLog {
     pub id: Uuid,
     pub user_id: Option<Uuid>,
+    // The admin acting as `user_id`, see impersonation.rs
+    pub impersonator_id: Option<Uuid>,
     pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
     pub metadata: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
+    // Position in the hash chain, see audit.rs
+    pub seq: i64,
+    pub prev_hash: Option<String>,
+    pub hash: String,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Serialize)]
 pub struct AuditLogResponse {
     pub id: Uuid,
+    pub seq: i64,
+    // Ids stay set after the user is deleted, the user objects don't
+    pub user_id: Option<Uuid>,
     pub user: Option<UserResponse>,
+    pub impersonator_id: Option<Uuid>,
+    pub impersonator: Option<UserResponse>,
     pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
     pub metadata: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
+    pub hash: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogPage {
+    pub logs: Vec<AuditLogResponse>,
+    // Pass as `cursor` for the next page; `None` on the last page
+    pub next_cursor: Option<i64>,
+}
+
+// Query string for searching and exporting the audit log. Unknown fields are
+// rejected so a typo doesn't silently widen a compliance export.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuditLogFilters {
+    // Acting user
+    pub user_id: Option<Uuid>,
+    pub impersonator_id: Option<Uuid>,
+    // One action or a comma-separated list
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    // Dotted path into `metadata` (e.g. `reason`, `old_grants.0.permission`);
+    // matches entries that have it, or where it equals `metadata_value` as text
+    pub metadata_path: Option<String>,
+    pub metadata_value: Option<String>,
+    // `seq` of the last entry already seen; results continue below it
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditExportFormat {
+    Csv,
+    Ndjson,
+}
+
+// Result of walking the audit log hash chain
+#[derive(Debug, Serialize)]
+pub struct AuditVerification {
+    pub ok: bool,
+    pub checked: i64,
+    pub head_seq: Option<i64>,
+    pub head_hash: Option<String>,
+    pub problems: Vec<AuditProblem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditProblem {
+    pub seq: i64,
+    pub problem: String,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct UserFilters {
     pub role: Option<UserRole>,
     pub status: Option<UserStatus>,
+    pub department: Option<String>,
+    // Matches name or email
     pub search: Option<String>,
-    pub page: Option<i64>,
+    pub sort: Option<Sort<UserSort>>,
+    pub cursor: Option<String>,
     pub limit: Option<i64>,
+    pub count: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSort {
+    CreatedAt,
+    LastLoginAt,
+    Email,
+    FirstName,
+    LastName,
+}
+
+impl SortField for UserSort {
+    fn column(self) -> Column {
+        match self {
+            UserSort::CreatedAt => Column::new("created_at", "timestamptz"),
+            UserSort::LastLoginAt => Column::nullable("last_login_at", "timestamptz"),
+            UserSort::Email => Column::new("email", "text"),
+            UserSort::FirstName => Column::new("first_name", "text"),
+            UserSort::LastName => Column::new("last_name", "text"),
+        }
+    }
 }
 
 // ============================================================================
@@ -526,7 +681,7 @@ pub struct AcademicResourceResponse {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, FromRow)]
 pub struct AcademicEventResponse {
     pub id: Uuid,
     pub title: String,
@@ -580,10 +735,71 @@ pub struct LogAttendanceRequest {
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct CourseFilter {
     pub semester: Option<String>,
     pub department: Option<String>,
+    pub course_type: Option<CourseType>,
+    pub instructor_id: Option<Uuid>,
+    // Matches title or code
     pub search: Option<String>,
+    pub sort: Option<Sort<CourseSort>>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    pub count: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseSort {
+    Code,
+    Title,
+    Credits,
+    Semester,
+    CreatedAt,
+}
+
+impl SortField for CourseSort {
+    fn column(self) -> Column {
+        match self {
+            CourseSort::Code => Column::new("code", "text"),
+            CourseSort::Title => Column::new("title", "text"),
+            CourseSort::Credits => Column::new("credits", "int4"),
+            CourseSort::Semester => Column::new("semester", "text"),
+            CourseSort::CreatedAt => Column::new("created_at", "timestamptz"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceFilter {
+    #[serde(rename = "type")]
+    pub resource_type: Option<ResourceType>,
+    pub year: Option<i32>,
+    pub search: Option<String>,
+    pub sort: Option<Sort<ResourceSort>>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    pub count: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceSort {
+    CreatedAt,
+    Year,
+    Title,
+}
+
+impl SortField for ResourceSort {
+    fn column(self) -> Column {
+        match self {
+            ResourceSort::CreatedAt => Column::new("created_at", "timestamptz"),
+            ResourceSort::Year => Column::nullable("year", "int4"),
+            ResourceSort::Title => Column::new("title", "text"),
+        }
+    }
 }
 
 // ============================================================================
@@ -784,4 +1000,202 @@ pub struct UpdateTaskRequest {
     pub progress_percentage: Option<i32>,
     pub due_date: Option<DateTime<Utc>>,
     pub tags: Option<Vec<String>>,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpportunityFilter {
+    pub department: Option<String>,
+    #[serde(rename = "type")]
+    pub opportunity_type: Option<OpportunityType>,
+    pub posted_by: Option<Uuid>,
+    // Matches title or description
+    pub search: Option<String>,
+    pub sort: Option<Sort<OpportunitySort>>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    pub count: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpportunitySort {
+    CreatedAt,
+    ApplicationDeadline,
+    Title,
+}
+
+impl SortField for OpportunitySort {
+    fn column(self) -> Column {
+        match self {
+            OpportunitySort::CreatedAt => Column::new("created_at", "timestamptz"),
+            OpportunitySort::ApplicationDeadline => {
+                Column::nullable("application_deadline", "timestamptz")
+            }
+            OpportunitySort::Title => Column::new("title", "text"),
+        }
+    }
+}
+// ============================================================================
+// PERSONAL ACCESS TOKENS
+// ============================================================================
+
+#[derive(Debug, Clone, FromRow)]
+pub struct PersonalAccessToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_hash: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PersonalAccessTokenResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<PersonalAccessToken> for PersonalAccessTokenResponse {
+    fn from(token: PersonalAccessToken) -> Self {
+        Self {
+            id: token.id,
+            name: token.name,
+            token_prefix: token.token_prefix,
+            scopes: token.scopes,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+            revoked_at: token.revoked_at,
+            created_at: token.created_at,
+        }
+    }
+}
+
+// Returned only from creation; the plaintext token can't be retrieved again
+#[derive(Debug, Serialize)]
+pub struct CreatedTokenResponse {
+    pub token: String,
+    #[serde(flatten)]
+    pub details: PersonalAccessTokenResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    // Never expires when omitted
+    pub expires_in_days: Option<i64>,
+}
+
+// ============================================================================
+// SESSIONS
+// ============================================================================
+
+// Recorded in the session data at login
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDevice {
+    pub user_agent: Option<String>,
+    pub ip: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActiveSessionResponse {
+    pub id: Uuid,
+    pub device: Option<SessionDevice>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    // The session this request was made with
+    pub current: bool,
+}
+
+// ============================================================================
+// PERMISSIONS
+// ============================================================================
+
+// Which records a role's grant of a permission covers, see `permissions::Resource`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, PartialOrd, Ord)]
+#[sqlx(type_name = "permission_scope", rename_all = "lowercase")]
+pub enum PermissionScope {
+    #[serde(rename = "any")]
+    Any,
+    #[serde(rename = "own")]
+    Own,
+    #[serde(rename = "assigned")]
+    Assigned,
+    #[serde(rename = "unassigned")]
+    Unassigned,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionGrant {
+    pub permission: Permission,
+    pub scope: PermissionScope,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EffectivePermission {
+    pub permission: Permission,
+    pub scopes: Vec<PermissionScope>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PermissionInfo {
+    pub permission: Permission,
+    pub description: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RolePermissionsResponse {
+    pub role: UserRole,
+    pub grants: Vec<PermissionGrant>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PermissionsOverviewResponse {
+    pub permissions: Vec<PermissionInfo>,
+    pub roles: Vec<RolePermissionsResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRolePermissionsRequest {
+    pub grants: Vec<PermissionGrant>,
+}
+
+// ============================================================================
+// IMPERSONATION
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct StartImpersonationRequest {
+    // Why the admin needs to see the app as this user, kept in the audit log
+    pub reason: String,
+    pub duration_minutes: Option<i64>,
+    #[serde(default)]
+    pub allow_writes: bool,
+}
+
+// Shown by the frontend as a banner while an admin is viewing as someone else
+#[derive(Debug, Serialize)]
+pub struct ImpersonationInfo {
+    pub admin: UserResponse,
+    pub expires_at: DateTime<Utc>,
+    pub allow_writes: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CurrentUserResponse {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    pub impersonation: Option<ImpersonationInfo>,
+}
@@ -1,11 +1,249 @@
+use crate::config::{LogFormat, TelemetryConfig};
+use axum::{
+    Router,
+    extract::Request,
+    http::{HeaderName, Response},
+    middleware::{self, Next},
+};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+use std::time::Duration;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::Span;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use uuid::Uuid;
+
+// Set on every request (or kept from the caller) and echoed back on the response
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    // The id of the request being handled, see `scope_request_id`
+    static REQUEST_ID: String;
+}
+
+// Flushes buffered spans to the collector when dropped at the end of `main`
+pub struct TelemetryGuard {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+pub fn init_telemetry(config: &TelemetryConfig) -> anyhow::Result<TelemetryGuard> {
+    let (text_layer, json_layer) = match config.log_format {
+        LogFormat::Text => (Some(tracing_subscriber::fmt::layer()), None),
+        LogFormat::Json => (
+            None,
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(false),
+            ),
+        ),
+    };
+
+    let provider = match &config.otlp_endpoint {
+        Some(endpoint) => Some(otlp_provider(endpoint, &config.service_name)?),
+        None => None,
+    };
 
-pub fn init_telemetry() {
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| format!("{}=debug", env!("CARGO_CRATE_NAME")).into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(text_layer)
+        .with(json_layer)
+        .with(provider.as_ref().map(|provider| {
+            tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_CRATE_NAME")))
+        }))
         .init();
+
+    if let Some(endpoint) = &config.otlp_endpoint {
+        tracing::info!("Exporting traces to {}", endpoint);
+    }
+
+    Ok(TelemetryGuard { provider })
+}
+
+fn otlp_provider(endpoint: &str, service_name: &str) -> anyhow::Result<SdkTracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint))
+        .build()?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
+}
+
+// ============================================================================
+// PER-REQUEST SPANS
+// ============================================================================
+
+// Wraps the whole app. Outermost first on the way in: drop a malformed incoming id,
+// assign one if missing, open the request span, echo the id back, expose it to handlers.
+pub fn with_request_ids(router: Router) -> Router {
+    router
+        .layer(middleware::from_fn(scope_request_id))
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(make_request_span)
+                .on_response(on_response),
+        )
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+        .layer(middleware::from_fn(discard_invalid_request_id))
+}
+
+// Middleware: a caller's id ends up in every log line, so only a UUID is kept.
+// Anything else is removed and replaced by a generated one.
+async fn discard_invalid_request_id(mut request: Request, next: Next) -> axum::response::Response {
+    let valid = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| Uuid::try_parse(value).is_ok());
+    if !valid {
+        request.headers_mut().remove(&REQUEST_ID_HEADER);
+    }
+    next.run(request).await
+}
+
+// One span per request carrying the request id, so every log line inside a handler can be
+// correlated. `route` is filled in once routing has matched, see `monitoring::track_http`.
+pub fn make_request_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        request_id = %request_id,
+        route = tracing::field::Empty,
+        otel.kind = "server",
+    )
+}
+
+// Middleware: makes the request id available to code without access to the request,
+// such as error responses, through `current_request_id`
+pub async fn scope_request_id(request: Request, next: Next) -> axum::response::Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    REQUEST_ID.scope(request_id, next.run(request)).await
+}
+
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID
+        .try_with(|request_id| request_id.clone())
+        .ok()
+        .filter(|request_id| !request_id.is_empty())
+}
+
+pub fn on_response<B>(response: &Response<B>, latency: Duration, _span: &Span) {
+    tracing::info!(
+        status = response.status().as_u16(),
+        latency_ms = latency.as_millis() as u64,
+        "request finished"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get};
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+    use tracing::field::{Field, Visit};
+    use tracing_subscriber::{Layer, layer::Context};
+
+    // Collects the `request_id` field of every span opened
+    struct SpanRequestIds(Arc<Mutex<Vec<String>>>);
+
+    impl<S: tracing::Subscriber> Layer<S> for SpanRequestIds {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: Context<'_, S>,
+        ) {
+            struct RequestId(Option<String>);
+            impl Visit for RequestId {
+                fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                    if field.name() == "request_id" {
+                        self.0 = Some(format!("{:?}", value));
+                    }
+                }
+            }
+
+            let mut visitor = RequestId(None);
+            attrs.record(&mut visitor);
+            if let Some(request_id) = visitor.0 {
+                self.0.lock().unwrap().push(request_id);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn request_ids_reach_the_response_and_the_span() {
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let _subscriber = tracing_subscriber::registry()
+            .with(SpanRequestIds(spans.clone()))
+            .set_default();
+        let app = with_request_ids(Router::new().route("/", get(|| async {})));
+
+        let valid = "5f0c8a9e-3d6b-4a51-9b7e-2c1d0e4f6a88".to_string();
+        let oversized = "a".repeat(10_000);
+        for (sent, kept) in [
+            (Some(&valid), true),
+            (Some(&"forged\tid".to_string()), false),
+            (Some(&oversized), false),
+            (None, false),
+        ] {
+            let mut request = Request::builder().uri("/");
+            if let Some(sent) = sent {
+                request = request.header(REQUEST_ID_HEADER, sent.as_str());
+            }
+            let response = app
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+
+            let echoed = response.headers()[&REQUEST_ID_HEADER].to_str().unwrap();
+            if kept {
+                assert_eq!(Some(echoed), sent.map(String::as_str));
+            } else {
+                assert!(Uuid::try_parse(echoed).is_ok(), "{:?} is not a UUID", echoed);
+                assert_ne!(Some(echoed), sent.map(String::as_str));
+            }
+            assert_eq!(spans.lock().unwrap().pop().as_deref(), Some(echoed));
+        }
+    }
 }
@@ -0,0 +1,427 @@
+// Test harness: the router from `app.rs` against a freshly migrated database
+// of its own (created from TEST_DATABASE_URL or DATABASE_URL, dropped with the
+// app), one seeded user per role and a local stand-in for Cloudinary. Tests
+// using it are `#[ignore]`d, run them with `cargo test -- --include-ignored`.
+
+use crate::app::{self, App};
+use crate::config::AppConfig;
+use crate::migrate;
+use crate::structs::{User, UserRole};
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{Multipart, State, connect_info::MockConnectInfo},
+    routing::post,
+};
+use http::{Method, Request, Response, StatusCode, header};
+use serde_json::{Value, json};
+use sqlx::{Connection, PgConnection, PgPool, postgres::PgPoolOptions};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+const CLOUD_NAME: &str = "aegis-test";
+const MULTIPART_BOUNDARY: &str = "aegis-test-boundary";
+// Enough for a handful of requests in flight while keeping parallel tests under max_connections
+const POOL_SIZE: u32 = 4;
+
+pub struct TestApp {
+    router: Router,
+    pub pool: PgPool,
+    pub config: Arc<AppConfig>,
+    pub cloudinary: MockCloudinary,
+    users: Vec<User>,
+    // Last, so the pool is dropped before the database goes
+    database: TestDatabase,
+}
+
+impl TestApp {
+    pub async fn spawn() -> Self {
+        let admin_url = std::env::var("TEST_DATABASE_URL")
+            .or_else(|_| std::env::var("DATABASE_URL"))
+            .expect("set TEST_DATABASE_URL or DATABASE_URL to run the database tests");
+
+        Self::start(&admin_url)
+            .await
+            .expect("failed to start the test app")
+    }
+
+    async fn start(admin_url: &str) -> anyhow::Result<Self> {
+        let database = TestDatabase::create(admin_url).await?;
+        let pool = PgPoolOptions::new()
+            .max_connections(POOL_SIZE)
+            .connect(&database.url)
+            .await?;
+        migrate::MIGRATOR.run(&pool).await?;
+
+        let mut users = Vec::new();
+        for role in [
+            UserRole::Student,
+            UserRole::Faculty,
+            UserRole::Authority,
+            UserRole::Admin,
+        ] {
+            users.push(seed_user(&pool, role).await?);
+        }
+
+        let cloudinary = MockCloudinary::start().await?;
+        let config = AppConfig::from_vars(&[
+            ("APP_ENV", "development"),
+            ("AUTH_PROVIDER", "dev"),
+            ("DATABASE_URL", &database.url),
+            ("RATE_LIMIT_ENABLED", "false"),
+            ("CLOUDINARY_CLOUD_NAME", CLOUD_NAME),
+            ("CLOUDINARY_API_KEY", "test-key"),
+            ("CLOUDINARY_API_SECRET", "test-secret"),
+            ("CLOUDINARY_API_URL", &cloudinary.url),
+        ])?;
+        let config = Arc::new(config);
+
+        let App { router, .. } = app::build_app(config.clone(), pool.clone())?;
+        // Stands in for the peer address that `main.rs` gets from the listener
+        let router = router.layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 0))));
+
+        Ok(Self {
+            router,
+            pool,
+            config,
+            cloudinary,
+            users,
+            database,
+        })
+    }
+
+    // The seeded user with this role
+    pub fn user(&self, role: UserRole) -> &User {
+        self.users
+            .iter()
+            .find(|user| user.role == role)
+            .expect("every role is seeded")
+    }
+
+    // A client without a session
+    pub fn anonymous(&self) -> Client {
+        Client {
+            router: self.router.clone(),
+            cookie: Mutex::new(None),
+        }
+    }
+
+    // Signs in as the seeded user through the dev identity provider, the same
+    // redirects a browser would follow
+    pub async fn login_as(&self, role: UserRole) -> Client {
+        let client = self.anonymous();
+        let email = &self.user(role).email;
+
+        let response = client
+            .send(
+                Method::GET,
+                &format!("/auth/login?login_hint={}", urlencoding::encode(email)),
+                Body::empty(),
+                None,
+            )
+            .await;
+        let callback = redirect_location(&response).expect("login redirects to the callback");
+
+        let response = client
+            .send(Method::GET, &callback, Body::empty(), None)
+            .await;
+        let landing = redirect_location(&response).expect("callback redirects to the frontend");
+        assert!(
+            !landing.contains("/auth-error"),
+            "login as {} failed: {}",
+            email,
+            landing
+        );
+
+        client
+    }
+}
+
+fn redirect_location(response: &Response<Body>) -> Option<String> {
+    if !response.status().is_redirection() {
+        return None;
+    }
+    response
+        .headers()
+        .get(header::LOCATION)?
+        .to_str()
+        .ok()
+        .map(str::to_string)
+}
+
+async fn seed_user(pool: &PgPool, role: UserRole) -> anyhow::Result<User> {
+    let name = format!("{:?}", role).to_lowercase();
+    let domain = match role {
+        UserRole::Student => "students.iitmandi.ac.in",
+        _ => "iitmandi.ac.in",
+    };
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (email, google_id, role, first_name, last_name)
+        VALUES ($1, $2, $3, $4, 'Test')
+        RETURNING *
+        "#,
+    )
+    .bind(format!("{}@{}", name, domain))
+    .bind(format!("test-{}", name))
+    .bind(&role)
+    .bind(&name)
+    .fetch_one(pool)
+    .await?;
+    Ok(user)
+}
+
+// ============================================================================
+// HTTP CLIENT
+// ============================================================================
+
+// Sends requests straight to the router, carrying the session cookie along
+pub struct Client {
+    router: Router,
+    cookie: Mutex<Option<String>>,
+}
+
+impl Client {
+    pub async fn get(&self, path: &str) -> (StatusCode, Value) {
+        self.json(Method::GET, path, None).await
+    }
+
+    pub async fn post(&self, path: &str, body: Value) -> (StatusCode, Value) {
+        self.json(Method::POST, path, Some(body)).await
+    }
+
+    pub async fn put(&self, path: &str, body: Value) -> (StatusCode, Value) {
+        self.json(Method::PUT, path, Some(body)).await
+    }
+
+    pub async fn delete(&self, path: &str) -> (StatusCode, Value) {
+        self.json(Method::DELETE, path, None).await
+    }
+
+    // Posts each file as a `field` part of a multipart form
+    pub async fn upload(&self, path: &str, field: &str, files: &[&[u8]]) -> (StatusCode, Value) {
+        let mut body = Vec::new();
+        for (index, file) in files.iter().enumerate() {
+            body.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}.jpg\"\r\nContent-Type: image/jpeg\r\n\r\n",
+                    MULTIPART_BOUNDARY, field, index
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(file);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", MULTIPART_BOUNDARY).as_bytes());
+
+        let content_type = format!("multipart/form-data; boundary={}", MULTIPART_BOUNDARY);
+        let response = self
+            .send(Method::POST, path, Body::from(body), Some(&content_type))
+            .await;
+        read_json(response).await
+    }
+
+    async fn json(&self, method: Method, path: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let response = match body {
+            Some(body) => {
+                self.send(
+                    method,
+                    path,
+                    Body::from(body.to_string()),
+                    Some("application/json"),
+                )
+                .await
+            }
+            None => self.send(method, path, Body::empty(), None).await,
+        };
+        read_json(response).await
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Body,
+        content_type: Option<&str>,
+    ) -> Response<Body> {
+        let mut request = Request::builder().method(method).uri(path);
+        if let Some(content_type) = content_type {
+            request = request.header(header::CONTENT_TYPE, content_type);
+        }
+        if let Some(cookie) = self.cookie.lock().unwrap().as_deref() {
+            request = request.header(header::COOKIE, cookie);
+        }
+
+        let response = self
+            .router
+            .clone()
+            .oneshot(request.body(body).expect("valid request"))
+            .await
+            .expect("the router is infallible");
+
+        // Only the session cookie is ever set; keep just its name=value
+        if let Some(set_cookie) = response
+            .headers()
+            .get(header::SET_COOKIE)
+            .and_then(|value| value.to_str().ok())
+        {
+            let pair = set_cookie.split(';').next().unwrap_or_default();
+            *self.cookie.lock().unwrap() = Some(pair.to_string());
+        }
+
+        response
+    }
+}
+
+// Empty bodies come back as `Null`, anything that isn't JSON as a string
+async fn read_json(response: Response<Body>) -> (StatusCode, Value) {
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("readable body");
+
+    let value = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+    };
+    (status, value)
+}
+
+// ============================================================================
+// DATABASE
+// ============================================================================
+
+struct TestDatabase {
+    admin_url: String,
+    name: String,
+    url: String,
+}
+
+impl TestDatabase {
+    async fn create(admin_url: &str) -> anyhow::Result<Self> {
+        let name = format!("aegis_test_{}", Uuid::new_v4().simple());
+
+        let mut admin = PgConnection::connect(admin_url).await?;
+        sqlx::query(&format!("CREATE DATABASE {}", name))
+            .execute(&mut admin)
+            .await?;
+        admin.close().await?;
+
+        let mut url = reqwest::Url::parse(admin_url)?;
+        url.set_path(&name);
+
+        Ok(Self {
+            admin_url: admin_url.to_string(),
+            name,
+            url: url.to_string(),
+        })
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        let admin_url = self.admin_url.clone();
+        // FORCE disconnects whatever the pool still has open
+        let statement = format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", self.name);
+
+        // Runs inside the test's runtime, which can't be blocked on, so use a
+        // runtime of our own on another thread
+        let dropped = std::thread::spawn(move || -> anyhow::Result<()> {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?
+                .block_on(async {
+                    let mut admin = PgConnection::connect(&admin_url).await?;
+                    sqlx::query(&statement).execute(&mut admin).await?;
+                    admin.close().await?;
+                    Ok(())
+                })
+        })
+        .join();
+
+        // Panicking here would abort a test that is already failing
+        match dropped {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => tracing::error!("Failed to drop test database {}: {}", self.name, err),
+            Err(_) => tracing::error!("Failed to drop test database {}", self.name),
+        }
+    }
+}
+
+// ============================================================================
+// CLOUDINARY
+// ============================================================================
+
+// Accepts signed uploads the way Cloudinary does and remembers their public ids
+pub struct MockCloudinary {
+    url: String,
+    uploads: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockCloudinary {
+    async fn start() -> anyhow::Result<Self> {
+        let uploads = Arc::new(Mutex::new(Vec::new()));
+        let router = Router::new()
+            .route("/v1_1/{cloud}/image/upload", post(mock_upload))
+            .with_state(uploads.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        Ok(Self { url, uploads })
+    }
+
+    // Public ids of the images uploaded so far, oldest first
+    pub fn uploads(&self) -> Vec<String> {
+        self.uploads.lock().unwrap().clone()
+    }
+}
+
+async fn mock_upload(
+    State(uploads): State<Arc<Mutex<Vec<String>>>>,
+    mut multipart: Multipart,
+) -> Result<Json<Value>, StatusCode> {
+    let mut public_id = None;
+    let mut signed = false;
+    let mut has_file = false;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?
+    {
+        match field.name() {
+            Some("public_id") => {
+                public_id = Some(field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?)
+            }
+            Some("signature") => signed = true,
+            Some("file") => has_file = true,
+            _ => {}
+        }
+    }
+
+    if !signed {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    if !has_file {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let public_id = public_id.unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+    uploads.lock().unwrap().push(public_id.clone());
+
+    Ok(Json(json!({
+        "public_id": public_id,
+        "secure_url": format!(
+            "https://res.cloudinary.com/{}/image/upload/{}.jpg",
+            CLOUD_NAME, public_id
+        ),
+    })))
+}
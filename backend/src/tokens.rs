@@ -0,0 +1,272 @@
+use crate::audit::{self, Actor, Target};
+use crate::auth::random_token;
+use crate::error::AppError;
+use crate::extractors::{AuthUser, Json, Path};
+use crate::structs::*;
+use axum::{
+    extract::State,
+    http::{HeaderMap, Method, header},
+};
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const TOKEN_PREFIX: &str = "aegis_pat_";
+// Characters of the token kept in plaintext for listings
+const DISPLAY_PREFIX_LEN: usize = TOKEN_PREFIX.len() + 6;
+const MAX_TOKEN_LIFETIME_DAYS: i64 = 365;
+
+// A token acts as its owner (role checks still apply) but only within the API areas its
+// scopes cover. Managing tokens themselves needs a browser session.
+pub const SCOPES: [&str; 11] = [
+    "profile:read",
+    "grievances:read",
+    "grievances:write",
+    "courses:read",
+    "courses:write",
+    "opportunities:read",
+    "opportunities:write",
+    "tasks:read",
+    "tasks:write",
+    "admin:read",
+    "admin:write",
+];
+
+// Scope a token needs for a request: the API area plus read (GET) or write.
+// `None` means the endpoint only accepts a browser session.
+pub fn required_scope(method: &Method, path: &str) -> Option<String> {
+    let access = if method == Method::GET { "read" } else { "write" };
+    let area = if path == "/auth/me" {
+        "profile"
+    } else {
+        match path.strip_prefix("/api/")?.split('/').next()? {
+            "grievances" | "departments" => "grievances",
+            "courses" | "attendance" | "events" => "courses",
+            "opportunities" | "applications" => "opportunities",
+            "tasks" => "tasks",
+            "admin" => "admin",
+            _ => return None,
+        }
+    };
+    Some(format!("{}:{}", area, access))
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+// The token from an `Authorization: Bearer` header, if there is one
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+// Owner of a live token, without the scope check and audit entry of `authenticate`.
+// Rate limiting uses it to count token requests against the account.
+pub async fn owner(pool: &PgPool, token: &str) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT user_id FROM personal_access_tokens
+        WHERE token_hash = $1
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await
+}
+
+// Resolve a bearer token to its owner, checking it covers this request.
+// Every attempt with a live token is written to audit_logs.
+pub async fn authenticate(
+    pool: &PgPool,
+    token: &str,
+    method: &Method,
+    path: &str,
+) -> Result<User, AppError> {
+    let token = sqlx::query_as::<_, PersonalAccessToken>(
+        r#"
+        SELECT * FROM personal_access_tokens
+        WHERE token_hash = $1
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await?
+    .ok_or(AppError::Unauthorized)?;
+
+    let scope = required_scope(method, path);
+    let allowed = scope.as_ref().is_some_and(|s| token.scopes.contains(s));
+
+    sqlx::query("UPDATE personal_access_tokens SET last_used_at = NOW() WHERE id = $1")
+        .bind(token.id)
+        .execute(pool)
+        .await?;
+    audit::record(
+        pool,
+        Actor::User(token.user_id),
+        "token_used",
+        Some(Target::Token(token.id)),
+        serde_json::json!({
+            "method": method.as_str(),
+            "path": path,
+            "scope": scope,
+            "allowed": allowed,
+        }),
+    )
+    .await?;
+
+    if !allowed {
+        tracing::warn!("Token {} lacks scope {:?} for {} {}", token.id, scope, method, path);
+        return Err(AppError::Forbidden);
+    }
+
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(token.user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::Unauthorized)
+}
+
+// ============================================================================
+// TOKEN MANAGEMENT
+// ============================================================================
+
+// GET /api/tokens/scopes
+pub async fn get_scopes() -> Json<ApiResponse<Vec<&'static str>>> {
+    Json(ApiResponse {
+        success: true,
+        data: Some(SCOPES.to_vec()),
+        message: None,
+        error: None,
+    })
+}
+
+// POST /api/tokens
+pub async fn create_token(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Json(payload): Json<CreateTokenRequest>,
+) -> Result<Json<ApiResponse<CreatedTokenResponse>>, AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(AppError::BadRequest("Token name must be 1-100 characters".to_string()));
+    }
+
+    let mut scopes = payload.scopes;
+    scopes.sort();
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(AppError::BadRequest("At least one scope is required".to_string()));
+    }
+    if let Some(unknown) = scopes.iter().find(|s| !SCOPES.contains(&s.as_str())) {
+        return Err(AppError::BadRequest(format!("Unknown scope {}", unknown)));
+    }
+
+    let expires_at = match payload.expires_in_days {
+        Some(days) if (1..=MAX_TOKEN_LIFETIME_DAYS).contains(&days) => {
+            Some(Utc::now() + Duration::days(days))
+        }
+        Some(_) => {
+            return Err(AppError::BadRequest(format!(
+                "expires_in_days must be between 1 and {}",
+                MAX_TOKEN_LIFETIME_DAYS
+            )));
+        }
+        None => None,
+    };
+
+    let plaintext = format!("{}{}", TOKEN_PREFIX, random_token());
+
+    let token = sqlx::query_as::<_, PersonalAccessToken>(
+        r#"
+        INSERT INTO personal_access_tokens (user_id, name, token_hash, token_prefix, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(user.id)
+    .bind(name)
+    .bind(hash_token(&plaintext))
+    .bind(&plaintext[..DISPLAY_PREFIX_LEN])
+    .bind(&scopes)
+    .bind(expires_at)
+    .fetch_one(&pool)
+    .await?;
+
+    audit::record(
+        &pool,
+        &user,
+        "token_created",
+        Some(Target::Token(token.id)),
+        serde_json::json!({ "name": token.name, "scopes": token.scopes }),
+    )
+    .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(CreatedTokenResponse {
+            token: plaintext,
+            details: token.into(),
+        }),
+        message: Some("Token created. Copy it now, it won't be shown again".to_string()),
+        error: None,
+    }))
+}
+
+// GET /api/tokens
+pub async fn get_my_tokens(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+) -> Result<Json<ApiResponse<Vec<PersonalAccessTokenResponse>>>, AppError> {
+    let tokens = sqlx::query_as::<_, PersonalAccessToken>(
+        "SELECT * FROM personal_access_tokens WHERE user_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(user.id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(tokens.into_iter().map(Into::into).collect()),
+        message: None,
+        error: None,
+    }))
+}
+
+// DELETE /api/tokens/{id}
+pub async fn revoke_token(
+    State(pool): State<PgPool>,
+    AuthUser(user): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<PersonalAccessTokenResponse>>, AppError> {
+    let token = sqlx::query_as::<_, PersonalAccessToken>(
+        r#"
+        UPDATE personal_access_tokens SET revoked_at = NOW()
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(user.id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    audit::record(&pool, &user, "token_revoked", Some(Target::Token(token.id)), serde_json::Value::Null)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(token.into()),
+        message: Some("Token revoked".to_string()),
+        error: None,
+    }))
+}
@@ -1,5 +1,5 @@
 import { PUBLIC_BACKEND_URL } from '$env/static/public';
-import type { ApiResponse, UserResponse } from '$lib/types';
+import type { ApiResponse, Paginated, UserResponse } from '$lib/types';
 
 // Check authentication status
 export async function checkAuth(): Promise<ApiResponse<UserResponse>> {
@@ -135,6 +135,19 @@ const api = {
         }
         return response.json();
     },
+    // Every item of a paginated list, following next_cursor until the last page
+    async getAll<T = any>(path: string): Promise<T[]> {
+        const separator = path.includes('?') ? '&' : '?';
+        const items: T[] = [];
+        let cursor: string | null = null;
+        do {
+            const query = cursor === null ? '' : `${separator}cursor=${encodeURIComponent(cursor)}`;
+            const res: ApiResponse<Paginated<T>> = await api.get(`${path}${query}`);
+            items.push(...(res.data?.items || []));
+            cursor = res.data?.has_more ? res.data.next_cursor : null;
+        } while (cursor !== null);
+        return items;
+    },
 };
 
 export default api;
@@ -39,10 +39,22 @@ export interface SystemStats {
 
 export interface AuditLogResponse {
     id: string;
+    seq: number;
+    user_id: string | null;
     user: UserResponse | null;
+    impersonator_id: string | null;
+    impersonator: UserResponse | null;
     action: string;
+    target_type: string | null;
+    target_id: string | null;
     metadata: any | null;
     created_at: string;
+    hash: string;
+}
+
+export interface AuditLogPage {
+    logs: AuditLogResponse[];
+    next_cursor: number | null;
 }
 
 // Matches Rust 'GrievanceStatusHistoryResponse'
@@ -103,10 +115,24 @@ export interface Department {
     created_at: string;
 }
 
+export interface ApiError {
+    code: string;
+    details?: Record<string, unknown>;
+}
+
 export interface ApiResponse<T> {
     success: boolean;
     data: T; // Rust usually sends data or throws error
     message: string | null;
+    error?: ApiError; // Present when success is false
+}
+
+// One page of a list endpoint; pass next_cursor back as ?cursor= for the next
+export interface Paginated<T> {
+    items: T[];
+    next_cursor: string | null;
+    has_more: boolean;
+    total: number | null; // Only with ?count=true
 }
 
 export type User = UserResponse;